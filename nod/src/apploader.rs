@@ -0,0 +1,309 @@
+//! Apploader types
+
+use std::{fmt, fs, mem::size_of, path::Path};
+
+use sha1::{Digest, Sha1};
+use zerocopy::FromBytes;
+
+use crate::{ApploaderHeader, Error, PartitionMeta, Result, ResultContext};
+
+/// An apploader build date, parsed from the `YYYY/MM/DD` string in the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ApploaderDate {
+    /// Year (e.g. 2004)
+    pub year: u16,
+    /// Month (1-12)
+    pub month: u8,
+    /// Day of month (1-31)
+    pub day: u8,
+}
+
+impl ApploaderDate {
+    /// Parses a date in the `YYYY/MM/DD` format.
+    ///
+    /// Returns `None` if the string is malformed or the date is out of range.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.trim().splitn(3, '/');
+        let year = parts.next()?;
+        let month = parts.next()?;
+        let day = parts.next()?;
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return None;
+        }
+        let date =
+            Self { year: year.parse().ok()?, month: month.parse().ok()?, day: day.parse().ok()? };
+        (date.month >= 1 && date.month <= 12 && date.day >= 1 && date.day <= date.days_in_month())
+            .then_some(date)
+    }
+
+    fn days_in_month(&self) -> u8 {
+        match self.month {
+            4 | 6 | 9 | 11 => 30,
+            2 if self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0) => 29,
+            2 => 28,
+            _ => 31,
+        }
+    }
+}
+
+impl fmt::Display for ApploaderDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}/{:02}/{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// A view into an apploader, with the code and trailer separated.
+///
+/// # Examples
+///
+/// ```no_run
+/// use nod::{Disc, PartitionKind};
+///
+/// fn main() -> nod::Result<()> {
+///     let disc = Disc::new("path/to/file.iso")?;
+///     let mut partition = disc.open_partition_kind(PartitionKind::Data)?;
+///     let meta = partition.meta()?;
+///     let apploader = meta.apploader()?;
+///     if let Some(date) = apploader.date() {
+///         println!("Apploader built {}", date);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Apploader<'a> {
+    /// The apploader header.
+    pub header: &'a ApploaderHeader,
+    /// The apploader code, loaded at 0x81200000.
+    pub code: &'a [u8],
+    /// The apploader trailer.
+    pub trailer: &'a [u8],
+}
+
+impl<'a> Apploader<'a> {
+    /// The address the apploader code is loaded to.
+    pub const LOAD_ADDRESS: u32 = 0x81200000;
+
+    /// Creates a new apploader view, checking that the code and trailer fit within the buffer.
+    pub fn new(data: &'a [u8]) -> Result<Self, String> {
+        let Some(header) = ApploaderHeader::ref_from_prefix(data) else {
+            return Err(format!("Apploader: invalid header ({:#X} bytes)", data.len()));
+        };
+        let code_start = size_of::<ApploaderHeader>() as u64;
+        let code_end = code_start + header.size.get() as u64;
+        let trailer_end = code_end + header.trailer_size.get() as u64;
+        if trailer_end > data.len() as u64 {
+            return Err(format!(
+                "Apploader: code and trailer ({:#X} + {:#X} bytes) exceed apploader size {:#X}",
+                header.size.get(),
+                header.trailer_size.get(),
+                data.len() - size_of::<ApploaderHeader>()
+            ));
+        }
+        Ok(Self {
+            header,
+            code: &data[code_start as usize..code_end as usize],
+            trailer: &data[code_end as usize..trailer_end as usize],
+        })
+    }
+
+    /// The build date as a string.
+    pub fn date_str(&self) -> Option<&'a str> { self.header.date_str() }
+
+    /// The parsed build date.
+    pub fn date(&self) -> Option<ApploaderDate> { self.date_str().and_then(ApploaderDate::parse) }
+
+    /// The entry point address.
+    pub fn entry_point(&self) -> u32 { self.header.entry_point.get() }
+
+    /// Whether the entry point lies within the loaded apploader code.
+    pub fn entry_point_in_code(&self) -> bool {
+        self.entry_point()
+            .checked_sub(Self::LOAD_ADDRESS)
+            .is_some_and(|offset| (offset as usize) < self.code.len())
+    }
+
+    /// SHA-1 hash of the apploader code and trailer.
+    pub fn hash(&self) -> [u8; 20] {
+        let mut hasher = Sha1::new();
+        hasher.update(self.code);
+        hasher.update(self.trailer);
+        hasher.finalize().into()
+    }
+}
+
+/// The kind of SDK apploader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApploaderVariant {
+    /// The standard apploader shipped on retail discs.
+    Retail,
+    /// The debug apploader used on development and prototype discs.
+    Debug,
+}
+
+impl fmt::Display for ApploaderVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Retail => write!(f, "Retail"),
+            Self::Debug => write!(f, "Debug"),
+        }
+    }
+}
+
+/// A known apploader, identified by [`Apploader::hash`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnownApploader {
+    /// SHA-1 hash of the apploader code and trailer.
+    pub hash: [u8; 20],
+    /// The kind of apploader.
+    pub variant: ApploaderVariant,
+    /// A description, such as the SDK release.
+    pub name: String,
+}
+
+/// A table of known apploaders, used to recognise the standard retail and debug variants.
+///
+/// No hashes are built in: tables are loaded from lists of verified apploader dumps (see
+/// [`load_str`](Self::load_str)). `nodtool info` prints the hash of each apploader.
+///
+/// # Examples
+///
+/// ```no_run
+/// use nod::{Disc, KnownApploaders, PartitionKind};
+///
+/// fn main() -> nod::Result<()> {
+///     let mut known = KnownApploaders::default();
+///     known.load_file("path/to/apploaders.txt")?;
+///     let disc = Disc::new("path/to/file.iso")?;
+///     let meta = disc.open_partition_kind(PartitionKind::Data)?.meta()?;
+///     match known.identify(&meta.apploader()?) {
+///         Some(known) => println!("{} apploader ({})", known.variant, known.name),
+///         None => println!("Unknown apploader"),
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KnownApploaders {
+    entries: Vec<KnownApploader>,
+}
+
+impl KnownApploaders {
+    /// Adds an apploader to the table, replacing any entry with the same hash.
+    pub fn insert(&mut self, apploader: KnownApploader) {
+        match self.entries.iter_mut().find(|e| e.hash == apploader.hash) {
+            Some(entry) => *entry = apploader,
+            None => self.entries.push(apploader),
+        }
+    }
+
+    /// The known apploaders.
+    pub fn entries(&self) -> &[KnownApploader] { &self.entries }
+
+    /// Finds the entry matching the apploader's hash.
+    pub fn identify(&self, apploader: &Apploader) -> Option<&KnownApploader> {
+        let hash = apploader.hash();
+        self.entries.iter().find(|e| e.hash == hash)
+    }
+
+    /// Loads known apploaders from a text file. (See [`load_str`](Self::load_str))
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Reading apploaders file {}", path.display()))?;
+        self.load_str(&text)
+            .map_err(|e| Error::Other(format!("Loading apploaders file {}: {}", path.display(), e)))
+    }
+
+    /// Loads known apploaders from text.
+    ///
+    /// Each line has the form `sha1 variant [name]`, where `variant` is `retail` or `debug`.
+    /// Empty lines and lines starting with `#` or `;` are ignored.
+    pub fn load_str(&mut self, text: &str) -> Result<(), String> {
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let mut parts = line.splitn(3, char::is_whitespace);
+            let (Some(hash), Some(variant)) = (parts.next(), parts.next()) else {
+                return Err(format!("line {}: expected `sha1 variant [name]`", line_num + 1));
+            };
+            let mut apploader = KnownApploader {
+                hash: [0; 20],
+                variant: ApploaderVariant::Retail,
+                name: parts.next().unwrap_or_default().trim().to_string(),
+            };
+            base16ct::mixed::decode(hash, &mut apploader.hash)
+                .ok()
+                .filter(|h| h.len() == 20)
+                .ok_or_else(|| format!("line {}: invalid SHA-1 hash", line_num + 1))?;
+            apploader.variant = if variant.eq_ignore_ascii_case("retail") {
+                ApploaderVariant::Retail
+            } else if variant.eq_ignore_ascii_case("debug") {
+                ApploaderVariant::Debug
+            } else {
+                return Err(format!("line {}: unknown variant {:?}", line_num + 1, variant));
+            };
+            self.insert(apploader);
+        }
+        Ok(())
+    }
+}
+
+impl PartitionMeta {
+    /// A view into the apploader, with the code and trailer separated.
+    pub fn apploader(&self) -> Result<Apploader<'_>, String> { Apploader::new(&self.raw_apploader) }
+}
+
+#[cfg(test)]
+mod tests {
+    use zerocopy::{big_endian::U32, AsBytes, FromZeroes};
+
+    use super::*;
+
+    #[test]
+    fn test_apploader() {
+        let mut header = ApploaderHeader::new_zeroed();
+        header.date[..10].copy_from_slice(b"2004/02/29");
+        header.entry_point = U32::new(0x81200004);
+        header.size = U32::new(8);
+        header.trailer_size = U32::new(4);
+        let mut data = header.as_bytes().to_vec();
+        data.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+
+        let apploader = Apploader::new(&data).unwrap();
+        assert_eq!(apploader.code, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(apploader.trailer, &[9, 10, 11, 12]);
+        assert_eq!(apploader.date(), Some(ApploaderDate { year: 2004, month: 2, day: 29 }));
+        assert!(apploader.entry_point_in_code());
+
+        assert!(Apploader::new(&data[..data.len() - 1]).is_err());
+
+        let mut known = KnownApploaders::default();
+        assert!(known.identify(&apploader).is_none());
+        let hash = base16ct::HexDisplay(&apploader.hash()).to_string();
+        known
+            .load_str(&format!(
+                "# Verified apploaders\n\
+                 {} debug Test SDK\n\
+                 {} retail\n",
+                hash,
+                "00".repeat(20)
+            ))
+            .unwrap();
+        assert_eq!(known.entries().len(), 2);
+        assert_eq!(
+            known.identify(&apploader),
+            Some(&KnownApploader {
+                hash: apploader.hash(),
+                variant: ApploaderVariant::Debug,
+                name: "Test SDK".to_string(),
+            })
+        );
+        assert!(known.load_str(&format!("{} release", hash)).is_err());
+        assert!(known.load_str("0011 retail").is_err());
+        assert_eq!(ApploaderDate::parse("2003/02/29"), None);
+        assert_eq!(ApploaderDate::parse("2003/2/28"), None);
+    }
+}
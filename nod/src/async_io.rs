@@ -0,0 +1,350 @@
+//! Async (tokio) adapters for disc images and partitions
+
+use std::{
+    cmp::min,
+    future::Future,
+    io,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncSeek, ReadBuf},
+    task::{spawn_blocking, JoinError, JoinHandle},
+};
+
+use crate::{
+    disc::SECTOR_SIZE, fst::NodeKind, streams::OwnedWindowedReadStream, Disc, DiscHeader, DiscMeta,
+    Error, Node, OpenOptions, PartitionBase, PartitionInfo, PartitionKind, PartitionMeta, Result,
+    ResultContext,
+};
+
+/// Maximum number of bytes read by a single blocking task.
+const MAX_READ_SIZE: usize = 0x100000;
+
+/// Runs a blocking closure on tokio's blocking thread pool.
+async fn run_blocking<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    spawn_blocking(f).await.map_err(join_error).context("Running blocking task")?
+}
+
+fn join_error(e: JoinError) -> io::Error { io::Error::new(io::ErrorKind::Other, e) }
+
+fn lost_stream_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "Stream lost after a failed read task")
+}
+
+/// An open disc image for use from async code.
+///
+/// All disc I/O is performed on tokio's blocking thread pool using the regular
+/// [`Disc`] readers. Dropping a future or stream cancels it: any blocking read already in
+/// progress runs to completion in the background and its result is discarded.
+///
+/// # Examples
+///
+/// ```no_run
+/// use nod::{AsyncDisc, PartitionKind};
+/// use tokio::io::AsyncReadExt;
+///
+/// # async fn run() -> nod::Result<()> {
+/// let disc = AsyncDisc::new("path/to/file.rvz").await?;
+/// let partition = disc.open_partition_kind(PartitionKind::Data).await?;
+/// let meta = partition.meta().await?;
+/// let fst = meta.fst()?;
+/// if let Some((_, node)) = fst.find("/MP3/Worlds.txt") {
+///     let mut s = String::new();
+///     partition.open_file(node)?.read_to_string(&mut s).await.expect("Failed to read file");
+///     println!("{}", s);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AsyncDisc {
+    disc: Arc<Disc>,
+}
+
+impl AsyncDisc {
+    /// Opens a disc image from a file path.
+    pub async fn new<P: AsRef<Path>>(path: P) -> Result<AsyncDisc> {
+        Self::new_with_options(path, &OpenOptions::default()).await
+    }
+
+    /// Opens a disc image from a file path with custom options.
+    pub async fn new_with_options<P: AsRef<Path>>(
+        path: P,
+        options: &OpenOptions,
+    ) -> Result<AsyncDisc> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+        let options = options.clone();
+        let disc = run_blocking(move || Disc::new_with_options(path, &options)).await?;
+        Ok(Self { disc: Arc::new(disc) })
+    }
+
+    /// Wraps an already open disc image.
+    pub fn from_disc(disc: Disc) -> AsyncDisc { Self { disc: Arc::new(disc) } }
+
+    /// The disc's primary header.
+    pub fn header(&self) -> &DiscHeader { self.disc.header() }
+
+    /// Returns extra metadata included in the disc file format, if any.
+    pub fn meta(&self) -> DiscMeta { self.disc.meta() }
+
+    /// The disc's size in bytes, or an estimate if not stored by the format.
+    pub fn disc_size(&self) -> u64 { self.disc.disc_size() }
+
+    /// A list of Wii partitions on the disc.
+    ///
+    /// **GameCube**: This will return an empty slice.
+    pub fn partitions(&self) -> &[PartitionInfo] { self.disc.partitions() }
+
+    /// Opens a decrypted partition for the specified partition index.
+    ///
+    /// **GameCube**: `index` must always be 0.
+    pub async fn open_partition(&self, index: usize) -> Result<AsyncPartition> {
+        let disc = self.disc.clone();
+        let partition = run_blocking(move || disc.open_partition(index)).await?;
+        Ok(AsyncPartition { partition, is_wii: self.header().is_wii() })
+    }
+
+    /// Opens a decrypted partition for the first partition matching the specified kind.
+    ///
+    /// **GameCube**: `kind` must always be [`PartitionKind::Data`].
+    pub async fn open_partition_kind(&self, kind: PartitionKind) -> Result<AsyncPartition> {
+        let disc = self.disc.clone();
+        let partition = run_blocking(move || disc.open_partition_kind(kind)).await?;
+        Ok(AsyncPartition { partition, is_wii: self.header().is_wii() })
+    }
+}
+
+/// An open disc partition for use from async code.
+///
+/// Each stream opened from the partition reads independently.
+#[derive(Clone)]
+pub struct AsyncPartition {
+    partition: Box<dyn PartitionBase>,
+    is_wii: bool,
+}
+
+impl AsyncPartition {
+    /// Reads the partition header and file system table.
+    pub async fn meta(&self) -> Result<Box<PartitionMeta>> {
+        let mut partition = self.partition.clone();
+        run_blocking(move || partition.meta()).await
+    }
+
+    /// Opens a read stream over the entire (decrypted) partition data.
+    pub fn open_stream(&self) -> AsyncReadStream<Box<dyn PartitionBase>> {
+        AsyncReadStream::new(self.partition.clone())
+    }
+
+    /// Opens a read stream for the specified file system node.
+    pub fn open_file(
+        &self,
+        node: &Node,
+    ) -> Result<AsyncReadStream<OwnedWindowedReadStream<Box<dyn PartitionBase>>>> {
+        if node.kind() != NodeKind::File {
+            return Err(Error::Other("Node is not a file".to_string()));
+        }
+        let stream = OwnedWindowedReadStream::new(
+            self.partition.clone(),
+            node.offset(self.is_wii),
+            node.length(),
+        )
+        .context("Opening file stream")?;
+        Ok(AsyncReadStream::new(stream))
+    }
+
+    /// The ideal size for buffered reads from this partition.
+    pub fn ideal_buffer_size(&self) -> usize { self.partition.ideal_buffer_size() }
+
+    /// The size of the partition read stream in bytes, if known.
+    pub fn data_size(&self) -> Option<u64> { self.partition.data_size() }
+}
+
+enum State<S> {
+    Idle(Option<S>),
+    Busy(JoinHandle<(S, io::Result<Vec<u8>>)>),
+}
+
+/// An [`AsyncRead`] + [`AsyncSeek`] adapter over a blocking read stream.
+///
+/// Reads are performed on tokio's blocking thread pool. Seeking only updates the position
+/// of the underlying stream and is performed inline.
+pub struct AsyncReadStream<S> {
+    state: State<S>,
+    /// Data already read from the underlying stream, ahead of the logical position.
+    buf: Vec<u8>,
+    buf_pos: usize,
+    seek: Option<SeekFrom>,
+}
+
+impl<S> AsyncReadStream<S>
+where S: Read + Seek + Send + 'static
+{
+    /// Creates a new async adapter over a blocking read stream.
+    pub fn new(stream: S) -> Self {
+        Self { state: State::Idle(Some(stream)), buf: Vec::new(), buf_pos: 0, seek: None }
+    }
+
+    fn remaining(&self) -> usize { self.buf.len() - self.buf_pos }
+
+    /// Waits for an in-flight read, if any, to complete.
+    fn poll_idle(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let State::Busy(handle) = &mut self.state {
+            let (stream, result) = ready!(Pin::new(handle).poll(cx)).map_err(join_error)?;
+            self.state = State::Idle(Some(stream));
+            self.buf_pos = 0;
+            self.buf = result?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn stream(&mut self) -> io::Result<&mut S> {
+        match &mut self.state {
+            State::Idle(Some(stream)) => Ok(stream),
+            _ => Err(lost_stream_error()),
+        }
+    }
+}
+
+fn read_full<S>(stream: &mut S, buf: &mut [u8]) -> io::Result<usize>
+where S: Read {
+    let mut total = 0;
+    while total < buf.len() {
+        match stream.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(n) => total += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(total)
+}
+
+impl<S> AsyncRead for AsyncReadStream<S>
+where S: Read + Seek + Send + Unpin + 'static
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.remaining() > 0 {
+                let len = min(buf.remaining(), this.remaining());
+                buf.put_slice(&this.buf[this.buf_pos..this.buf_pos + len]);
+                this.buf_pos += len;
+                return Poll::Ready(Ok(()));
+            }
+            match &mut this.state {
+                State::Idle(stream) => {
+                    let Some(mut stream) = stream.take() else {
+                        return Poll::Ready(Err(lost_stream_error()));
+                    };
+                    let len = buf.remaining().clamp(SECTOR_SIZE, MAX_READ_SIZE);
+                    let mut data = std::mem::take(&mut this.buf);
+                    this.buf_pos = 0;
+                    this.state = State::Busy(spawn_blocking(move || {
+                        data.resize(len, 0);
+                        let result = read_full(&mut stream, &mut data).map(|n| {
+                            data.truncate(n);
+                            data
+                        });
+                        (stream, result)
+                    }));
+                }
+                State::Busy(_) => {
+                    ready!(this.poll_idle(cx))?;
+                    if this.buf.is_empty() {
+                        // End of stream
+                        return Poll::Ready(Ok(()));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<S> AsyncSeek for AsyncReadStream<S>
+where S: Read + Seek + Send + Unpin + 'static
+{
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+        if this.seek.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Other seek operation is already in progress",
+            ));
+        }
+        this.seek = Some(position);
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        let result = ready!(this.poll_idle(cx));
+        let seek = this.seek.take();
+        result?;
+        let remaining = this.remaining() as u64;
+        let stream = this.stream()?;
+        let pos = match seek {
+            Some(pos) => {
+                // The underlying stream is ahead of the logical position by any buffered data
+                let pos = match pos {
+                    SeekFrom::Current(v) => SeekFrom::Current(v - remaining as i64),
+                    v => v,
+                };
+                let result = stream.seek(pos)?;
+                this.buf.clear();
+                this.buf_pos = 0;
+                result
+            }
+            None => stream.stream_position()? - remaining,
+        };
+        Poll::Ready(Ok(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    use super::*;
+
+    fn test_data() -> Vec<u8> { (0..0x123456u32).map(|v| (v % 251) as u8).collect() }
+
+    #[tokio::test]
+    async fn test_read_to_end() {
+        let data = test_data();
+        let mut stream = AsyncReadStream::new(Cursor::new(data.clone()));
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).await.unwrap();
+        assert!(out == data);
+    }
+
+    #[tokio::test]
+    async fn test_seek_with_buffered_data() {
+        let data = test_data();
+        let mut stream = AsyncReadStream::new(Cursor::new(data.clone()));
+        let mut buf = [0u8; 16];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(stream.stream_position().await.unwrap(), 16);
+        assert_eq!(stream.seek(SeekFrom::Current(16)).await.unwrap(), 32);
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, data[32..48]);
+        assert_eq!(stream.seek(SeekFrom::End(-16)).await.unwrap(), data.len() as u64 - 16);
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, data[data.len() - 16..]);
+        assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,235 @@
+//! GameCube banner (opening.bnr) types
+
+use std::{fmt, io::Read, mem::size_of};
+
+use encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+use zerocopy::{FromBytes, FromZeroes};
+
+use crate::{
+    static_assert, util::image::decode_rgb5a3, PartitionBase, PartitionMeta, Region, Result,
+    ResultContext,
+};
+
+/// Banner image width in pixels.
+pub const BANNER_WIDTH: usize = 96;
+/// Banner image height in pixels.
+pub const BANNER_HEIGHT: usize = 32;
+/// Banner image size in bytes. (RGB5A3)
+const BANNER_IMAGE_SIZE: usize = BANNER_WIDTH * BANNER_HEIGHT * 2;
+/// Offset of the image data in the banner.
+const BANNER_IMAGE_OFFSET: usize = 0x20;
+
+/// The banner variant, determined by the magic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BannerKind {
+    /// `BNR1`: A single set of strings. (NTSC-U, NTSC-J)
+    Bnr1,
+    /// `BNR2`: Six sets of strings, one per [`BannerLanguage`]. (PAL)
+    Bnr2,
+}
+
+impl BannerKind {
+    /// The number of string sets in the banner.
+    pub fn num_languages(self) -> usize {
+        match self {
+            Self::Bnr1 => 1,
+            Self::Bnr2 => 6,
+        }
+    }
+}
+
+impl fmt::Display for BannerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bnr1 => write!(f, "BNR1"),
+            Self::Bnr2 => write!(f, "BNR2"),
+        }
+    }
+}
+
+/// Banner string language, in BNR2 order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BannerLanguage {
+    /// English
+    English,
+    /// German
+    German,
+    /// French
+    French,
+    /// Spanish
+    Spanish,
+    /// Italian
+    Italian,
+    /// Dutch
+    Dutch,
+}
+
+impl BannerLanguage {
+    /// All languages, in BNR2 order.
+    pub const ALL: [BannerLanguage; 6] =
+        [Self::English, Self::German, Self::French, Self::Spanish, Self::Italian, Self::Dutch];
+}
+
+impl fmt::Display for BannerLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::English => write!(f, "English"),
+            Self::German => write!(f, "German"),
+            Self::French => write!(f, "French"),
+            Self::Spanish => write!(f, "Spanish"),
+            Self::Italian => write!(f, "Italian"),
+            Self::Dutch => write!(f, "Dutch"),
+        }
+    }
+}
+
+#[derive(FromBytes, FromZeroes)]
+#[repr(C)]
+struct RawBannerText {
+    short_name: [u8; 0x20],
+    short_maker: [u8; 0x20],
+    long_name: [u8; 0x40],
+    long_maker: [u8; 0x40],
+    description: [u8; 0x80],
+}
+
+static_assert!(size_of::<RawBannerText>() == 0x140);
+
+/// A set of decoded banner strings.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BannerText {
+    /// Short game name
+    pub short_name: String,
+    /// Short developer name
+    pub short_maker: String,
+    /// Full game title
+    pub long_name: String,
+    /// Full developer name
+    pub long_maker: String,
+    /// Game description, may contain line breaks
+    pub description: String,
+}
+
+/// A parsed GameCube banner. (opening.bnr)
+///
+/// # Examples
+///
+/// ```no_run
+/// use nod::{Banner, BannerLanguage, Disc};
+///
+/// fn main() -> nod::Result<()> {
+///     let disc = Disc::new("path/to/file.iso")?;
+///     let mut partition = disc.open_partition(0)?;
+///     let meta = partition.meta()?;
+///     if let Some(banner) = Banner::read(partition.as_mut(), &meta)? {
+///         if let Some(text) = banner.text(BannerLanguage::English) {
+///             println!("{}", text.long_name);
+///         }
+///         let rgba = banner.to_rgba8();
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Banner {
+    /// The banner variant.
+    pub kind: BannerKind,
+    /// Raw RGB5A3 image data, [`BANNER_WIDTH`] x [`BANNER_HEIGHT`] in 4x4 tiles.
+    pub image: Box<[u8]>,
+    /// Decoded strings, one set per language. (See [`BannerKind::num_languages`])
+    pub texts: Vec<BannerText>,
+}
+
+impl Banner {
+    /// Parses a banner.
+    ///
+    /// Strings are decoded as Shift-JIS if `japanese` is set, otherwise as Windows-1252.
+    pub fn new(data: &[u8], japanese: bool) -> Result<Self, String> {
+        let kind = match data.get(..4) {
+            Some(b"BNR1") => BannerKind::Bnr1,
+            Some(b"BNR2") => BannerKind::Bnr2,
+            Some(magic) => return Err(format!("Banner: invalid magic {:02X?}", magic)),
+            None => return Err("Banner: too small".to_string()),
+        };
+        let text_offset = BANNER_IMAGE_OFFSET + BANNER_IMAGE_SIZE;
+        let raw_texts = data
+            .get(text_offset..)
+            .and_then(|d| RawBannerText::slice_from_prefix(d, kind.num_languages()))
+            .map(|(texts, _)| texts)
+            .ok_or_else(|| format!("Banner: {} too small ({:#X} bytes)", kind, data.len()))?;
+        let decode = |raw: &[u8]| {
+            let len = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+            let encoding = if japanese { SHIFT_JIS } else { WINDOWS_1252 };
+            encoding.decode_without_bom_handling(&raw[..len]).0.into_owned()
+        };
+        let texts = raw_texts
+            .iter()
+            .map(|raw| BannerText {
+                short_name: decode(&raw.short_name),
+                short_maker: decode(&raw.short_maker),
+                long_name: decode(&raw.long_name),
+                long_maker: decode(&raw.long_maker),
+                description: decode(&raw.description),
+            })
+            .collect();
+        Ok(Self { kind, image: data[BANNER_IMAGE_OFFSET..text_offset].into(), texts })
+    }
+
+    /// Finds `opening.bnr` in the partition's file system and parses it.
+    ///
+    /// Strings are decoded as Shift-JIS for NTSC-J discs (detected from the game ID),
+    /// otherwise as Windows-1252. Returns `None` if the file doesn't exist.
+    pub fn read(partition: &mut dyn PartitionBase, meta: &PartitionMeta) -> Result<Option<Self>> {
+        let fst = meta.fst()?;
+        let Some((_, node)) = fst.find("opening.bnr") else {
+            return Ok(None);
+        };
+        let mut data = Vec::with_capacity(node.length() as usize);
+        partition
+            .open_file(node)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .context("Reading opening.bnr")?;
+        let japanese = Region::from_game_id(&meta.header().game_id) == Some(Region::NtscJ);
+        Ok(Some(Self::new(&data, japanese)?))
+    }
+
+    /// The strings for a language.
+    ///
+    /// BNR1 banners only contain one set of strings, which is returned for every language.
+    pub fn text(&self, language: BannerLanguage) -> Option<&BannerText> {
+        match self.kind {
+            BannerKind::Bnr1 => self.texts.first(),
+            BannerKind::Bnr2 => self.texts.get(language as usize),
+        }
+    }
+
+    /// Decodes the image to RGBA8, [`BANNER_WIDTH`] x [`BANNER_HEIGHT`] pixels.
+    pub fn to_rgba8(&self) -> Vec<u8> { decode_rgb5a3(&self.image, BANNER_WIDTH, BANNER_HEIGHT) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_banner() {
+        let mut data = b"BNR1".to_vec();
+        data.resize(BANNER_IMAGE_OFFSET, 0);
+        data.resize(BANNER_IMAGE_OFFSET + BANNER_IMAGE_SIZE, 0xFF);
+        let mut text = [0u8; 0x140];
+        text[..5].copy_from_slice(b"Pok\xE9m");
+        text[0x20..0x23].copy_from_slice(b"\x83\x41!");
+        data.extend_from_slice(&text);
+
+        let banner = Banner::new(&data, false).unwrap();
+        assert_eq!(banner.kind, BannerKind::Bnr1);
+        assert_eq!(banner.text(BannerLanguage::Dutch).unwrap().short_name, "Pokém");
+        assert_eq!(banner.to_rgba8().len(), BANNER_WIDTH * BANNER_HEIGHT * 4);
+        assert!(banner.to_rgba8().iter().all(|&b| b == 0xFF));
+        let banner = Banner::new(&data, true).unwrap();
+        assert_eq!(banner.texts[0].short_maker, "ア!");
+
+        data[3] = b'2';
+        assert!(Banner::new(&data, false).is_err());
+    }
+}
@@ -0,0 +1,519 @@
+//! Certificate chain parsing and signature verification
+
+use std::{ffi::CStr, fmt, mem::size_of};
+
+use sha1::{Digest, Sha1};
+
+use crate::{disc::wii::Ticket, util::rsa::verify_pkcs1_sha1, KeyStore, PartitionMeta, Tmd};
+
+/// The type of a signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureType {
+    /// RSA-4096 with SHA-1
+    Rsa4096,
+    /// RSA-2048 with SHA-1
+    Rsa2048,
+    /// ECC (sect233r1) with SHA-1
+    Ecc,
+}
+
+impl SignatureType {
+    /// Parses the signature type field. (0x00010000-0x00010002)
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0x10000 => Some(Self::Rsa4096),
+            0x10001 => Some(Self::Rsa2048),
+            0x10002 => Some(Self::Ecc),
+            _ => None,
+        }
+    }
+
+    /// The size of the signature in bytes.
+    pub fn signature_size(self) -> usize {
+        match self {
+            Self::Rsa4096 => 0x200,
+            Self::Rsa2048 => 0x100,
+            Self::Ecc => 0x3C,
+        }
+    }
+
+    /// The size of the signature block in bytes, including the type field and padding.
+    ///
+    /// Signed data (starting with the issuer) follows the signature block.
+    pub fn block_size(self) -> usize {
+        match self {
+            Self::Rsa4096 => 0x240,
+            Self::Rsa2048 => 0x140,
+            Self::Ecc => 0x80,
+        }
+    }
+}
+
+impl fmt::Display for SignatureType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rsa4096 => write!(f, "RSA-4096"),
+            Self::Rsa2048 => write!(f, "RSA-2048"),
+            Self::Ecc => write!(f, "ECC"),
+        }
+    }
+}
+
+/// A public key from a certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublicKey<'a> {
+    /// RSA public key. (RSA-2048 or RSA-4096)
+    Rsa {
+        /// Big-endian modulus
+        modulus: &'a [u8],
+        /// Public exponent
+        exponent: u32,
+    },
+    /// ECC public key. (sect233r1)
+    Ecc(&'a [u8]),
+}
+
+impl fmt::Display for PublicKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rsa { modulus, .. } => write!(f, "RSA-{}", modulus.len() * 8),
+            Self::Ecc(_) => write!(f, "ECC"),
+        }
+    }
+}
+
+/// A certificate from a certificate chain.
+#[derive(Debug, Clone)]
+pub struct Certificate<'a> {
+    /// The signature type.
+    pub sig_type: SignatureType,
+    /// The signature over [`signed_data`](Self::signed_data).
+    pub signature: &'a [u8],
+    /// The issuer of the certificate. (e.g. Root-CA00000001)
+    pub issuer: &'a str,
+    /// The name of the certificate. (e.g. CP00000004)
+    pub name: &'a str,
+    /// Key ID
+    pub key_id: u32,
+    /// The public key.
+    pub key: PublicKey<'a>,
+    /// The signed portion of the certificate, starting with the issuer.
+    pub signed_data: &'a [u8],
+}
+
+impl Certificate<'_> {
+    /// The full name used by data signed with this certificate's key.
+    /// (e.g. Root-CA00000001-CP00000004)
+    pub fn full_name(&self) -> String { format!("{}-{}", self.issuer, self.name) }
+}
+
+/// The result of verifying a signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// The signature is valid.
+    Valid,
+    /// The signature is zeroed and the SHA-1 hash of the signed data starts with 0x00.
+    ///
+    /// Such data passes the signature check of early IOS versions (the "Trucha" bug), and
+    /// indicates that the data was modified after signing.
+    Fakesigned,
+    /// The signature is invalid.
+    Invalid,
+    /// The issuer certificate is not in the chain, so the signature could not be checked.
+    MissingIssuer,
+}
+
+impl fmt::Display for SignatureStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Valid => write!(f, "Valid"),
+            Self::Fakesigned => write!(f, "Fakesigned"),
+            Self::Invalid => write!(f, "Invalid"),
+            Self::MissingIssuer => write!(f, "Missing issuer"),
+        }
+    }
+}
+
+/// A certificate chain. (cert.bin)
+///
+/// Disc partitions contain the CA certificate (signed by Root), and the CP and XS certificates
+/// (signed by the CA) used to sign the TMD and ticket respectively.
+#[derive(Debug, Clone)]
+pub struct CertChain<'a> {
+    /// The certificates, in chain order.
+    pub certs: Vec<Certificate<'a>>,
+}
+
+impl<'a> CertChain<'a> {
+    /// Parses a certificate chain.
+    pub fn new(mut data: &'a [u8]) -> Result<Self, String> {
+        let mut certs = Vec::new();
+        // Trailing zero padding is allowed
+        while data.iter().any(|&b| b != 0) {
+            let (cert, remain) =
+                parse_cert(data).map_err(|e| format!("Certificate {}: {}", certs.len(), e))?;
+            certs.push(cert);
+            data = remain;
+        }
+        Ok(Self { certs })
+    }
+
+    /// Finds the certificate used for an issuer string. (e.g. Root-CA00000001-XS00000003)
+    pub fn find(&self, issuer: &str) -> Option<&Certificate<'a>> {
+        self.certs.iter().find(|c| {
+            issuer
+                .strip_prefix(c.issuer)
+                .and_then(|s| s.strip_prefix('-'))
+                .is_some_and(|name| name == c.name)
+        })
+    }
+
+    /// Verifies a signature over data issued by `issuer`.
+    pub fn verify(
+        &self,
+        sig_type: SignatureType,
+        signature: &[u8],
+        signed_data: &[u8],
+        issuer: &str,
+    ) -> SignatureStatus {
+        let hash: [u8; 20] = Sha1::digest(signed_data).into();
+        if signature.iter().all(|&b| b == 0) {
+            return if hash[0] == 0 {
+                SignatureStatus::Fakesigned
+            } else {
+                SignatureStatus::Invalid
+            };
+        }
+        match self.find(issuer) {
+            Some(cert) => verify_with_key(&cert.key, sig_type, signature, &hash),
+            None => SignatureStatus::MissingIssuer,
+        }
+    }
+
+    /// Verifies a signed blob (ticket, TMD or certificate), starting with the signature type.
+    pub fn verify_signed(&self, data: &[u8]) -> Result<SignatureStatus, String> {
+        let signed = parse_signed(data)?;
+        Ok(self.verify(signed.sig_type, signed.signature, signed.signed_data, signed.issuer))
+    }
+
+    /// Verifies each certificate in the chain against its issuer.
+    ///
+    /// The root public key is not stored in the chain. Without `root_key`, certificates
+    /// issued by Root are reported as [`SignatureStatus::MissingIssuer`], unless the signature
+    /// is zeroed.
+    pub fn verify_certs(&self, root_key: Option<&PublicKey>) -> Vec<SignatureStatus> {
+        self.certs
+            .iter()
+            .map(|cert| match root_key {
+                Some(key) if cert.issuer == "Root" => {
+                    let hash: [u8; 20] = Sha1::digest(cert.signed_data).into();
+                    if cert.signature.iter().all(|&b| b == 0) && hash[0] == 0 {
+                        SignatureStatus::Fakesigned
+                    } else {
+                        verify_with_key(key, cert.sig_type, cert.signature, &hash)
+                    }
+                }
+                _ => self.verify(cert.sig_type, cert.signature, cert.signed_data, cert.issuer),
+            })
+            .collect()
+    }
+}
+
+/// Signature verification results for a Wii partition.
+#[derive(Debug, Clone)]
+pub struct PartitionSignatures {
+    /// Ticket signature status.
+    pub ticket: SignatureStatus,
+    /// TMD signature status.
+    pub tmd: SignatureStatus,
+    /// Certificate full names and signature statuses, in chain order.
+    pub certs: Vec<(String, SignatureStatus)>,
+}
+
+impl PartitionSignatures {
+    /// Whether the ticket, TMD and all certificates, up to the root key, are properly signed.
+    pub fn is_valid(&self) -> bool {
+        self.ticket == SignatureStatus::Valid
+            && self.tmd == SignatureStatus::Valid
+            && self.certs.iter().all(|(_, s)| *s == SignatureStatus::Valid)
+    }
+
+    /// Whether no signature is invalid, but some could not be checked because the issuer's key
+    /// is unavailable, such as certificates issued by Root when the root key is not loaded.
+    pub fn is_unverified(&self) -> bool {
+        let statuses =
+            || [self.ticket, self.tmd].into_iter().chain(self.certs.iter().map(|(_, s)| *s));
+        statuses().any(|s| s == SignatureStatus::MissingIssuer)
+            && statuses().all(|s| s != SignatureStatus::Invalid)
+    }
+
+    /// Whether the ticket or TMD is fakesigned.
+    pub fn is_fakesigned(&self) -> bool {
+        self.ticket == SignatureStatus::Fakesigned || self.tmd == SignatureStatus::Fakesigned
+    }
+}
+
+impl PartitionMeta {
+    /// A view into the certificate chain. (Wii only)
+    pub fn cert_chain(&self) -> Result<Option<CertChain<'_>>, String> {
+        self.raw_cert_chain.as_deref().map(CertChain::new).transpose()
+    }
+
+    /// Verifies the ticket, TMD and certificate chain signatures. (Wii only)
+    ///
+    /// Certificates issued by Root are verified with the root key for the TMD's certificate
+    /// authority (see [`KeyStore::root_key`]), and are reported as
+    /// [`SignatureStatus::MissingIssuer`] if it is not loaded.
+    pub fn verify_signatures(
+        &self,
+        keys: &KeyStore,
+    ) -> Result<Option<PartitionSignatures>, String> {
+        let (Some(raw_ticket), Some(raw_tmd), Some(chain)) =
+            (self.raw_ticket.as_deref(), self.raw_tmd.as_deref(), self.cert_chain()?)
+        else {
+            return Ok(None);
+        };
+        let ticket =
+            chain.verify_signed(&raw_ticket[..size_of::<Ticket>().min(raw_ticket.len())])?;
+        let tmd_size = Tmd::new(raw_tmd)?.size();
+        let tmd = chain.verify_signed(&raw_tmd[..tmd_size])?;
+        let root_key = keys.root_key(parse_signed(raw_tmd)?.issuer);
+        let certs = chain
+            .certs
+            .iter()
+            .zip(chain.verify_certs(root_key.as_ref()))
+            .map(|(cert, status)| (cert.full_name(), status))
+            .collect();
+        Ok(Some(PartitionSignatures { ticket, tmd, certs }))
+    }
+}
+
+fn verify_with_key(
+    key: &PublicKey,
+    sig_type: SignatureType,
+    signature: &[u8],
+    hash: &[u8; 20],
+) -> SignatureStatus {
+    match *key {
+        PublicKey::Rsa { modulus, exponent }
+            if sig_type != SignatureType::Ecc && modulus.len() == signature.len() =>
+        {
+            if verify_pkcs1_sha1(modulus, exponent, signature, hash) {
+                SignatureStatus::Valid
+            } else {
+                SignatureStatus::Invalid
+            }
+        }
+        // ECC signatures are only used by console-specific certificates
+        _ => SignatureStatus::Invalid,
+    }
+}
+
+/// A signed blob split into its signature and signed data.
+struct Signed<'a> {
+    sig_type: SignatureType,
+    signature: &'a [u8],
+    issuer: &'a str,
+    signed_data: &'a [u8],
+}
+
+fn parse_signed(data: &[u8]) -> Result<Signed<'_>, String> {
+    let raw_type =
+        u32::from_be_bytes(data.get(..4).ok_or("missing signature type")?.try_into().unwrap());
+    let sig_type = SignatureType::from_u32(raw_type)
+        .ok_or_else(|| format!("unknown signature type {:#010X}", raw_type))?;
+    let signed_data = data.get(sig_type.block_size()..).ok_or("truncated signature")?;
+    let signature = &data[4..4 + sig_type.signature_size()];
+    let issuer = read_str(signed_data.get(..64).ok_or("truncated issuer")?)?;
+    Ok(Signed { sig_type, signature, issuer, signed_data })
+}
+
+fn read_str(data: &[u8]) -> Result<&str, String> {
+    CStr::from_bytes_until_nul(data)
+        .ok()
+        .and_then(|s| s.to_str().ok())
+        .ok_or_else(|| "invalid string".to_string())
+}
+
+fn parse_cert(data: &[u8]) -> Result<(Certificate<'_>, &[u8]), String> {
+    let Signed { sig_type, signature, issuer, signed_data: body } = parse_signed(data)?;
+    let header = body.get(..0x88).ok_or("truncated certificate")?;
+    let key_type = u32::from_be_bytes(header[0x40..0x44].try_into().unwrap());
+    let name = read_str(&header[0x44..0x84])?;
+    let key_id = u32::from_be_bytes(header[0x84..0x88].try_into().unwrap());
+    let key_size = match key_type {
+        0 => 0x238,
+        1 => 0x138,
+        2 => 0x78,
+        _ => return Err(format!("unknown key type {}", key_type)),
+    };
+    let body_size = 0x88 + key_size;
+    let key_data = body.get(0x88..body_size).ok_or("truncated public key")?;
+    let key = match key_type {
+        0 | 1 => {
+            let modulus_size = key_size - 0x38;
+            PublicKey::Rsa {
+                modulus: &key_data[..modulus_size],
+                exponent: u32::from_be_bytes(
+                    key_data[modulus_size..modulus_size + 4].try_into().unwrap(),
+                ),
+            }
+        }
+        _ => PublicKey::Ecc(&key_data[..0x3C]),
+    };
+    let cert = Certificate {
+        sig_type,
+        signature,
+        issuer,
+        name,
+        key_id,
+        key,
+        signed_data: &body[..body_size],
+    };
+    Ok((cert, &body[body_size..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use zerocopy::{big_endian::*, AsBytes, FromZeroes};
+
+    use super::*;
+    use crate::TmdHeader;
+
+    // RSA-2048 test key, and its signature over the zeroed TMD built in `test_verify`
+    const MODULUS: [&str; 8] = [
+        "aee041405339d0eca920ba5e32b317a95e800ba95a292db38fa04ae6eee7fb34",
+        "2cab8151dc6f15312d33638812916f0148463defa22f2bbd35931ca88519c728",
+        "3443d1b5a55b4c97f2d188bf3db5d876e485a68409f667468753f5f4490d8b4e",
+        "ee94cf72e6de4f3a9f40036a338b080328af23196e2f32ddb4614caeacb7a8b9",
+        "b5533c6c5c4d7c874bef584a43380f66d7f04a440db28d2313a3131a9883053d",
+        "8debe2c2d76beb0fe681176a61b732652deb8c0e23e99c727ec48afde155f9fd",
+        "dfdda5813f8df7f96e6f4eb1e5fb6c31209695d109cbabb2b7cecf37f093b808",
+        "a455a517c35b3321bb8f3d9990a308756f987e947c2ebc724a0b19e8dce0efc9",
+    ];
+    const SIGNATURE: [&str; 8] = [
+        "565f46e1bf6d640d02d0438a42322999e6111027718026ad077422aa7ba3f382",
+        "70069e9b1c9311f7c3cb02dd254c70cfaf4c55694d5eae764e8611c73b0a33f3",
+        "c4dd70079f25af4ce077c1eaff99c28d3298de2da77a4ff4d04b676fa6221941",
+        "b74c9d89972783f678dfeaac3cecb57b6b8e6b7797f844b706eb95ba79429bf9",
+        "4a8492cd9948ce07ee9eb6e6dd1134531154675c344ad115c73d0c2988431a9a",
+        "1dbaf9198f61b8d0098d5d00687937db1ed929a37c641115e61121950c43067b",
+        "ca2e9e2fd08cf8b7d3fc9a6f9c8a674e60a7e5fffa4fb8f3e281474158a90578",
+        "fb868f9c7f4cb1d8e48c8535dfa07fbc332c197b86e7bc477eab9d278d605ddd",
+    ];
+
+    // Signature by the test key over the CA certificate built in `test_verify_root`
+    const CA_SIGNATURE: [&str; 8] = [
+        "723294b894e468c65e45565301373cf2f72b191362e65e8069c613219d3a799f",
+        "2fc1fc271cd1e5fb83b29e612a66d68c1297b1ec60afb2c080d0c75d103f5920",
+        "bef8f836a7ead1df134fbb639e03f8f9e92cd31e9dbdf1e6c0951aec2338b850",
+        "1e24ddea84b27e56fec9ba3631ee9c71802b09cc8b36561ac46e4050440fd166",
+        "849c0705ce656963f4ea9e0cd752c1575a82a7d57784e6cbd3ab66a8ecf7c4a9",
+        "662070d73f840d215593179f2f1af002395d117d2669b0f5ef64eeb105a0dc78",
+        "5409b377d3b0e5b481f9c556ea037df8d27625ec9c17bf9dfa5da745b23e2999",
+        "9a4c27ca09ae99c70b73bde010cf27fe0a44072ab182ae23f9def3af03fb0307",
+    ];
+
+    fn str_field(s: &str) -> [u8; 64] {
+        let mut out = [0u8; 64];
+        out[..s.len()].copy_from_slice(s.as_bytes());
+        out
+    }
+
+    fn cert(issuer: &str, name: &str, modulus: &[u8]) -> Vec<u8> {
+        let mut out = 0x10000u32.to_be_bytes().to_vec(); // RSA-4096
+        out.resize(0x240, 0); // Zeroed signature
+        out.extend(cert_body(issuer, name, modulus));
+        out
+    }
+
+    fn signed_cert(issuer: &str, name: &str, modulus: &[u8], signature: &[u8; 256]) -> Vec<u8> {
+        let mut out = 0x10001u32.to_be_bytes().to_vec(); // RSA-2048
+        out.extend_from_slice(signature);
+        out.resize(0x140, 0);
+        out.extend(cert_body(issuer, name, modulus));
+        out
+    }
+
+    fn cert_body(issuer: &str, name: &str, modulus: &[u8]) -> Vec<u8> {
+        let mut out = str_field(issuer).to_vec();
+        out.extend_from_slice(&1u32.to_be_bytes()); // RSA-2048 key
+        out.extend_from_slice(&str_field(name));
+        out.extend_from_slice(&0u32.to_be_bytes());
+        out.extend_from_slice(modulus);
+        out.extend_from_slice(&65537u32.to_be_bytes());
+        out.resize(out.len() + 0x34, 0);
+        out
+    }
+
+    fn decode(hex: &[&str]) -> [u8; 256] {
+        let mut out = [0u8; 256];
+        base16ct::lower::decode(hex.concat(), &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_verify() {
+        let modulus = decode(&MODULUS);
+        let mut raw_chain = cert("Root", "CA00000001", &modulus);
+        raw_chain.extend(cert("Root-CA00000001", "CP00000004", &modulus));
+        let chain = CertChain::new(&raw_chain).unwrap();
+        assert_eq!(chain.certs.len(), 2);
+        assert_eq!(chain.certs[1].full_name(), "Root-CA00000001-CP00000004");
+        // Zeroed signatures, with hashes not starting with 0x00
+        assert_eq!(chain.verify_certs(None), [SignatureStatus::Invalid, SignatureStatus::Invalid]);
+
+        let mut tmd = TmdHeader::new_zeroed();
+        tmd.header.sig_type = U32::new(0x10001);
+        tmd.header.sig = decode(&SIGNATURE);
+        tmd.sig_issuer = str_field("Root-CA00000001-CP00000004");
+        assert_eq!(chain.verify_signed(tmd.as_bytes()), Ok(SignatureStatus::Valid));
+
+        tmd.title_version = U16::new(1);
+        assert_eq!(chain.verify_signed(tmd.as_bytes()), Ok(SignatureStatus::Invalid));
+
+        tmd.header.sig = [0; 256];
+        let fakesigned = (0..=u16::MAX).any(|v| {
+            tmd.minor_version = U16::new(v);
+            Sha1::digest(&tmd.as_bytes()[0x140..])[0] == 0
+        });
+        assert!(fakesigned);
+        assert_eq!(chain.verify_signed(tmd.as_bytes()), Ok(SignatureStatus::Fakesigned));
+
+        tmd.sig_issuer = str_field("Root-CA00000001-CP00000005");
+        tmd.header.sig[0] = 1;
+        assert_eq!(chain.verify_signed(tmd.as_bytes()), Ok(SignatureStatus::MissingIssuer));
+    }
+
+    #[test]
+    fn test_verify_root() {
+        let modulus = decode(&MODULUS);
+        let signature = decode(&CA_SIGNATURE);
+        let root = PublicKey::Rsa { modulus: &modulus, exponent: 65537 };
+        let raw_chain = signed_cert("Root", "CA00000001", &modulus, &signature);
+        let chain = CertChain::new(&raw_chain).unwrap();
+        assert_eq!(chain.verify_certs(Some(&root)), [SignatureStatus::Valid]);
+        assert_eq!(chain.verify_certs(None), [SignatureStatus::MissingIssuer]);
+
+        // CA certificate with a substituted public key, keeping the original signature
+        let mut forged_modulus = modulus;
+        forged_modulus[0x80] ^= 1;
+        let raw_forged = signed_cert("Root", "CA00000001", &forged_modulus, &signature);
+        let forged = CertChain::new(&raw_forged).unwrap();
+        assert_eq!(forged.verify_certs(Some(&root)), [SignatureStatus::Invalid]);
+
+        // A chain that can't be checked up to the root is not valid
+        let mut signatures = PartitionSignatures {
+            ticket: SignatureStatus::Valid,
+            tmd: SignatureStatus::Valid,
+            certs: vec![("Root-CA00000001".to_string(), SignatureStatus::MissingIssuer)],
+        };
+        assert!(!signatures.is_valid());
+        assert!(signatures.is_unverified());
+        signatures.tmd = SignatureStatus::Invalid;
+        assert!(!signatures.is_unverified());
+        signatures.tmd = SignatureStatus::Valid;
+        signatures.certs[0].1 = SignatureStatus::Valid;
+        assert!(signatures.is_valid());
+    }
+}
@@ -16,17 +16,20 @@ use crate::{
     io::block::{Block, BlockIO},
     streams::{ReadStream, SharedWindowedReadStream},
     util::read::{read_box, read_box_slice, read_vec},
-    Result, ResultContext,
+    Format, Result, ResultContext,
 };
 
 pub struct PartitionGC {
     io: Box<dyn BlockIO>,
+    format: Format,
     block: Block,
     block_buf: Box<[u8]>,
     block_idx: u32,
     sector_buf: Box<[u8; SECTOR_SIZE]>,
     sector: u32,
     pos: u64,
+    /// The stream size, if known.
+    size: Option<u64>,
     disc_header: Box<DiscHeader>,
 }
 
@@ -34,21 +37,28 @@ impl Clone for PartitionGC {
     fn clone(&self) -> Self {
         Self {
             io: self.io.clone(),
+            format: self.format,
             block: Block::default(),
             block_buf: <u8>::new_box_slice_zeroed(self.block_buf.len()),
             block_idx: u32::MAX,
             sector_buf: <[u8; SECTOR_SIZE]>::new_box_zeroed(),
             sector: u32::MAX,
             pos: 0,
+            size: self.size,
             disc_header: self.disc_header.clone(),
         }
     }
 }
 
 impl PartitionGC {
-    pub fn new(inner: Box<dyn BlockIO>, disc_header: Box<DiscHeader>) -> Result<Box<Self>> {
+    pub fn new(
+        inner: Box<dyn BlockIO>,
+        disc_header: Box<DiscHeader>,
+        size: Option<u64>,
+    ) -> Result<Box<Self>> {
         let block_size = inner.block_size();
         Ok(Box::new(Self {
+            format: inner.meta().format,
             io: inner,
             block: Block::default(),
             block_buf: <u8>::new_box_slice_zeroed(block_size as usize),
@@ -56,6 +66,7 @@ impl PartitionGC {
             sector_buf: <[u8; SECTOR_SIZE]>::new_box_zeroed(),
             sector: u32::MAX,
             pos: 0,
+            size,
             disc_header,
         }))
     }
@@ -65,6 +76,10 @@ impl PartitionGC {
 
 impl Read for PartitionGC {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.size.map_or(u64::MAX, |size| size.saturating_sub(self.pos));
+        if remaining == 0 {
+            return Ok(0);
+        }
         let sector = (self.pos / SECTOR_SIZE as u64) as u32;
         let block_idx = (sector as u64 * SECTOR_SIZE as u64 / self.block_buf.len() as u64) as u32;
 
@@ -81,12 +96,13 @@ impl Read for PartitionGC {
                 self.block_buf.as_ref(),
                 sector,
                 &self.disc_header,
+                self.format,
             )?;
             self.sector = sector;
         }
 
         let offset = (self.pos % SECTOR_SIZE as u64) as usize;
-        let len = min(buf.len(), SECTOR_SIZE - offset);
+        let len = min(min(buf.len(), SECTOR_SIZE - offset) as u64, remaining) as usize;
         buf[..len].copy_from_slice(&self.sector_buf[offset..offset + len]);
         self.pos += len as u64;
         Ok(len)
@@ -97,12 +113,16 @@ impl Seek for PartitionGC {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         self.pos = match pos {
             SeekFrom::Start(v) => v,
-            SeekFrom::End(_) => {
-                return Err(io::Error::new(
-                    io::ErrorKind::Unsupported,
-                    "GCPartitionReader: SeekFrom::End is not supported".to_string(),
-                ));
-            }
+            SeekFrom::End(v) => match self.size {
+                Some(size) => size.saturating_add_signed(v),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        "GCPartitionReader: SeekFrom::End is not supported without a known disc size"
+                            .to_string(),
+                    ));
+                }
+            },
             SeekFrom::Current(v) => self.pos.saturating_add_signed(v),
         };
         Ok(self.pos)
@@ -115,12 +135,14 @@ impl PartitionBase for PartitionGC {
         read_part_meta(self, false)
     }
 
-    fn open_file(&mut self, node: &Node) -> io::Result<SharedWindowedReadStream> {
+    fn open_file(&mut self, node: &Node) -> io::Result<SharedWindowedReadStream<'_>> {
         assert_eq!(node.kind(), NodeKind::File);
         self.new_window(node.offset(false), node.length())
     }
 
     fn ideal_buffer_size(&self) -> usize { SECTOR_SIZE }
+
+    fn data_size(&self) -> Option<u64> { self.size }
 }
 
 pub(crate) fn read_part_meta(
@@ -197,3 +219,50 @@ pub(crate) fn read_part_meta(
         raw_h3_table: None,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{io::block::PartitionInfo, DiscMeta};
+
+    /// Every byte is the low byte of its sector index.
+    #[derive(Clone)]
+    struct SectorIO;
+
+    impl BlockIO for SectorIO {
+        fn read_block_internal(
+            &mut self,
+            out: &mut [u8],
+            block: u32,
+            _partition: Option<&PartitionInfo>,
+        ) -> io::Result<Block> {
+            out.fill(block as u8);
+            Ok(Block::Raw)
+        }
+
+        fn block_size_internal(&self) -> u32 { SECTOR_SIZE as u32 }
+
+        fn meta(&self) -> DiscMeta { DiscMeta::default() }
+    }
+
+    #[test]
+    fn test_partition_size() {
+        let header = DiscHeader::new_box_zeroed();
+        let mut partition =
+            PartitionGC::new(Box::new(SectorIO), header.clone(), Some(0x18000)).unwrap();
+        assert_eq!(partition.data_size(), Some(0x18000));
+        assert_eq!(partition.seek(SeekFrom::End(-4)).unwrap(), 0x17FFC);
+        let mut out = Vec::new();
+        partition.read_to_end(&mut out).unwrap();
+        assert_eq!(out, [2; 4]);
+
+        // Without a known size, reads aren't clamped and there's no end to seek from
+        let mut partition = PartitionGC::new(Box::new(SectorIO), header, None).unwrap();
+        assert_eq!(partition.data_size(), None);
+        assert!(partition.seek(SeekFrom::End(0)).is_err());
+        let mut buf = [0u8; 4];
+        partition.seek(SeekFrom::Start(0x20000)).unwrap();
+        partition.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [4; 4]);
+    }
+}
@@ -1,6 +1,9 @@
 use std::{
     io::{Read, Seek, SeekFrom},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
     time::Instant,
 };
 
@@ -16,7 +19,8 @@ use crate::{
     },
     io::HashBytes,
     util::read::read_box_slice,
-    OpenOptions, Result, ResultContext, SECTOR_SIZE,
+    Error, OpenOptions, Progress, ProgressHandler, ProgressStage, Result, ResultContext,
+    SECTOR_SIZE,
 };
 
 /// In a sector, following the 0x400 byte block of hashes, each 0x400 bytes of decrypted data is
@@ -69,7 +73,10 @@ impl HashTable {
     }
 }
 
-pub fn rebuild_hashes(reader: &mut DiscReader) -> Result<()> {
+pub fn rebuild_hashes(
+    reader: &mut DiscReader,
+    progress: Option<&dyn ProgressHandler>,
+) -> Result<()> {
     const NUM_H0_HASHES: usize = SECTOR_DATA_SIZE / HASHES_SIZE;
 
     log::info!(
@@ -84,6 +91,11 @@ pub fn rebuild_hashes(reader: &mut DiscReader) -> Result<()> {
     let zero_h0_hash = hash_bytes(ZERO_H0_BYTES);
 
     let partitions = reader.partitions();
+    let total_groups = partitions
+        .iter()
+        .map(|part| (part.data_end_sector - part.data_start_sector).div_ceil(64) as u64)
+        .sum::<u64>();
+    let processed_groups = AtomicU64::new(0);
     let mut hash_tables = Vec::with_capacity(partitions.len());
     for part in partitions {
         let part_sectors = part.data_end_sector - part.data_start_sector;
@@ -100,6 +112,9 @@ pub fn rebuild_hashes(reader: &mut DiscReader) -> Result<()> {
         (0..group_count).into_par_iter().try_for_each_with(
             (reader.open_partition(part.index, &OpenOptions::default())?, mutex.clone()),
             |(stream, mutex), h3_index| -> Result<()> {
+                if progress.is_some_and(|p| p.is_cancelled()) {
+                    return Err(Error::Cancelled);
+                }
                 let mut result = HashResult::new_box_zeroed();
                 let mut data_buf = <u8>::new_box_slice_zeroed(SECTOR_DATA_SIZE);
                 let mut h3_hasher = Sha1::new();
@@ -142,6 +157,15 @@ pub fn rebuild_hashes(reader: &mut DiscReader) -> Result<()> {
                 result.h3_hash = h3_hasher.finalize().into();
                 let mut hash_table = mutex.lock().map_err(|_| "Failed to lock mutex")?;
                 hash_table.extend(h3_index, &result);
+                drop(hash_table);
+                let processed = processed_groups.fetch_add(1, Ordering::Relaxed) + 1;
+                if let Some(progress) = progress {
+                    progress.on_progress(Progress {
+                        stage: ProgressStage::RebuildHashes,
+                        processed,
+                        total: total_groups,
+                    });
+                }
                 Ok(())
             },
         )?;
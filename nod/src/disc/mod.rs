@@ -17,11 +17,12 @@ use crate::{
     fst::Node,
     static_assert,
     streams::{ReadStream, SharedWindowedReadStream},
-    Fst, Result,
+    DecompressedStream, Fst, Result,
 };
 
 pub(crate) mod gcn;
 pub(crate) mod hashes;
+pub(crate) mod read_ahead;
 pub(crate) mod reader;
 pub(crate) mod wii;
 
@@ -32,9 +33,11 @@ pub const SECTOR_SIZE: usize = 0x8000;
 ///
 /// This header is always at the start of the disc image and within each Wii partition.
 #[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct DiscHeader {
     /// Game ID (e.g. GM8E01 for Metroid Prime)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::cstr"))]
     pub game_id: [u8; 6],
     /// Used in multi-disc games
     pub disc_num: u8,
@@ -45,18 +48,23 @@ pub struct DiscHeader {
     /// Audio streaming buffer size
     pub audio_stream_buf_size: u8,
     /// Padding
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
     _pad1: [u8; 14],
     /// If this is a Wii disc, this will be 0x5D1C9EA3
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub wii_magic: U32,
     /// If this is a GameCube disc, this will be 0xC2339F3D
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub gcn_magic: U32,
     /// Game title
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::cstr"))]
     pub game_title: [u8; 64],
     /// If 1, disc omits partition hashes
     pub no_partition_hashes: u8,
     /// If 1, disc omits partition encryption
     pub no_partition_encryption: u8,
     /// Padding
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
     _pad2: [u8; 926],
 }
 
@@ -87,29 +95,41 @@ impl DiscHeader {
 ///
 /// **Wii**: Follows the disc header within each partition.
 #[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct PartitionHeader {
     /// Debug monitor offset
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub debug_mon_offset: U32,
     /// Debug monitor load address
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub debug_load_address: U32,
     /// Padding
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
     _pad1: [u8; 0x18],
     /// Offset to main DOL (Wii: >> 2)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub dol_offset: U32,
     /// Offset to file system table (Wii: >> 2)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub fst_offset: U32,
     /// File system size (Wii: >> 2)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub fst_size: U32,
     /// File system max size (Wii: >> 2)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub fst_max_size: U32,
     /// File system table load address
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub fst_memory_address: U32,
     /// User position
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub user_position: U32,
     /// User size
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub user_size: U32,
     /// Padding
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
     _pad2: [u8; 4],
 }
 
@@ -155,17 +175,23 @@ impl PartitionHeader {
 
 /// Apploader header.
 #[derive(Debug, PartialEq, Clone, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct ApploaderHeader {
     /// Apploader build date
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::cstr"))]
     pub date: [u8; 16],
     /// Entry point
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub entry_point: U32,
     /// Apploader size
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub size: U32,
     /// Apploader trailer size
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub trailer_size: U32,
     /// Padding
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
     _pad: [u8; 4],
 }
 
@@ -176,33 +202,154 @@ impl ApploaderHeader {
     }
 }
 
+/// Debug and region information (bi2.bin).
+///
+/// Follows the partition header and is loaded by the apploader to 0x800000F4.
+#[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C, align(4))]
+pub struct Bi2 {
+    /// Debug monitor size
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub debug_monitor_size: U32,
+    /// Simulated memory size
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub simulated_memory_size: U32,
+    /// Argument offset
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub argument_offset: U32,
+    /// Debug flag
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub debug_flag: U32,
+    /// Track location
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub track_location: U32,
+    /// Track size
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub track_size: U32,
+    /// Country code (0 = Japan, 1 = Americas, 2 = Europe, 4 = Korea)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub country_code: U32,
+    /// Total number of discs
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub total_discs: U32,
+    /// Long file name support
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub long_file_names: U32,
+    /// Padding specification
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub pad_spec: U32,
+    /// Maximum DOL size
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub dol_limit: U32,
+    /// Padding
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
+    _pad1: [u8; 4],
+    /// Dual-layer value (Wii only)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub dual_layer_value: U32,
+    /// Padding
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
+    _pad2: [u8; 0x1FCC],
+}
+
+static_assert!(size_of::<Bi2>() == BI2_SIZE);
+
+/// Generates `u32` getters and setters for [`Bi2`] fields.
+macro_rules! bi2_accessors {
+    ($($(#[$meta:meta])* $field:ident, $setter:ident;)*) => {
+        $(
+            $(#[$meta])*
+            #[inline]
+            pub fn $field(&self) -> u32 { self.$field.get() }
+
+            #[doc = concat!("Sets [`", stringify!($field), "`](Self::", stringify!($field), ").")]
+            #[inline]
+            pub fn $setter(&mut self, value: u32) { self.$field.set(value) }
+        )*
+    };
+}
+
+impl Bi2 {
+    bi2_accessors! {
+        /// Debug monitor size.
+        debug_monitor_size, set_debug_monitor_size;
+        /// Simulated memory size.
+        simulated_memory_size, set_simulated_memory_size;
+        /// Argument offset.
+        argument_offset, set_argument_offset;
+        /// Track location.
+        track_location, set_track_location;
+        /// Track size.
+        track_size, set_track_size;
+        /// Country code. (0 = Japan, 1 = Americas, 2 = Europe, 4 = Korea)
+        country_code, set_country_code;
+        /// Total number of discs.
+        total_discs, set_total_discs;
+        /// Padding specification.
+        pad_spec, set_pad_spec;
+        /// Maximum DOL size. Zero if unlimited.
+        dol_limit, set_dol_limit;
+        /// Dual-layer value. (Wii only)
+        dual_layer_value, set_dual_layer_value;
+    }
+
+    /// Whether the debug flag is set.
+    #[inline]
+    pub fn debug_flag(&self) -> bool { self.debug_flag.get() != 0 }
+
+    /// Sets the debug flag.
+    #[inline]
+    pub fn set_debug_flag(&mut self, value: bool) { self.debug_flag.set(value as u32) }
+
+    /// Whether long file names are supported.
+    #[inline]
+    pub fn long_file_names(&self) -> bool { self.long_file_names.get() != 0 }
+
+    /// Sets long file name support.
+    #[inline]
+    pub fn set_long_file_names(&mut self, value: bool) { self.long_file_names.set(value as u32) }
+}
+
 /// Maximum number of text sections in a DOL.
 pub const DOL_MAX_TEXT_SECTIONS: usize = 7;
 /// Maximum number of data sections in a DOL.
 pub const DOL_MAX_DATA_SECTIONS: usize = 11;
 
 /// Dolphin executable (DOL) header.
-#[derive(Debug, Clone, FromBytes, FromZeroes)]
+#[derive(Debug, Clone, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
 pub struct DolHeader {
     /// Text section offsets
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be_array"))]
     pub text_offs: [U32; DOL_MAX_TEXT_SECTIONS],
     /// Data section offsets
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be_array"))]
     pub data_offs: [U32; DOL_MAX_DATA_SECTIONS],
     /// Text section addresses
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be_array"))]
     pub text_addrs: [U32; DOL_MAX_TEXT_SECTIONS],
     /// Data section addresses
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be_array"))]
     pub data_addrs: [U32; DOL_MAX_DATA_SECTIONS],
     /// Text section sizes
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be_array"))]
     pub text_sizes: [U32; DOL_MAX_TEXT_SECTIONS],
     /// Data section sizes
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be_array"))]
     pub data_sizes: [U32; DOL_MAX_DATA_SECTIONS],
     /// BSS address
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub bss_addr: U32,
     /// BSS size
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub bss_size: U32,
     /// Entry point
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub entry_point: U32,
     /// Padding
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
     _pad: [u8; 0x1C],
 }
 
@@ -210,6 +357,7 @@ static_assert!(size_of::<DolHeader>() == 0x100);
 
 /// The kind of disc partition.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PartitionKind {
     /// Data partition.
     Data,
@@ -237,7 +385,7 @@ impl Display for PartitionKind {
 
 impl PartitionKind {
     /// Returns the directory name for the partition kind.
-    pub fn dir_name(&self) -> Cow<str> {
+    pub fn dir_name(&self) -> Cow<'_, str> {
         match self {
             Self::Data => Cow::Borrowed("DATA"),
             Self::Update => Cow::Borrowed("UPDATE"),
@@ -294,12 +442,31 @@ pub trait PartitionBase: DynClone + ReadStream + Send + Sync {
     ///     Ok(())
     /// }
     /// ```
-    fn open_file(&mut self, node: &Node) -> io::Result<SharedWindowedReadStream>;
+    fn open_file(&mut self, node: &Node) -> io::Result<SharedWindowedReadStream<'_>>;
+
+    /// Opens a file like [`PartitionBase::open_file`], transparently decompressing Yaz0 and
+    /// Yay0 data. Other files are read unchanged. (See [`DecompressedStream`])
+    fn open_file_decompressed(
+        &mut self,
+        node: &Node,
+    ) -> io::Result<DecompressedStream<SharedWindowedReadStream<'_>>> {
+        DecompressedStream::new(self.open_file(node)?)
+    }
 
     /// The ideal size for buffered reads from this partition.
     /// GameCube discs have a data block size of 0x8000,
     /// whereas Wii discs have a data block size of 0x7C00.
     fn ideal_buffer_size(&self) -> usize;
+
+    /// The size of the partition read stream in bytes, if known.
+    ///
+    /// **GameCube**: This is the size of the disc image, if stored by the disc format.
+    ///
+    /// **Wii**: This is the partition data size with hashes removed, i.e. 0x7C00 bytes for
+    /// each 0x8000 byte sector.
+    ///
+    /// The default implementation returns `None`.
+    fn data_size(&self) -> Option<u64> { None }
 }
 
 dyn_clone::clone_trait_object!(PartitionBase);
@@ -343,13 +510,19 @@ impl PartitionMeta {
         PartitionHeader::ref_from(&self.raw_boot[size_of::<DiscHeader>()..]).unwrap()
     }
 
+    /// A view into the debug and region information (bi2.bin).
+    pub fn bi2(&self) -> &Bi2 { Bi2::ref_from(self.raw_bi2.as_slice()).unwrap() }
+
+    /// A mutable view into the debug and region information (bi2.bin).
+    pub fn bi2_mut(&mut self) -> &mut Bi2 { Bi2::mut_from(self.raw_bi2.as_mut_slice()).unwrap() }
+
     /// A view into the apploader header.
     pub fn apploader_header(&self) -> &ApploaderHeader {
         ApploaderHeader::ref_from_prefix(&self.raw_apploader).unwrap()
     }
 
     /// A view into the file system table (FST).
-    pub fn fst(&self) -> Result<Fst, &'static str> { Fst::new(&self.raw_fst) }
+    pub fn fst(&self) -> Result<Fst<'_>, &'static str> { Fst::new(&self.raw_fst) }
 
     /// A view into the DOL header.
     pub fn dol_header(&self) -> &DolHeader { DolHeader::ref_from_prefix(&self.raw_dol).unwrap() }
@@ -0,0 +1,318 @@
+use std::{
+    cmp::min,
+    collections::VecDeque,
+    io,
+    sync::{
+        mpsc::{sync_channel, Receiver},
+        Arc, Mutex,
+    },
+};
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use zerocopy::FromZeroes;
+
+use crate::{
+    array_ref_mut,
+    disc::{
+        reader::{copy_sector, find_partition, EncryptionMode},
+        SECTOR_SIZE,
+    },
+    io::block::{Block, BlockIO, PartitionInfo},
+    DiscHeader, Error, Format, Result,
+};
+
+/// Minimum number of bytes decoded by a single read-ahead task.
+const MIN_CHUNK_SIZE: u32 = 0x200000;
+
+type ChunkResult = io::Result<Box<[u8]>>;
+
+/// State shared between the reader and the read-ahead tasks.
+struct Shared {
+    /// Instance used to create new [`BlockIO`] handles for tasks.
+    io: Box<dyn BlockIO>,
+    format: Format,
+    /// Idle [`BlockIO`] handles, reused between tasks to preserve their internal caches.
+    idle: Mutex<Vec<Box<dyn BlockIO>>>,
+    disc_header: Box<DiscHeader>,
+    partitions: Vec<PartitionInfo>,
+    mode: EncryptionMode,
+    chunk_size: u32,
+}
+
+impl Shared {
+    fn take_io(&self) -> Box<dyn BlockIO> {
+        self.idle
+            .lock()
+            .ok()
+            .and_then(|mut idle| idle.pop())
+            .unwrap_or_else(|| dyn_clone::clone_box(&*self.io))
+    }
+
+    fn return_io(&self, io: Box<dyn BlockIO>) {
+        if let Ok(mut idle) = self.idle.lock() {
+            idle.push(io);
+        }
+    }
+
+    /// Reads, decodes and (if necessary) encrypts every sector within a chunk.
+    fn decode_chunk(&self, io: &mut dyn BlockIO, chunk_idx: u32) -> ChunkResult {
+        let block_size = io.block_size();
+        let mut out = <u8>::new_box_slice_zeroed(self.chunk_size as usize);
+        let mut block_buf = <u8>::new_box_slice_zeroed(block_size as usize);
+        let mut block = Block::default();
+        let mut block_idx = u32::MAX;
+        let first_sector = chunk_idx * (self.chunk_size / SECTOR_SIZE as u32);
+        for (i, sector_buf) in out.chunks_exact_mut(SECTOR_SIZE).enumerate() {
+            let abs_sector = first_sector + i as u32;
+            let partition = find_partition(&self.disc_header, &self.partitions, abs_sector);
+            let sector_block_idx =
+                (abs_sector as u64 * SECTOR_SIZE as u64 / block_size as u64) as u32;
+            if sector_block_idx != block_idx {
+                block = io.read_block(block_buf.as_mut(), sector_block_idx, partition)?;
+                block_idx = sector_block_idx;
+            }
+            copy_sector(
+                block,
+                array_ref_mut![sector_buf, 0, SECTOR_SIZE],
+                block_buf.as_ref(),
+                abs_sector,
+                partition,
+                &self.disc_header,
+                self.mode,
+                self.format,
+            )?;
+        }
+        Ok(out)
+    }
+}
+
+/// Decodes upcoming chunks of the disc image on a dedicated thread pool and hands them back
+/// in order.
+///
+/// The pool is owned by the reader (and shared with its clones), so waiting for a chunk never
+/// blocks the global rayon pool, which the caller may itself be running on.
+///
+/// Any non-sequential access discards in-flight chunks and restarts the pipeline at the new
+/// position.
+pub struct ReadAhead {
+    shared: Arc<Shared>,
+    pool: Arc<ThreadPool>,
+    disc_size: u64,
+    depth: usize,
+    // Receivers are wrapped in a mutex to keep the reader `Sync`
+    pending: VecDeque<(u32, Mutex<Receiver<ChunkResult>>)>,
+    chunk: Box<[u8]>,
+    chunk_idx: u32,
+}
+
+impl Clone for ReadAhead {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            pool: self.pool.clone(),
+            disc_size: self.disc_size,
+            depth: self.depth,
+            pending: VecDeque::new(),
+            chunk: Box::default(),
+            chunk_idx: u32::MAX,
+        }
+    }
+}
+
+impl ReadAhead {
+    pub fn new(
+        io: Box<dyn BlockIO>,
+        disc_header: Box<DiscHeader>,
+        partitions: Vec<PartitionInfo>,
+        mode: EncryptionMode,
+        disc_size: u64,
+    ) -> Result<Self> {
+        // Decode whole format blocks (e.g. WIA/RVZ groups) per task where possible,
+        // so that no two tasks need to decompress the same data.
+        let block_size = io.block_size();
+        let format_block_size = io.meta().block_size.unwrap_or(0).next_multiple_of(block_size);
+        let mut chunk_size = block_size.max(format_block_size);
+        if chunk_size < MIN_CHUNK_SIZE {
+            chunk_size *= MIN_CHUNK_SIZE.div_ceil(chunk_size);
+        }
+        let pool = ThreadPoolBuilder::new()
+            .thread_name(|i| format!("nod-read-ahead-{}", i))
+            .build()
+            .map_err(|e| Error::Other(format!("Failed to create read-ahead thread pool: {}", e)))?;
+        let depth = pool.current_num_threads() * 2;
+        Ok(Self {
+            shared: Arc::new(Shared {
+                format: io.meta().format,
+                io,
+                idle: Mutex::new(Vec::new()),
+                disc_header,
+                partitions,
+                mode,
+                chunk_size,
+            }),
+            pool: Arc::new(pool),
+            disc_size,
+            depth,
+            pending: VecDeque::with_capacity(depth),
+            chunk: Box::default(),
+            chunk_idx: u32::MAX,
+        })
+    }
+
+    /// Reads from the disc image at the given position, waiting for the containing chunk
+    /// to be decoded if necessary.
+    pub fn read(&mut self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let chunk_size = self.shared.chunk_size as u64;
+        let chunk_idx = (pos / chunk_size) as u32;
+        if chunk_idx != self.chunk_idx {
+            self.chunk = self.take_chunk(chunk_idx)?;
+            self.chunk_idx = chunk_idx;
+        }
+        let offset = (pos % chunk_size) as usize;
+        let len = min(buf.len(), self.chunk.len() - offset);
+        buf[..len].copy_from_slice(&self.chunk[offset..offset + len]);
+        Ok(len)
+    }
+
+    fn take_chunk(&mut self, chunk_idx: u32) -> ChunkResult {
+        if self.pending.front().is_some_and(|(idx, _)| *idx != chunk_idx) {
+            // Non-sequential read, discard in-flight chunks
+            self.pending.clear();
+        }
+        let mut next_idx = self.pending.back().map_or(chunk_idx, |(idx, _)| idx + 1);
+        while self.pending.is_empty()
+            || (self.pending.len() < self.depth
+                && (next_idx as u64 * self.shared.chunk_size as u64) < self.disc_size)
+        {
+            self.pending.push_back((next_idx, Mutex::new(self.spawn(next_idx))));
+            next_idx += 1;
+        }
+        let (_, rx) = self.pending.pop_front().unwrap();
+        let rx = rx
+            .into_inner()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Failed to lock mutex"))?;
+        rx.recv().unwrap_or_else(|_| {
+            Err(io::Error::new(io::ErrorKind::Other, "Read-ahead task terminated unexpectedly"))
+        })
+    }
+
+    fn spawn(&self, chunk_idx: u32) -> Receiver<ChunkResult> {
+        let (tx, rx) = sync_channel(1);
+        let shared = self.shared.clone();
+        self.pool.spawn(move || {
+            let mut io = shared.take_io();
+            let result = shared.decode_chunk(io.as_mut(), chunk_idx);
+            shared.return_io(io);
+            // The receiver is dropped if the chunk was discarded
+            let _ = tx.send(result);
+        });
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Seek, SeekFrom};
+
+    use super::*;
+    use crate::{disc::reader::DiscReader, DiscMeta, OpenOptions};
+
+    #[derive(Clone)]
+    struct MemoryIO(Arc<[u8]>);
+
+    impl BlockIO for MemoryIO {
+        fn read_block_internal(
+            &mut self,
+            out: &mut [u8],
+            block: u32,
+            _partition: Option<&PartitionInfo>,
+        ) -> io::Result<Block> {
+            let offset = block as usize * SECTOR_SIZE;
+            if offset >= self.0.len() {
+                return Ok(Block::Zero);
+            }
+            out.copy_from_slice(&self.0[offset..offset + SECTOR_SIZE]);
+            Ok(Block::Raw)
+        }
+
+        fn block_size_internal(&self) -> u32 { SECTOR_SIZE as u32 }
+
+        fn meta(&self) -> DiscMeta {
+            DiscMeta {
+                block_size: Some(3 * SECTOR_SIZE as u32),
+                disc_size: Some(self.0.len() as u64),
+                ..Default::default()
+            }
+        }
+    }
+
+    fn test_disc() -> Arc<[u8]> {
+        let mut data = vec![0u8; 0x2A0 * SECTOR_SIZE];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (i as u32).wrapping_mul(2654435761).to_be_bytes()[0];
+        }
+        // GameCube magic
+        data[0x1C..0x20].copy_from_slice(&0xC2339F3Du32.to_be_bytes());
+        data.into()
+    }
+
+    fn open(data: &Arc<[u8]>, read_ahead: bool) -> DiscReader {
+        let options = OpenOptions { read_ahead, ..Default::default() };
+        DiscReader::new(Box::new(MemoryIO(data.clone())), &options).unwrap()
+    }
+
+    #[test]
+    fn test_reader_is_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<DiscReader>();
+    }
+
+    #[test]
+    fn test_sequential_read() {
+        let data = test_disc();
+        let mut expected = Vec::new();
+        open(&data, false).take(data.len() as u64).read_to_end(&mut expected).unwrap();
+        let mut actual = Vec::new();
+        open(&data, true).take(data.len() as u64).read_to_end(&mut actual).unwrap();
+        assert_eq!(expected.as_slice(), data.as_ref());
+        assert!(actual == expected);
+    }
+
+    #[test]
+    fn test_read_from_rayon_worker() {
+        // Reading from the only worker of a pool must not wait on tasks queued to that pool
+        let data = test_disc();
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let actual = pool.install(|| {
+            let mut out = Vec::new();
+            open(&data, true).take(data.len() as u64).read_to_end(&mut out).unwrap();
+            out
+        });
+        assert!(actual.as_slice() == data.as_ref());
+    }
+
+    #[test]
+    fn test_seek_restarts_pipeline() {
+        let data = test_disc();
+        let mut reader = open(&data, true);
+        let mut buf = [0u8; 0x1000];
+        for pos in [0x1000000u64, 0x20, 0x8000 * 0x29F, 0x400000] {
+            reader.seek(SeekFrom::Start(pos)).unwrap();
+            reader.read_exact(&mut buf).unwrap();
+            let end = min(pos as usize + buf.len(), data.len());
+            assert_eq!(&buf[..end - pos as usize], &data[pos as usize..end]);
+        }
+    }
+
+    #[test]
+    fn test_seek_end() {
+        let data = test_disc();
+        for read_ahead in [false, true] {
+            let mut reader = open(&data, read_ahead);
+            assert_eq!(reader.seek(SeekFrom::End(-0x10)).unwrap(), data.len() as u64 - 0x10);
+            let mut out = Vec::new();
+            reader.read_to_end(&mut out).unwrap();
+            assert_eq!(out.as_slice(), &data[data.len() - 0x10..]);
+        }
+    }
+}
@@ -10,13 +10,14 @@ use crate::{
     disc::{
         gcn::PartitionGC,
         hashes::{rebuild_hashes, HashTable},
+        read_ahead::ReadAhead,
         wii::{PartitionWii, WiiPartEntry, WiiPartGroup, WiiPartitionHeader, WII_PART_GROUP_OFF},
         DL_DVD_SIZE, MINI_DVD_SIZE, SL_DVD_SIZE,
     },
     io::block::{Block, BlockIO, PartitionInfo},
     util::read::{read_box, read_from, read_vec},
-    DiscHeader, DiscMeta, Error, OpenOptions, PartitionBase, PartitionHeader, PartitionKind,
-    Result, ResultContext, SECTOR_SIZE,
+    DiscHeader, DiscMeta, Error, Format, KeyStore, OpenOptions, PartitionBase, PartitionHeader,
+    PartitionKind, Result, ResultContext, SECTOR_SIZE,
 };
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -27,32 +28,38 @@ pub enum EncryptionMode {
 
 pub struct DiscReader {
     io: Box<dyn BlockIO>,
+    format: Format,
     block: Block,
     block_buf: Box<[u8]>,
     block_idx: u32,
     sector_buf: Box<[u8; SECTOR_SIZE]>,
     sector_idx: u32,
     pos: u64,
+    size: u64,
     mode: EncryptionMode,
     disc_header: Box<DiscHeader>,
     pub(crate) partitions: Vec<PartitionInfo>,
     hash_tables: Vec<HashTable>,
+    read_ahead: Option<ReadAhead>,
 }
 
 impl Clone for DiscReader {
     fn clone(&self) -> Self {
         Self {
             io: self.io.clone(),
+            format: self.format,
             block: Block::default(),
             block_buf: <u8>::new_box_slice_zeroed(self.block_buf.len()),
             block_idx: u32::MAX,
             sector_buf: <[u8; SECTOR_SIZE]>::new_box_zeroed(),
             sector_idx: u32::MAX,
             pos: 0,
+            size: self.size,
             mode: self.mode,
             disc_header: self.disc_header.clone(),
             partitions: self.partitions.clone(),
             hash_tables: self.hash_tables.clone(),
+            read_ahead: self.read_ahead.clone(),
         }
     }
 }
@@ -62,6 +69,7 @@ impl DiscReader {
         let block_size = inner.block_size();
         let meta = inner.meta();
         let mut reader = Self {
+            format: meta.format,
             io: inner,
             block: Block::default(),
             block_buf: <u8>::new_box_slice_zeroed(block_size as usize),
@@ -69,6 +77,8 @@ impl DiscReader {
             sector_buf: <[u8; SECTOR_SIZE]>::new_box_zeroed(),
             sector_idx: u32::MAX,
             pos: 0,
+            // Unknown until the partition table is read
+            size: u64::MAX,
             mode: if options.rebuild_encryption {
                 EncryptionMode::Encrypted
             } else {
@@ -77,16 +87,30 @@ impl DiscReader {
             disc_header: DiscHeader::new_box_zeroed(),
             partitions: vec![],
             hash_tables: vec![],
+            read_ahead: None,
         };
         let disc_header: Box<DiscHeader> = read_box(&mut reader).context("Reading disc header")?;
         reader.disc_header = disc_header;
         if reader.disc_header.is_wii() {
-            reader.partitions = read_partition_info(&mut reader)?;
+            reader.partitions = read_partition_info(&mut reader, options)?;
+        }
+        reader.size =
+            reader.io.meta().disc_size.unwrap_or_else(|| guess_disc_size(&reader.partitions));
+        if reader.disc_header.is_wii() {
             // Rebuild hashes if the format requires it
             if (options.rebuild_encryption || options.validate_hashes) && meta.needs_hash_recovery {
-                rebuild_hashes(&mut reader)?;
+                rebuild_hashes(&mut reader, options.progress.as_deref())?;
             }
         }
+        if options.read_ahead {
+            reader.read_ahead = Some(ReadAhead::new(
+                reader.io.clone(),
+                reader.disc_header.clone(),
+                reader.partitions.clone(),
+                reader.mode,
+                reader.disc_size(),
+            )?);
+        }
         reader.reset();
         Ok(reader)
     }
@@ -100,9 +124,7 @@ impl DiscReader {
         self.pos = 0;
     }
 
-    pub fn disc_size(&self) -> u64 {
-        self.io.meta().disc_size.unwrap_or_else(|| guess_disc_size(&self.partitions))
-    }
+    pub fn disc_size(&self) -> u64 { self.size }
 
     pub fn header(&self) -> &DiscHeader { &self.disc_header }
 
@@ -118,7 +140,8 @@ impl DiscReader {
     ) -> Result<Box<dyn PartitionBase>> {
         if self.disc_header.is_gamecube() {
             if index == 0 {
-                Ok(PartitionGC::new(self.io.clone(), self.disc_header.clone())?)
+                let size = self.io.meta().disc_size;
+                Ok(PartitionGC::new(self.io.clone(), self.disc_header.clone(), size)?)
             } else {
                 Err(Error::DiscFormat("GameCube discs only have one partition".to_string()))
             }
@@ -138,7 +161,8 @@ impl DiscReader {
     ) -> Result<Box<dyn PartitionBase>> {
         if self.disc_header.is_gamecube() {
             if kind == PartitionKind::Data {
-                Ok(PartitionGC::new(self.io.clone(), self.disc_header.clone())?)
+                let size = self.io.meta().disc_size;
+                Ok(PartitionGC::new(self.io.clone(), self.disc_header.clone(), size)?)
             } else {
                 Err(Error::DiscFormat("GameCube discs only have a data partition".to_string()))
             }
@@ -152,16 +176,20 @@ impl DiscReader {
 
 impl Read for DiscReader {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.size {
+            return Ok(0);
+        }
+        let buf_len = min(buf.len() as u64, self.size - self.pos) as usize;
+        let buf = &mut buf[..buf_len];
+        if let Some(read_ahead) = &mut self.read_ahead {
+            let len = read_ahead.read(self.pos, buf)?;
+            self.pos += len as u64;
+            return Ok(len);
+        }
+
         let block_idx = (self.pos / self.block_buf.len() as u64) as u32;
         let abs_sector = (self.pos / SECTOR_SIZE as u64) as u32;
-
-        let partition = if self.disc_header.is_wii() {
-            self.partitions.iter().find(|part| {
-                abs_sector >= part.data_start_sector && abs_sector < part.data_end_sector
-            })
-        } else {
-            None
-        };
+        let partition = find_partition(&self.disc_header, &self.partitions, abs_sector);
 
         // Read new block
         if block_idx != self.block_idx {
@@ -171,29 +199,16 @@ impl Read for DiscReader {
 
         // Read new sector into buffer
         if abs_sector != self.sector_idx {
-            if let Some(partition) = partition {
-                match self.mode {
-                    EncryptionMode::Decrypted => self.block.decrypt(
-                        self.sector_buf.as_mut(),
-                        self.block_buf.as_ref(),
-                        abs_sector,
-                        partition,
-                    )?,
-                    EncryptionMode::Encrypted => self.block.encrypt(
-                        self.sector_buf.as_mut(),
-                        self.block_buf.as_ref(),
-                        abs_sector,
-                        partition,
-                    )?,
-                }
-            } else {
-                self.block.copy_raw(
-                    self.sector_buf.as_mut(),
-                    self.block_buf.as_ref(),
-                    abs_sector,
-                    &self.disc_header,
-                )?;
-            }
+            copy_sector(
+                self.block,
+                self.sector_buf.as_mut(),
+                self.block_buf.as_ref(),
+                abs_sector,
+                partition,
+                &self.disc_header,
+                self.mode,
+                self.format,
+            )?;
             self.sector_idx = abs_sector;
         }
 
@@ -206,23 +221,71 @@ impl Read for DiscReader {
     }
 }
 
+/// Finds the Wii partition containing the given absolute sector, if any.
+pub(crate) fn find_partition<'a>(
+    disc_header: &DiscHeader,
+    partitions: &'a [PartitionInfo],
+    abs_sector: u32,
+) -> Option<&'a PartitionInfo> {
+    if disc_header.is_wii() {
+        partitions
+            .iter()
+            .find(|part| abs_sector >= part.data_start_sector && abs_sector < part.data_end_sector)
+    } else {
+        None
+    }
+}
+
+/// Writes a single sector of the disc image to the output buffer, decrypting or encrypting
+/// Wii partition data as required by the encryption mode.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn copy_sector(
+    block: Block,
+    out: &mut [u8; SECTOR_SIZE],
+    block_buf: &[u8],
+    abs_sector: u32,
+    partition: Option<&PartitionInfo>,
+    disc_header: &DiscHeader,
+    mode: EncryptionMode,
+    format: Format,
+) -> io::Result<()> {
+    if let Some(partition) = partition {
+        match mode {
+            EncryptionMode::Decrypted => {
+                block.decrypt(out, block_buf, abs_sector, partition, format)
+            }
+            EncryptionMode::Encrypted => {
+                block.encrypt(out, block_buf, abs_sector, partition, format)
+            }
+        }
+    } else {
+        block.copy_raw(out, block_buf, abs_sector, disc_header, format)
+    }
+}
+
 impl Seek for DiscReader {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         self.pos = match pos {
             SeekFrom::Start(v) => v,
-            SeekFrom::End(_) => {
-                return Err(io::Error::new(
-                    io::ErrorKind::Unsupported,
-                    "BlockIOReader: SeekFrom::End is not supported".to_string(),
-                ));
-            }
+            SeekFrom::End(v) => self.size.saturating_add_signed(v),
             SeekFrom::Current(v) => self.pos.saturating_add_signed(v),
         };
         Ok(self.pos)
     }
 }
 
-fn read_partition_info(reader: &mut DiscReader) -> Result<Vec<PartitionInfo>> {
+fn read_partition_info(
+    reader: &mut DiscReader,
+    options: &OpenOptions,
+) -> Result<Vec<PartitionInfo>> {
+    let default_keys;
+    let keys = match &options.keys {
+        Some(keys) => keys.as_ref(),
+        None => {
+            default_keys = KeyStore::default();
+            &default_keys
+        }
+    };
     reader.seek(SeekFrom::Start(WII_PART_GROUP_OFF)).context("Seeking to partition groups")?;
     let part_groups: [WiiPartGroup; 4] = read_from(reader).context("Reading partition groups")?;
     let mut part_info = Vec::new();
@@ -244,7 +307,7 @@ fn read_partition_info(reader: &mut DiscReader) -> Result<Vec<PartitionInfo>> {
             let header: Box<WiiPartitionHeader> = read_box(reader)
                 .with_context(|| format!("Reading partition header {group_idx}:{part_idx}"))?;
 
-            let key = header.ticket.decrypt_title_key()?;
+            let key = header.ticket.decrypt_title_key_with(keys)?;
             let start_offset = entry.offset();
             if start_offset % SECTOR_SIZE as u64 != 0 {
                 return Err(Error::DiscFormat(format!(
@@ -310,6 +373,7 @@ fn guess_disc_size(part_info: &[PartitionInfo]) -> u64 {
     } else if max_offset < SL_DVD_SIZE {
         SL_DVD_SIZE
     } else {
-        DL_DVD_SIZE
+        // Never cut off partition data
+        DL_DVD_SIZE.max(max_offset)
     }
 }
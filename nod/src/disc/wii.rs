@@ -13,18 +13,18 @@ use crate::{
     array_ref,
     disc::{
         gcn::{read_part_meta, PartitionGC},
-        PartitionBase, PartitionKind, PartitionMeta, SECTOR_SIZE,
+        PartitionBase, PartitionMeta, SECTOR_SIZE,
     },
     fst::{Node, NodeKind},
     io::{
-        aes_decrypt,
+        aes_decrypt, aes_encrypt,
         block::{Block, BlockIO, PartitionInfo},
         KeyBytes,
     },
     static_assert,
     streams::{ReadStream, SharedWindowedReadStream},
     util::{div_rem, read::read_box_slice},
-    DiscHeader, Error, OpenOptions, Result, ResultContext,
+    DiscHeader, Error, Format, HashLevel, KeyStore, OpenOptions, Result, ResultContext,
 };
 
 /// Size in bytes of the hashes block in a Wii disc sector
@@ -33,30 +33,6 @@ pub(crate) const HASHES_SIZE: usize = 0x400;
 /// Size in bytes of the data block in a Wii disc sector (excluding hashes)
 pub(crate) const SECTOR_DATA_SIZE: usize = SECTOR_SIZE - HASHES_SIZE; // 0x7C00
 
-// ppki (Retail)
-const RVL_CERT_ISSUER_PPKI_TICKET: &str = "Root-CA00000001-XS00000003";
-#[rustfmt::skip]
-const RETAIL_COMMON_KEYS: [KeyBytes; 3] = [
-    /* RVL_KEY_RETAIL */
-    [0xeb, 0xe4, 0x2a, 0x22, 0x5e, 0x85, 0x93, 0xe4, 0x48, 0xd9, 0xc5, 0x45, 0x73, 0x81, 0xaa, 0xf7],
-    /* RVL_KEY_KOREAN */
-    [0x63, 0xb8, 0x2b, 0xb4, 0xf4, 0x61, 0x4e, 0x2e, 0x13, 0xf2, 0xfe, 0xfb, 0xba, 0x4c, 0x9b, 0x7e],
-    /* vWii_KEY_RETAIL */
-    [0x30, 0xbf, 0xc7, 0x6e, 0x7c, 0x19, 0xaf, 0xbb, 0x23, 0x16, 0x33, 0x30, 0xce, 0xd7, 0xc2, 0x8d],
-];
-
-// dpki (Debug)
-const RVL_CERT_ISSUER_DPKI_TICKET: &str = "Root-CA00000002-XS00000006";
-#[rustfmt::skip]
-const DEBUG_COMMON_KEYS: [KeyBytes; 3] = [
-    /* RVL_KEY_DEBUG */
-    [0xa1, 0x60, 0x4a, 0x6a, 0x71, 0x23, 0xb5, 0x29, 0xae, 0x8b, 0xec, 0x32, 0xc8, 0x16, 0xfc, 0xaa],
-    /* RVL_KEY_KOREAN_DEBUG */
-    [0x67, 0x45, 0x8b, 0x6b, 0xc6, 0x23, 0x7b, 0x32, 0x69, 0x98, 0x3c, 0x64, 0x73, 0x48, 0x33, 0x66],
-    /* vWii_KEY_DEBUG */
-    [0x2f, 0x5c, 0x1b, 0x29, 0x44, 0xe7, 0xfd, 0x6f, 0xc3, 0x97, 0x96, 0x4b, 0x05, 0x76, 0x91, 0xfa],
-];
-
 #[derive(Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
 #[repr(C, align(4))]
 pub(crate) struct WiiPartEntry {
@@ -70,18 +46,6 @@ impl WiiPartEntry {
     pub(crate) fn offset(&self) -> u64 { (self.offset.get() as u64) << 2 }
 }
 
-#[derive(Debug, PartialEq)]
-pub(crate) struct WiiPartInfo {
-    pub(crate) group_idx: u32,
-    pub(crate) part_idx: u32,
-    pub(crate) offset: u64,
-    pub(crate) kind: PartitionKind,
-    pub(crate) header: WiiPartitionHeader,
-    pub(crate) junk_id: [u8; 4],
-    pub(crate) junk_start: u64,
-    pub(crate) title_key: KeyBytes,
-}
-
 pub(crate) const WII_PART_GROUP_OFF: u64 = 0x40000;
 
 #[derive(Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
@@ -97,102 +61,213 @@ impl WiiPartGroup {
     pub(crate) fn part_entry_off(&self) -> u64 { (self.part_entry_off.get() as u64) << 2 }
 }
 
+/// Signature block preceding signed data. (Ticket, TMD)
 #[derive(Debug, Clone, PartialEq, FromBytes, FromZeroes, AsBytes)]
-#[repr(C, align(4))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
 pub struct SignedHeader {
     /// Signature type, always 0x00010001 (RSA-2048)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub sig_type: U32,
     /// RSA-2048 signature
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub sig: [u8; 256],
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
     _pad: [u8; 60],
 }
 
 static_assert!(size_of::<SignedHeader>() == 0x140);
 
+/// A ticket time limit.
 #[derive(Debug, Clone, PartialEq, Default, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct TicketTimeLimit {
+    /// Whether the time limit is enabled
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub enable_time_limit: U32,
+    /// Time limit in seconds
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub time_limit: U32,
 }
 
 static_assert!(size_of::<TicketTimeLimit>() == 8);
 
+/// Wii ticket, containing the encrypted title key. (ticket.bin)
 #[derive(Debug, Clone, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct Ticket {
+    /// Signature block
     pub header: SignedHeader,
+    /// Signature issuer (e.g. Root-CA00000001-XS00000003)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::cstr"))]
     pub sig_issuer: [u8; 64],
+    /// ECDH data
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub ecdh: [u8; 60],
+    /// Ticket format version
     pub version: u8,
-    _pad1: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
+    _pad1: [u8; 2],
+    /// Title key, encrypted with the common key
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub title_key: KeyBytes,
-    _pad2: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
+    _pad2: [u8; 1],
+    /// Ticket ID
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub ticket_id: [u8; 8],
+    /// Console ID
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub console_id: [u8; 4],
+    /// Title ID
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub title_id: [u8; 8],
-    _pad3: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
+    _pad3: [u8; 2],
+    /// Title version
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub ticket_title_version: U16,
+    /// Permitted titles mask
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub permitted_titles_mask: U32,
+    /// Permit mask
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub permit_mask: U32,
+    /// Whether the title can be exported
     pub title_export_allowed: u8,
+    /// Common key index (0 = normal, 1 = Korean, 2 = vWii)
     pub common_key_idx: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     _pad4: [u8; 48],
+    /// Content access permissions, one bit per content index
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub content_access_permissions: [u8; 64],
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     _pad5: [u8; 2],
+    /// Time limits
     pub time_limits: [TicketTimeLimit; 8],
 }
 
 static_assert!(size_of::<Ticket>() == 0x2A4);
 
 impl Ticket {
-    pub fn decrypt_title_key(&self) -> Result<KeyBytes> {
+    /// The ticket's signature issuer. (e.g. `Root-CA00000001-XS00000003`)
+    pub fn issuer(&self) -> Option<&str> {
+        CStr::from_bytes_until_nul(&self.sig_issuer).ok().and_then(|c| c.to_str().ok())
+    }
+
+    /// The common key used to encrypt the title key, selected by the issuer and key index.
+    fn common_key<'a>(&self, keys: &'a KeyStore) -> Result<&'a KeyBytes> {
+        let Some(issuer) = self.issuer() else {
+            return Err(Error::DiscFormat("failed to parse certificate issuer".to_string()));
+        };
+        keys.common_key(issuer, self.common_key_idx).ok_or_else(|| {
+            Error::DiscFormat(format!(
+                "unknown common key {} for certificate issuer {:?}",
+                self.common_key_idx, issuer
+            ))
+        })
+    }
+
+    fn title_key_iv(&self) -> KeyBytes {
         let mut iv: KeyBytes = [0; 16];
         iv[..8].copy_from_slice(&self.title_id);
-        let cert_issuer_ticket =
-            CStr::from_bytes_until_nul(&self.sig_issuer).ok().and_then(|c| c.to_str().ok());
-        let common_keys = match cert_issuer_ticket {
-            Some(RVL_CERT_ISSUER_PPKI_TICKET) => &RETAIL_COMMON_KEYS,
-            Some(RVL_CERT_ISSUER_DPKI_TICKET) => &DEBUG_COMMON_KEYS,
-            Some(v) => {
-                return Err(Error::DiscFormat(format!("unknown certificate issuer {:?}", v)));
-            }
-            None => {
-                return Err(Error::DiscFormat("failed to parse certificate issuer".to_string()));
-            }
-        };
-        let common_key = common_keys.get(self.common_key_idx as usize).ok_or(Error::DiscFormat(
-            format!("unknown common key index {}", self.common_key_idx),
-        ))?;
+        iv
+    }
+
+    /// Decrypts the title key using the built-in common key selected by the issuer and
+    /// key index.
+    pub fn decrypt_title_key(&self) -> Result<KeyBytes> {
+        self.decrypt_title_key_with(&KeyStore::default())
+    }
+
+    /// Decrypts the title key using the common key from `keys` selected by the issuer and
+    /// key index.
+    pub fn decrypt_title_key_with(&self, keys: &KeyStore) -> Result<KeyBytes> {
         let mut title_key = self.title_key;
-        aes_decrypt(common_key, iv, &mut title_key);
+        aes_decrypt(self.common_key(keys)?, self.title_key_iv(), &mut title_key);
         Ok(title_key)
     }
+
+    /// Encrypts and stores the title key using the built-in common key selected by the
+    /// issuer and key index.
+    pub fn encrypt_title_key(&mut self, title_key: KeyBytes) -> Result<()> {
+        self.encrypt_title_key_with(title_key, &KeyStore::default())
+    }
+
+    /// Encrypts and stores the title key using the common key from `keys` selected by the
+    /// issuer and key index.
+    pub fn encrypt_title_key_with(&mut self, title_key: KeyBytes, keys: &KeyStore) -> Result<()> {
+        let mut encrypted = title_key;
+        aes_encrypt(self.common_key(keys)?, self.title_key_iv(), &mut encrypted);
+        self.title_key = encrypted;
+        Ok(())
+    }
 }
 
+/// Wii title metadata (TMD) header. Followed by the content records. (tmd.bin)
+///
+/// See [`Tmd`](crate::Tmd) for a view including the content records.
 #[derive(Debug, Clone, PartialEq, FromBytes, FromZeroes, AsBytes)]
-#[repr(C, align(4))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
 pub struct TmdHeader {
+    /// Signature block
     pub header: SignedHeader,
+    /// Signature issuer (e.g. Root-CA00000001-CP00000004)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::cstr"))]
     pub sig_issuer: [u8; 64],
+    /// TMD format version
     pub version: u8,
+    /// CA CRL version
     pub ca_crl_version: u8,
+    /// Signer CRL version
     pub signer_crl_version: u8,
+    /// Whether this is a vWii title
     pub is_vwii: u8,
+    /// Required IOS title ID (e.g. 00000001-00000024 for IOS36)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub ios_id: [u8; 8],
+    /// Title ID
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub title_id: [u8; 8],
-    pub title_type: u32,
+    /// Title type
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub title_type: U32,
+    /// Group ID
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub group_id: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     _pad1: [u8; 2],
+    /// Region (0 = Japan, 1 = USA, 2 = Europe, 3 = region free, 4 = Korea)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub region: U16,
+    /// Age ratings
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub ratings: KeyBytes,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     _pad2: [u8; 12],
+    /// IPC mask
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub ipc_mask: [u8; 12],
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     _pad3: [u8; 18],
+    /// Access rights (bit 0: AHBPROT disabled, bit 1: DVD video access)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub access_flags: U32,
+    /// Title version
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub title_version: U16,
+    /// Number of content records
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub num_contents: U16,
+    /// Boot content index
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub boot_idx: U16,
+    /// Minor version
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub minor_version: U16,
 }
 
@@ -201,15 +276,23 @@ static_assert!(size_of::<TmdHeader>() == 0x1E4);
 pub const H3_TABLE_SIZE: usize = 0x18000;
 
 #[derive(Debug, Clone, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct WiiPartitionHeader {
     pub ticket: Ticket,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     tmd_size: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     tmd_off: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     cert_chain_size: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     cert_chain_off: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     h3_table_off: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     data_off: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     data_size: U32,
 }
 
@@ -235,6 +318,7 @@ impl WiiPartitionHeader {
 
 pub struct PartitionWii {
     io: Box<dyn BlockIO>,
+    format: Format,
     partition: PartitionInfo,
     block: Block,
     block_buf: Box<[u8]>,
@@ -252,6 +336,7 @@ impl Clone for PartitionWii {
     fn clone(&self) -> Self {
         Self {
             io: self.io.clone(),
+            format: self.format,
             partition: self.partition.clone(),
             block: Block::default(),
             block_buf: <u8>::new_box_slice_zeroed(self.block_buf.len()),
@@ -275,10 +360,10 @@ impl PartitionWii {
         options: &OpenOptions,
     ) -> Result<Box<Self>> {
         let block_size = inner.block_size();
-        let mut reader = PartitionGC::new(inner, disc_header)?;
-
         // Read TMD, cert chain, and H3 table
         let offset = partition.start_sector as u64 * SECTOR_SIZE as u64;
+        let end = partition.data_end_sector as u64 * SECTOR_SIZE as u64;
+        let mut reader = PartitionGC::new(inner, disc_header, Some(end))?;
         reader
             .seek(SeekFrom::Start(offset + partition.header.tmd_off()))
             .context("Seeking to TMD offset")?;
@@ -296,8 +381,10 @@ impl PartitionWii {
         let raw_h3_table: Box<[u8]> =
             read_box_slice(&mut reader, H3_TABLE_SIZE).context("Reading H3 table")?;
 
+        let io = reader.into_inner();
         Ok(Box::new(Self {
-            io: reader.into_inner(),
+            format: io.meta().format,
+            io,
             partition: partition.clone(),
             block: Block::default(),
             block_buf: <u8>::new_box_slice_zeroed(block_size as usize),
@@ -313,6 +400,14 @@ impl PartitionWii {
     }
 }
 
+impl PartitionWii {
+    /// The partition data size with hashes removed.
+    fn size(&self) -> u64 {
+        (self.partition.data_end_sector - self.partition.data_start_sector) as u64
+            * SECTOR_DATA_SIZE as u64
+    }
+}
+
 impl Read for PartitionWii {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let part_sector = (self.pos / SECTOR_DATA_SIZE as u64) as u32;
@@ -337,9 +432,15 @@ impl Read for PartitionWii {
                 self.block_buf.as_ref(),
                 abs_sector,
                 &self.partition,
+                self.format,
             )?;
             if self.verify {
-                verify_hashes(self.sector_buf.as_ref(), part_sector, self.raw_h3_table.as_ref())?;
+                verify_hashes(
+                    self.sector_buf.as_ref(),
+                    self.partition.index,
+                    part_sector,
+                    self.raw_h3_table.as_ref(),
+                )?;
             }
             self.sector = abs_sector;
         }
@@ -357,86 +458,53 @@ impl Seek for PartitionWii {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         self.pos = match pos {
             SeekFrom::Start(v) => v,
-            SeekFrom::End(_) => {
-                return Err(io::Error::new(
-                    io::ErrorKind::Unsupported,
-                    "WiiPartitionReader: SeekFrom::End is not supported".to_string(),
-                ));
-            }
+            SeekFrom::End(v) => self.size().saturating_add_signed(v),
             SeekFrom::Current(v) => self.pos.saturating_add_signed(v),
         };
         Ok(self.pos)
     }
 }
 
-#[inline(always)]
-pub(crate) fn as_digest(slice: &[u8; 20]) -> digest::Output<Sha1> { (*slice).into() }
-
-fn verify_hashes(buf: &[u8; SECTOR_SIZE], part_sector: u32, h3_table: &[u8]) -> io::Result<()> {
+fn verify_hashes(
+    buf: &[u8; SECTOR_SIZE],
+    partition: usize,
+    part_sector: u32,
+    h3_table: &[u8],
+) -> io::Result<()> {
     let (cluster, sector) = div_rem(part_sector as usize, 8);
     let (group, sub_group) = div_rem(cluster, 8);
+    let check = |level: HashLevel, index: usize, data: &[u8], expected: &[u8; 20]| {
+        let actual: [u8; 20] = Sha1::digest(data).into();
+        if actual != *expected {
+            return Err(Error::HashMismatch {
+                level,
+                partition,
+                sector: part_sector,
+                index: index as u32,
+                expected: *expected,
+                actual,
+            });
+        }
+        Ok(())
+    };
 
     // H0 hashes
     for i in 0..31 {
-        let mut hash = Sha1::new();
-        hash.update(array_ref![buf, (i + 1) * 0x400, 0x400]);
-        let expected = as_digest(array_ref![buf, i * 20, 20]);
-        let output = hash.finalize();
-        if output != expected {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Invalid H0 hash! (block {:?}) {:x}\n\texpected {:x}", i, output, expected),
-            ));
-        }
+        let data = array_ref![buf, (i + 1) * 0x400, 0x400];
+        check(HashLevel::H0, i, data, array_ref![buf, i * 20, 20])?;
     }
 
     // H1 hash
-    {
-        let mut hash = Sha1::new();
-        hash.update(array_ref![buf, 0, 0x26C]);
-        let expected = as_digest(array_ref![buf, 0x280 + sector * 20, 20]);
-        let output = hash.finalize();
-        if output != expected {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "Invalid H1 hash! (subgroup {:?}) {:x}\n\texpected {:x}",
-                    sector, output, expected
-                ),
-            ));
-        }
-    }
+    let expected = array_ref![buf, 0x280 + sector * 20, 20];
+    check(HashLevel::H1, sector, array_ref![buf, 0, 0x26C], expected)?;
 
     // H2 hash
-    {
-        let mut hash = Sha1::new();
-        hash.update(array_ref![buf, 0x280, 0xA0]);
-        let expected = as_digest(array_ref![buf, 0x340 + sub_group * 20, 20]);
-        let output = hash.finalize();
-        if output != expected {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "Invalid H2 hash! (group {:?}) {:x}\n\texpected {:x}",
-                    sub_group, output, expected
-                ),
-            ));
-        }
-    }
+    let expected = array_ref![buf, 0x340 + sub_group * 20, 20];
+    check(HashLevel::H2, sub_group, array_ref![buf, 0x280, 0xA0], expected)?;
 
     // H3 hash
-    {
-        let mut hash = Sha1::new();
-        hash.update(array_ref![buf, 0x340, 0xA0]);
-        let expected = as_digest(array_ref![h3_table, group * 20, 20]);
-        let output = hash.finalize();
-        if output != expected {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Invalid H3 hash! {:x}\n\texpected {:x}", output, expected),
-            ));
-        }
-    }
+    let expected = array_ref![h3_table, group * 20, 20];
+    check(HashLevel::H3, group, array_ref![buf, 0x340, 0xA0], expected)?;
 
     Ok(())
 }
@@ -452,10 +520,12 @@ impl PartitionBase for PartitionWii {
         Ok(meta)
     }
 
-    fn open_file(&mut self, node: &Node) -> io::Result<SharedWindowedReadStream> {
+    fn open_file(&mut self, node: &Node) -> io::Result<SharedWindowedReadStream<'_>> {
         assert_eq!(node.kind(), NodeKind::File);
         self.new_window(node.offset(true), node.length())
     }
 
     fn ideal_buffer_size(&self) -> usize { SECTOR_DATA_SIZE }
+
+    fn data_size(&self) -> Option<u64> { Some(self.size()) }
 }
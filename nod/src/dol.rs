@@ -0,0 +1,484 @@
+//! Dolphin executable (DOL) types
+
+use std::{fmt, mem::size_of};
+
+use zerocopy::{big_endian::*, AsBytes, FromBytes, FromZeroes};
+
+use crate::{
+    disc::{DOL_MAX_DATA_SECTIONS, DOL_MAX_TEXT_SECTIONS},
+    static_assert, DolHeader, PartitionMeta,
+};
+
+/// The kind of a DOL section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DolSectionKind {
+    /// Executable code.
+    Text,
+    /// Initialized data.
+    Data,
+    /// Zero-initialized data, not stored in the file.
+    Bss,
+}
+
+impl fmt::Display for DolSectionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Data => write!(f, "data"),
+            Self::Bss => write!(f, "bss"),
+        }
+    }
+}
+
+/// A section of a DOL executable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DolSection {
+    /// The kind of section.
+    pub kind: DolSectionKind,
+    /// The index of the section within its kind.
+    ///
+    /// For text and data sections, this is the slot in the DOL header.
+    pub index: usize,
+    /// The virtual address of the section.
+    pub address: u32,
+    /// The size of the section in bytes.
+    pub size: u32,
+    /// The file offset of the section data. Zero for BSS.
+    pub offset: u32,
+}
+
+impl DolSection {
+    /// Whether the section contains the virtual address.
+    pub fn contains(&self, address: u32) -> bool {
+        address >= self.address && address - self.address < self.size
+    }
+
+    /// The name used for the section when exporting to ELF. (e.g. `.text0`, `.data3`, `.bss0`)
+    pub fn name(&self) -> String { format!(".{}{}", self.kind, self.index) }
+}
+
+/// A view into a DOL executable.
+///
+/// # Examples
+///
+/// ```no_run
+/// use nod::{Disc, PartitionKind};
+///
+/// fn main() -> nod::Result<()> {
+///     let disc = Disc::new("path/to/file.iso")?;
+///     let mut partition = disc.open_partition_kind(PartitionKind::Data)?;
+///     let meta = partition.meta()?;
+///     let dol = meta.dol()?;
+///     for section in dol.sections() {
+///         println!("{} {:#010X} {:#X}", section.name(), section.address, section.size);
+///     }
+///     std::fs::write("main.elf", dol.to_elf()).expect("Failed to write ELF");
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Dol<'a> {
+    /// The DOL header.
+    pub header: &'a DolHeader,
+    /// The full DOL file, including the header.
+    pub data: &'a [u8],
+    sections: Vec<DolSection>,
+}
+
+impl<'a> Dol<'a> {
+    /// Creates a new DOL view from a buffer, checking that all sections are in bounds.
+    pub fn new(data: &'a [u8]) -> Result<Self, String> {
+        let Some(header) = DolHeader::ref_from_prefix(data) else {
+            return Err(format!("DOL: file too small for header ({:#X} bytes)", data.len()));
+        };
+        let mut sections = Vec::new();
+        let text = (0..DOL_MAX_TEXT_SECTIONS).map(|i| {
+            (
+                DolSectionKind::Text,
+                i,
+                header.text_offs[i],
+                header.text_addrs[i],
+                header.text_sizes[i],
+            )
+        });
+        let data_sections = (0..DOL_MAX_DATA_SECTIONS).map(|i| {
+            (
+                DolSectionKind::Data,
+                i,
+                header.data_offs[i],
+                header.data_addrs[i],
+                header.data_sizes[i],
+            )
+        });
+        for (kind, index, offset, address, size) in text.chain(data_sections) {
+            let section = DolSection {
+                kind,
+                index,
+                address: address.get(),
+                size: size.get(),
+                offset: offset.get(),
+            };
+            if section.size == 0 {
+                continue;
+            }
+            if section.offset as u64 + section.size as u64 > data.len() as u64 {
+                return Err(format!(
+                    "DOL: {} data out of bounds (offset {:#X}, size {:#X}, file size {:#X})",
+                    section.name(),
+                    section.offset,
+                    section.size,
+                    data.len()
+                ));
+            }
+            sections.push(section);
+        }
+
+        // Data sections (e.g. .sdata) can be placed within the BSS range. Only the remaining
+        // gaps are zero-initialized.
+        let bss_start = header.bss_addr.get() as u64;
+        let bss_end = bss_start + header.bss_size.get() as u64;
+        let mut occupied = sections
+            .iter()
+            .map(|s| (s.address as u64, s.address as u64 + s.size as u64))
+            .filter(|&(start, end)| start < bss_end && end > bss_start)
+            .collect::<Vec<_>>();
+        occupied.sort_unstable();
+        let mut cursor = bss_start;
+        let mut bss = Vec::new();
+        for (start, end) in occupied.into_iter().chain([(bss_end, bss_end)]) {
+            if start > cursor {
+                bss.push((cursor, start.min(bss_end)));
+            }
+            cursor = cursor.max(end);
+        }
+        for (index, (start, end)) in bss.into_iter().enumerate() {
+            sections.push(DolSection {
+                kind: DolSectionKind::Bss,
+                index,
+                address: start as u32,
+                size: (end - start) as u32,
+                offset: 0,
+            });
+        }
+        Ok(Self { header, data, sections })
+    }
+
+    /// The entry point address.
+    pub fn entry_point(&self) -> u32 { self.header.entry_point.get() }
+
+    /// The non-empty text and data sections, followed by the BSS ranges.
+    ///
+    /// BSS ranges exclude any text or data sections placed within the BSS area of the header.
+    pub fn sections(&self) -> &[DolSection] { &self.sections }
+
+    /// The file data of a section. Empty for BSS.
+    pub fn section_data(&self, section: &DolSection) -> &'a [u8] {
+        match section.kind {
+            DolSectionKind::Bss => &[],
+            _ => &self.data[section.offset as usize..(section.offset + section.size) as usize],
+        }
+    }
+
+    /// Finds the section containing a virtual address.
+    pub fn section_at(&self, address: u32) -> Option<&DolSection> {
+        self.sections.iter().find(|s| s.contains(address))
+    }
+
+    /// Converts a virtual address to a file offset.
+    ///
+    /// Returns `None` if the address is not within a text or data section.
+    pub fn virtual_to_offset(&self, address: u32) -> Option<u32> {
+        self.section_at(address)
+            .filter(|s| s.kind != DolSectionKind::Bss)
+            .map(|s| s.offset + (address - s.address))
+    }
+
+    /// Converts a file offset to a virtual address.
+    ///
+    /// Returns `None` if the offset is not within a text or data section.
+    pub fn offset_to_virtual(&self, offset: u32) -> Option<u32> {
+        self.sections
+            .iter()
+            .filter(|s| s.kind != DolSectionKind::Bss)
+            .find(|s| offset >= s.offset && offset - s.offset < s.size)
+            .map(|s| s.address + (offset - s.offset))
+    }
+
+    /// Exports the DOL as a 32-bit big-endian PowerPC ELF executable.
+    ///
+    /// Each section becomes a loadable segment and a named section header. No symbols
+    /// are included.
+    pub fn to_elf(&self) -> Vec<u8> {
+        let num_sections = self.sections.len();
+        let ph_offset = size_of::<Elf32Header>();
+        let mut offset = ph_offset + num_sections * size_of::<Elf32ProgramHeader>();
+
+        // Section name table: null, section names, .shstrtab
+        let mut shstrtab = vec![0u8];
+        let mut name_offsets = Vec::with_capacity(num_sections);
+        for section in &self.sections {
+            name_offsets.push(shstrtab.len() as u32);
+            shstrtab.extend_from_slice(section.name().as_bytes());
+            shstrtab.push(0);
+        }
+        let shstrtab_name = shstrtab.len() as u32;
+        shstrtab.extend_from_slice(b".shstrtab\0");
+
+        let mut program_headers = Vec::with_capacity(num_sections);
+        let mut section_headers = vec![Elf32SectionHeader::new_zeroed()];
+        let mut body = Vec::new();
+        for (section, &name) in self.sections.iter().zip(&name_offsets) {
+            let (flags, sh_type, sh_flags, file_size) = match section.kind {
+                DolSectionKind::Text => {
+                    (PF_R | PF_X, SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, section.size)
+                }
+                DolSectionKind::Data => {
+                    (PF_R | PF_W, SHT_PROGBITS, SHF_ALLOC | SHF_WRITE, section.size)
+                }
+                DolSectionKind::Bss => (PF_R | PF_W, SHT_NOBITS, SHF_ALLOC | SHF_WRITE, 0),
+            };
+            // Loaders require the file offset and address to be congruent modulo the alignment
+            let padding = (section.address as usize).wrapping_sub(offset) % SECTION_ALIGN as usize;
+            offset += padding;
+            body.resize(body.len() + padding, 0);
+            program_headers.push(Elf32ProgramHeader {
+                p_type: U32::new(PT_LOAD),
+                p_offset: U32::new(offset as u32),
+                p_vaddr: U32::new(section.address),
+                p_paddr: U32::new(section.address),
+                p_filesz: U32::new(file_size),
+                p_memsz: U32::new(section.size),
+                p_flags: U32::new(flags),
+                p_align: U32::new(SECTION_ALIGN),
+            });
+            section_headers.push(Elf32SectionHeader {
+                sh_name: U32::new(name),
+                sh_type: U32::new(sh_type),
+                sh_flags: U32::new(sh_flags),
+                sh_addr: U32::new(section.address),
+                sh_offset: U32::new(offset as u32),
+                sh_size: U32::new(section.size),
+                sh_link: U32::new(0),
+                sh_info: U32::new(0),
+                sh_addralign: U32::new(SECTION_ALIGN),
+                sh_entsize: U32::new(0),
+            });
+            body.extend_from_slice(self.section_data(section));
+            offset += file_size as usize;
+        }
+
+        section_headers.push(Elf32SectionHeader {
+            sh_name: U32::new(shstrtab_name),
+            sh_type: U32::new(SHT_STRTAB),
+            sh_offset: U32::new(offset as u32),
+            sh_size: U32::new(shstrtab.len() as u32),
+            sh_addralign: U32::new(1),
+            ..Elf32SectionHeader::new_zeroed()
+        });
+        body.extend_from_slice(&shstrtab);
+        offset += shstrtab.len();
+        let padding = offset.next_multiple_of(4) - offset;
+        body.resize(body.len() + padding, 0);
+        offset += padding;
+
+        let header = Elf32Header {
+            e_ident: [
+                0x7F,
+                b'E',
+                b'L',
+                b'F',
+                ELFCLASS32,
+                ELFDATA2MSB,
+                EV_CURRENT,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ],
+            e_type: U16::new(ET_EXEC),
+            e_machine: U16::new(EM_PPC),
+            e_version: U32::new(EV_CURRENT as u32),
+            e_entry: U32::new(self.entry_point()),
+            e_phoff: U32::new(ph_offset as u32),
+            e_shoff: U32::new(offset as u32),
+            e_flags: U32::new(0),
+            e_ehsize: U16::new(size_of::<Elf32Header>() as u16),
+            e_phentsize: U16::new(size_of::<Elf32ProgramHeader>() as u16),
+            e_phnum: U16::new(program_headers.len() as u16),
+            e_shentsize: U16::new(size_of::<Elf32SectionHeader>() as u16),
+            e_shnum: U16::new(section_headers.len() as u16),
+            e_shstrndx: U16::new(section_headers.len() as u16 - 1),
+        };
+
+        let mut out = Vec::with_capacity(offset + section_headers.len() * 40);
+        out.extend_from_slice(header.as_bytes());
+        out.extend_from_slice(program_headers.as_bytes());
+        out.extend_from_slice(&body);
+        out.extend_from_slice(section_headers.as_bytes());
+        out
+    }
+}
+
+impl PartitionMeta {
+    /// A view into the DOL, with parsed sections.
+    pub fn dol(&self) -> Result<Dol<'_>, String> { Dol::new(&self.raw_dol) }
+}
+
+const SECTION_ALIGN: u32 = 32;
+
+const ELFCLASS32: u8 = 1;
+const ELFDATA2MSB: u8 = 2;
+const EV_CURRENT: u8 = 1;
+const ET_EXEC: u16 = 2;
+const EM_PPC: u16 = 20;
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+const SHT_PROGBITS: u32 = 1;
+const SHT_STRTAB: u32 = 3;
+const SHT_NOBITS: u32 = 8;
+const SHF_WRITE: u32 = 1;
+const SHF_ALLOC: u32 = 2;
+const SHF_EXECINSTR: u32 = 4;
+
+#[derive(Clone, FromBytes, FromZeroes, AsBytes)]
+#[repr(C)]
+struct Elf32Header {
+    e_ident: [u8; 16],
+    e_type: U16,
+    e_machine: U16,
+    e_version: U32,
+    e_entry: U32,
+    e_phoff: U32,
+    e_shoff: U32,
+    e_flags: U32,
+    e_ehsize: U16,
+    e_phentsize: U16,
+    e_phnum: U16,
+    e_shentsize: U16,
+    e_shnum: U16,
+    e_shstrndx: U16,
+}
+
+static_assert!(size_of::<Elf32Header>() == 0x34);
+
+#[derive(Clone, FromBytes, FromZeroes, AsBytes)]
+#[repr(C)]
+struct Elf32ProgramHeader {
+    p_type: U32,
+    p_offset: U32,
+    p_vaddr: U32,
+    p_paddr: U32,
+    p_filesz: U32,
+    p_memsz: U32,
+    p_flags: U32,
+    p_align: U32,
+}
+
+static_assert!(size_of::<Elf32ProgramHeader>() == 0x20);
+
+#[derive(Clone, FromBytes, FromZeroes, AsBytes)]
+#[repr(C)]
+struct Elf32SectionHeader {
+    sh_name: U32,
+    sh_type: U32,
+    sh_flags: U32,
+    sh_addr: U32,
+    sh_offset: U32,
+    sh_size: U32,
+    sh_link: U32,
+    sh_info: U32,
+    sh_addralign: U32,
+    sh_entsize: U32,
+}
+
+static_assert!(size_of::<Elf32SectionHeader>() == 0x28);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dol() -> Vec<u8> {
+        let mut header = DolHeader::new_zeroed();
+        header.text_offs[0] = U32::new(0x100);
+        header.text_addrs[0] = U32::new(0x80003100);
+        header.text_sizes[0] = U32::new(0x20);
+        header.data_offs[0] = U32::new(0x120);
+        header.data_addrs[0] = U32::new(0x80004000);
+        header.data_sizes[0] = U32::new(0x10);
+        // .sdata within the BSS range
+        header.data_offs[1] = U32::new(0x130);
+        header.data_addrs[1] = U32::new(0x80005010);
+        header.data_sizes[1] = U32::new(0x8);
+        header.bss_addr = U32::new(0x80005000);
+        header.bss_size = U32::new(0x40);
+        header.entry_point = U32::new(0x80003100);
+        let mut data = header.as_bytes().to_vec();
+        data.extend((0..0x38u8).map(|i| i + 1));
+        data
+    }
+
+    #[test]
+    fn test_sections() {
+        let data = test_dol();
+        let dol = Dol::new(&data).unwrap();
+        let sections =
+            dol.sections().iter().map(|s| (s.name(), s.address, s.size)).collect::<Vec<_>>();
+        assert_eq!(sections, [
+            (".text0".to_string(), 0x80003100, 0x20),
+            (".data0".to_string(), 0x80004000, 0x10),
+            (".data1".to_string(), 0x80005010, 0x8),
+            (".bss0".to_string(), 0x80005000, 0x10),
+            (".bss1".to_string(), 0x80005018, 0x28),
+        ]);
+        assert_eq!(dol.virtual_to_offset(0x80004004), Some(0x124));
+        assert_eq!(dol.virtual_to_offset(0x80005020), None);
+        assert_eq!(dol.offset_to_virtual(0x134), Some(0x80005014));
+        assert_eq!(dol.section_data(&dol.sections()[2]), &data[0x130..0x138]);
+
+        let mut truncated = data.clone();
+        truncated.truncate(0x130);
+        assert!(Dol::new(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_elf() {
+        let data = test_dol();
+        let dol = Dol::new(&data).unwrap();
+        let elf = dol.to_elf();
+        let header = Elf32Header::read_from_prefix(&elf).unwrap();
+        assert_eq!(header.e_machine.get(), EM_PPC);
+        assert_eq!(header.e_entry.get(), 0x80003100);
+        assert_eq!(header.e_phnum.get(), 5);
+        assert_eq!(header.e_shnum.get(), 7);
+        let phdrs = Elf32ProgramHeader::slice_from_prefix(&elf[header.e_phoff.get() as usize..], 5)
+            .unwrap()
+            .0;
+        for (phdr, section) in phdrs.iter().zip(dol.sections()) {
+            let offset = phdr.p_offset.get() as usize;
+            assert_eq!(offset as u32 % SECTION_ALIGN, phdr.p_vaddr.get() % SECTION_ALIGN);
+            assert_eq!(
+                &elf[offset..offset + phdr.p_filesz.get() as usize],
+                dol.section_data(section)
+            );
+        }
+        let shdrs = Elf32SectionHeader::slice_from_prefix(&elf[header.e_shoff.get() as usize..], 7)
+            .unwrap()
+            .0;
+        let strtab = &shdrs[6];
+        let names = &elf[strtab.sh_offset.get() as usize..][..strtab.sh_size.get() as usize];
+        let name = |shdr: &Elf32SectionHeader| {
+            let start = shdr.sh_name.get() as usize;
+            let end = start + names[start..].iter().position(|&b| b == 0).unwrap();
+            std::str::from_utf8(&names[start..end]).unwrap().to_string()
+        };
+        assert_eq!(name(&shdrs[1]), ".text0");
+        assert_eq!(name(&shdrs[6]), ".shstrtab");
+    }
+}
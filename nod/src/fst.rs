@@ -9,6 +9,7 @@ use crate::{static_assert, Result};
 
 /// File system node kind.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NodeKind {
     /// Node is a file.
     File,
@@ -20,12 +21,16 @@ pub enum NodeKind {
 
 /// An individual file system node.
 #[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct Node {
     kind: u8,
     // u24 big-endian
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::u24"))]
     name_offset: [u8; 3],
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     offset: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     length: U32,
 }
 
@@ -41,6 +46,9 @@ impl Node {
         }
     }
 
+    /// The raw node kind value.
+    pub(crate) fn raw_kind(&self) -> u8 { self.kind }
+
     /// Whether the node is a file.
     pub fn is_file(&self) -> bool { self.kind == 0 }
 
@@ -72,6 +80,7 @@ impl Node {
 }
 
 /// A view into the file system table (FST).
+#[derive(Clone, Copy)]
 pub struct Fst<'a> {
     /// The nodes in the FST.
     pub nodes: &'a [Node],
@@ -96,10 +105,87 @@ impl<'a> Fst<'a> {
     }
 
     /// Iterate over the nodes in the FST.
-    pub fn iter(&self) -> FstIter { FstIter { fst: self, idx: 1 } }
+    pub fn iter(&self) -> FstIter<'a> { FstIter { fst: *self, idx: 1 } }
 
-    /// Get the name of a node.
-    pub fn get_name(&self, node: &Node) -> Result<Cow<str>, String> {
+    /// Builds an owned directory tree from the FST, with full paths and parent / child links
+    /// for every node.
+    pub fn tree(&self) -> Result<FstTree, String> {
+        let Some(root) = self.nodes.first() else {
+            return Err("FST: root node not found".to_string());
+        };
+        let mut entries = Vec::with_capacity(self.nodes.len());
+        entries.push(FstEntry {
+            index: 0,
+            node: root.clone(),
+            name: String::new(),
+            path: String::new(),
+            depth: 0,
+            parent: None,
+            children: Vec::new(),
+        });
+        // Stack of open directories and their end indices
+        let mut dirs = vec![(0usize, self.nodes.len())];
+        for (idx, node, name) in self.iter() {
+            while dirs.len() > 1 && dirs.last().is_some_and(|&(_, end)| end <= idx) {
+                dirs.pop();
+            }
+            let (parent, parent_end) = *dirs.last().unwrap(); // Safe: root is never removed
+            let name = name?.into_owned();
+            let path = if parent == 0 {
+                name.clone()
+            } else {
+                format!("{}/{}", entries[parent].path, name)
+            };
+            entries[parent].children.push(idx);
+            entries.push(FstEntry {
+                index: idx,
+                node: node.clone(),
+                name,
+                path,
+                depth: dirs.len(),
+                parent: Some(parent),
+                children: Vec::new(),
+            });
+            if node.is_dir() {
+                // Clamp malformed directory ranges to the parent's range
+                dirs.push((idx, (node.length() as usize).min(parent_end)));
+            }
+        }
+        Ok(FstTree { entries })
+    }
+
+    /// Finds the index of the directory containing a node.
+    ///
+    /// Returns `None` for the root node or an out of bounds index.
+    pub fn parent_of(&self, index: usize) -> Option<usize> {
+        if index == 0 || index >= self.nodes.len() {
+            return None;
+        }
+        // The closest preceding directory whose range includes the node
+        (0..index)
+            .rev()
+            .find(|&i| self.nodes[i].is_dir() && self.nodes[i].length() as usize > index)
+    }
+
+    /// Builds the full path of a node, separated by `/` and without a leading slash.
+    ///
+    /// The root node has an empty path.
+    pub fn path_of(&self, index: usize) -> Result<String, String> {
+        if index >= self.nodes.len() {
+            return Err(format!("FST: node index {} out of bounds", index));
+        }
+        let mut names = Vec::new();
+        let mut current = index;
+        while current != 0 {
+            names.push(self.get_name(&self.nodes[current])?);
+            current = self.parent_of(current).unwrap_or(0);
+        }
+        names.reverse();
+        Ok(names.join("/"))
+    }
+
+    /// Get the raw (Shift-JIS encoded) name of a node, without the null terminator.
+    pub fn get_raw_name(&self, node: &Node) -> Result<&'a [u8], String> {
         let name_buf = self.string_table.get(node.name_offset() as usize..).ok_or_else(|| {
             format!(
                 "FST: name offset {} out of bounds (string table size: {})",
@@ -110,7 +196,12 @@ impl<'a> Fst<'a> {
         let c_string = CStr::from_bytes_until_nul(name_buf).map_err(|_| {
             format!("FST: name at offset {} not null-terminated", node.name_offset())
         })?;
-        let (decoded, _, errors) = SHIFT_JIS.decode(c_string.to_bytes());
+        Ok(c_string.to_bytes())
+    }
+
+    /// Get the name of a node.
+    pub fn get_name(&self, node: &Node) -> Result<Cow<'a, str>, String> {
+        let (decoded, _, errors) = SHIFT_JIS.decode(self.get_raw_name(node)?);
         if errors {
             return Err(format!("FST: Failed to decode name at offset {}", node.name_offset()));
         }
@@ -118,14 +209,16 @@ impl<'a> Fst<'a> {
     }
 
     /// Finds a particular file or directory by path.
-    pub fn find(&self, path: &str) -> Option<(usize, &Node)> {
+    ///
+    /// Names are compared ASCII case-insensitively, and the first match is returned.
+    /// Use [`Fst::find_all`] to select the comparison and detect ambiguous paths.
+    pub fn find(&self, path: &str) -> Option<(usize, &'a Node)> {
         let mut split = path.trim_matches('/').split('/');
         let mut current = split.next()?;
         let mut idx = 1;
         let mut stop_at = None;
         while let Some(node) = self.nodes.get(idx) {
-            if self.get_name(node).as_ref().map_or(false, |name| name.eq_ignore_ascii_case(current))
-            {
+            if self.get_name(node).as_ref().is_ok_and(|name| name.eq_ignore_ascii_case(current)) {
                 if let Some(next) = split.next() {
                     current = next;
                 } else {
@@ -149,11 +242,139 @@ impl<'a> Fst<'a> {
         }
         None
     }
+
+    /// Finds all files and directories matching a path, in FST order.
+    ///
+    /// Names are compared with the decoded result of [`Fst::get_name`]. More than one result
+    /// means that the path is ambiguous under the selected comparison, for example when a
+    /// directory contains names differing only in case.
+    pub fn find_all(&self, path: &str, mode: NameMatch) -> Vec<(usize, &'a Node)> {
+        let components = path.trim_matches('/').split('/').collect::<Vec<_>>();
+        self.find_matching(components.len(), |node, i| {
+            self.get_name(node).is_ok_and(|name| mode.is_match(&name, components[i]))
+        })
+    }
+
+    /// Finds all files and directories matching a raw (Shift-JIS encoded) path, in FST order.
+    ///
+    /// Names are compared with the raw result of [`Fst::get_raw_name`]. ASCII case-insensitive
+    /// comparison only folds single-byte characters, leaving double-byte characters intact.
+    /// Unicode case-insensitive comparison decodes both names first.
+    pub fn find_all_raw(&self, path: &[u8], mode: NameMatch) -> Vec<(usize, &'a Node)> {
+        let path = trim_slashes(path);
+        let components = path.split(|&b| b == b'/').collect::<Vec<_>>();
+        self.find_matching(components.len(), |node, i| {
+            self.get_raw_name(node).is_ok_and(|name| mode.is_match_raw(name, components[i]))
+        })
+    }
+
+    /// Walks every directory whose path matches so far, collecting nodes that match all
+    /// `count` path components.
+    fn find_matching<F>(&self, count: usize, mut is_match: F) -> Vec<(usize, &'a Node)>
+    where F: FnMut(&Node, usize) -> bool {
+        let mut results = Vec::new();
+        // Stack of (path component, first node, end node)
+        let mut stack = vec![(0usize, 1usize, self.nodes.len())];
+        while let Some((component, mut idx, end)) = stack.pop() {
+            while idx < end {
+                let node = &self.nodes[idx];
+                let next = if node.is_dir() {
+                    (node.length() as usize).clamp(idx + 1, end)
+                } else {
+                    idx + 1
+                };
+                if is_match(node, component) {
+                    if component + 1 == count {
+                        results.push((idx, node));
+                    } else if node.is_dir() {
+                        stack.push((component + 1, idx + 1, next));
+                    }
+                }
+                idx = next;
+            }
+        }
+        results.sort_by_key(|&(idx, _)| idx);
+        results
+    }
+}
+
+/// Name comparison used by [`Fst::find_all`] and [`Fst::find_all_raw`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum NameMatch {
+    /// Names must be identical.
+    Exact,
+    /// ASCII letters are compared case-insensitively. This is the behavior of [`Fst::find`].
+    #[default]
+    AsciiCaseInsensitive,
+    /// All letters are compared case-insensitively, using Unicode lowercase mappings.
+    /// (e.g. full-width `Ａ` matches `ａ`)
+    UnicodeCaseInsensitive,
+}
+
+impl NameMatch {
+    /// Whether two decoded names match.
+    pub fn is_match(self, a: &str, b: &str) -> bool {
+        match self {
+            NameMatch::Exact => a == b,
+            NameMatch::AsciiCaseInsensitive => a.eq_ignore_ascii_case(b),
+            NameMatch::UnicodeCaseInsensitive => {
+                a.chars().flat_map(char::to_lowercase).eq(b.chars().flat_map(char::to_lowercase))
+            }
+        }
+    }
+
+    /// Whether two raw (Shift-JIS encoded) names match.
+    pub fn is_match_raw(self, a: &[u8], b: &[u8]) -> bool {
+        match self {
+            NameMatch::Exact => a == b,
+            NameMatch::AsciiCaseInsensitive => sjis_eq_ignore_ascii_case(a, b),
+            NameMatch::UnicodeCaseInsensitive => {
+                let (a, _, a_errors) = SHIFT_JIS.decode(a);
+                let (b, _, b_errors) = SHIFT_JIS.decode(b);
+                !a_errors && !b_errors && self.is_match(&a, &b)
+            }
+        }
+    }
+}
+
+/// Compares Shift-JIS strings, ignoring the case of single-byte ASCII letters only.
+/// The trail byte of a double-byte character can fall within the ASCII letter range.
+fn sjis_eq_ignore_ascii_case(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if matches!(a[i], 0x81..=0x9F | 0xE0..=0xFC) {
+            // Double-byte character, compare exactly
+            let end = (i + 2).min(a.len());
+            if a[i..end] != b[i..end] {
+                return false;
+            }
+            i = end;
+        } else {
+            if !a[i].eq_ignore_ascii_case(&b[i]) {
+                return false;
+            }
+            i += 1;
+        }
+    }
+    true
+}
+
+fn trim_slashes(mut path: &[u8]) -> &[u8] {
+    while let [b'/', rest @ ..] = path {
+        path = rest;
+    }
+    while let [rest @ .., b'/'] = path {
+        path = rest;
+    }
+    path
 }
 
 /// Iterator over the nodes in an FST.
 pub struct FstIter<'a> {
-    fst: &'a Fst<'a>,
+    fst: Fst<'a>,
     idx: usize,
 }
 
@@ -168,3 +389,156 @@ impl<'a> Iterator for FstIter<'a> {
         Some((idx, node, name))
     }
 }
+
+/// A file system node with its location in the directory tree.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FstEntry {
+    /// The node's index in the FST.
+    pub index: usize,
+    /// The file system node.
+    pub node: Node,
+    /// The decoded name. Empty for the root node.
+    pub name: String,
+    /// The full path, separated by `/` and without a leading slash. Empty for the root node.
+    pub path: String,
+    /// The nesting depth. The root node has a depth of 0, its children a depth of 1.
+    pub depth: usize,
+    /// The index of the parent directory. `None` for the root node.
+    pub parent: Option<usize>,
+    /// The indices of the direct children, in FST order. Empty for files.
+    pub children: Vec<usize>,
+}
+
+/// An owned directory tree built from the file system table, created by [`Fst::tree`].
+///
+/// Entries are stored in FST order and indexed by node index.
+#[derive(Clone, Debug)]
+pub struct FstTree {
+    entries: Vec<FstEntry>,
+}
+
+impl FstTree {
+    /// The root directory entry.
+    pub fn root(&self) -> &FstEntry { &self.entries[0] }
+
+    /// Gets an entry by node index.
+    pub fn get(&self, index: usize) -> Option<&FstEntry> { self.entries.get(index) }
+
+    /// The parent directory of an entry.
+    pub fn parent(&self, entry: &FstEntry) -> Option<&FstEntry> {
+        entry.parent.and_then(|idx| self.entries.get(idx))
+    }
+
+    /// Iterates over the direct children of an entry.
+    pub fn children<'a>(&'a self, entry: &'a FstEntry) -> impl Iterator<Item = &'a FstEntry> + 'a {
+        entry.children.iter().filter_map(move |&idx| self.entries.get(idx))
+    }
+
+    /// Iterates over all entries except the root, in FST order.
+    ///
+    /// Directories are always visited before their contents.
+    pub fn iter(&self) -> std::slice::Iter<'_, FstEntry> { self.entries[1..].iter() }
+
+    /// The number of entries, including the root.
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    /// Whether the tree only contains the root.
+    pub fn is_empty(&self) -> bool { self.entries.len() <= 1 }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Creates a node with a 24-bit name offset.
+    pub(crate) fn node(kind: u8, name_offset: u32, offset: u32, length: u32) -> Node {
+        let name_offset = name_offset.to_be_bytes();
+        Node {
+            kind,
+            name_offset: [name_offset[1], name_offset[2], name_offset[3]],
+            offset: U32::new(offset),
+            length: U32::new(length),
+        }
+    }
+
+    #[test]
+    fn test_tree() {
+        // /a.bin, /dir/b.bin, /dir/sub/c.bin, /d.bin
+        let nodes = [
+            node(1, 0, 0, 7),
+            node(0, 0, 0, 1),
+            node(1, 6, 0, 6),
+            node(0, 10, 0, 1),
+            node(1, 16, 2, 6),
+            node(0, 20, 0, 1),
+            node(0, 26, 0, 1),
+        ];
+        let mut buf = nodes.as_bytes().to_vec();
+        buf.extend_from_slice(b"a.bin\0dir\0b.bin\0sub\0c.bin\0d.bin\0");
+        let fst = Fst::new(&buf).unwrap();
+        let tree = fst.tree().unwrap();
+        let paths = tree.iter().map(|e| (e.path.as_str(), e.depth, e.parent)).collect::<Vec<_>>();
+        assert_eq!(paths, [
+            ("a.bin", 1, Some(0)),
+            ("dir", 1, Some(0)),
+            ("dir/b.bin", 2, Some(2)),
+            ("dir/sub", 2, Some(2)),
+            ("dir/sub/c.bin", 3, Some(4)),
+            ("d.bin", 1, Some(0)),
+        ]);
+        assert_eq!(tree.root().children, [1, 2, 6]);
+        assert_eq!(tree.get(2).unwrap().children, [3, 4]);
+        for entry in tree.iter() {
+            assert_eq!(fst.path_of(entry.index).unwrap(), entry.path);
+            assert_eq!(fst.parent_of(entry.index), entry.parent);
+        }
+    }
+
+    #[test]
+    fn test_find_all() {
+        // /Dir/a.bin, /dir/a.bin, /dir/アa.bin, /Ａ.bin
+        // "ア" is 0x83 0x41 in Shift-JIS, "Ａ" is 0x82 0x60
+        let mut strings = Vec::new();
+        let mut name = |s: &[u8]| {
+            let offset = strings.len() as u32;
+            strings.extend_from_slice(s);
+            strings.push(0);
+            offset
+        };
+        let nodes = [
+            node(1, name(b""), 0, 7),
+            node(1, name(b"Dir"), 0, 3),
+            node(0, name(b"a.bin"), 0, 1),
+            node(1, name(b"dir"), 0, 6),
+            node(0, name(b"a.bin"), 0, 1),
+            node(0, name(b"\x83\x41a.bin"), 0, 1),
+            node(0, name(b"\x82\x60.bin"), 0, 1),
+        ];
+        let mut buf = nodes.as_bytes().to_vec();
+        buf.extend_from_slice(&strings);
+        let fst = Fst::new(&buf).unwrap();
+        let indices = |results: Vec<(usize, &Node)>| {
+            results.into_iter().map(|(idx, _)| idx).collect::<Vec<_>>()
+        };
+
+        assert_eq!(indices(fst.find_all("/dir/a.bin", NameMatch::Exact)), [4]);
+        assert_eq!(indices(fst.find_all("/DIR/A.BIN", NameMatch::AsciiCaseInsensitive)), [2, 4]);
+        assert_eq!(indices(fst.find_all("/dir/アA.bin", NameMatch::AsciiCaseInsensitive)), [5]);
+        assert!(indices(fst.find_all("/ａ.BIN", NameMatch::AsciiCaseInsensitive)).is_empty());
+        assert_eq!(indices(fst.find_all("/ａ.BIN", NameMatch::UnicodeCaseInsensitive)), [6]);
+
+        assert_eq!(indices(fst.find_all_raw(b"dir/\x83\x41a.bin", NameMatch::Exact)), [5]);
+        // The trail byte 0x41 ("A") must not be folded: 0x83 0x61 is "ヂ"
+        assert!(indices(fst.find_all_raw(b"DIR/\x83\x61A.BIN", NameMatch::AsciiCaseInsensitive))
+            .is_empty());
+        assert_eq!(
+            indices(fst.find_all_raw(b"/DIR/\x83\x41A.BIN", NameMatch::AsciiCaseInsensitive)),
+            [5]
+        );
+        assert_eq!(
+            indices(fst.find_all_raw(b"\x82\x81.bin", NameMatch::UnicodeCaseInsensitive)),
+            [6]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+//! Wii banner (opening.bnr) types
+
+use std::{
+    fmt, io,
+    io::{Cursor, Read},
+    mem::size_of,
+};
+
+use zerocopy::{big_endian::*, FromBytes, FromZeroes};
+
+use crate::{
+    static_assert, util::compress::decompress_lz77, Error, PartitionBase, PartitionMeta, Result,
+    ResultContext, Tpl, U8Archive,
+};
+
+/// IMET magic.
+pub const IMET_MAGIC: [u8; 4] = *b"IMET";
+/// IMD5 magic.
+const IMD5_MAGIC: [u8; 4] = *b"IMD5";
+/// Size of the IMD5 header preceding compressed banner resources.
+const IMD5_HEADER_SIZE: usize = 0x20;
+/// Number of channel name languages.
+const NUM_LANGUAGES: usize = 10;
+/// Maximum length of a channel name in UTF-16 code units.
+const NAME_LENGTH: usize = 42;
+
+/// Channel name language, in IMET order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImetLanguage {
+    /// Japanese
+    Japanese,
+    /// English
+    English,
+    /// German
+    German,
+    /// French
+    French,
+    /// Spanish
+    Spanish,
+    /// Italian
+    Italian,
+    /// Dutch
+    Dutch,
+    /// Simplified Chinese
+    SimplifiedChinese,
+    /// Traditional Chinese
+    TraditionalChinese,
+    /// Korean
+    Korean,
+}
+
+impl ImetLanguage {
+    /// All languages, in IMET order.
+    pub const ALL: [ImetLanguage; NUM_LANGUAGES] = [
+        Self::Japanese,
+        Self::English,
+        Self::German,
+        Self::French,
+        Self::Spanish,
+        Self::Italian,
+        Self::Dutch,
+        Self::SimplifiedChinese,
+        Self::TraditionalChinese,
+        Self::Korean,
+    ];
+}
+
+impl fmt::Display for ImetLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Japanese => write!(f, "Japanese"),
+            Self::English => write!(f, "English"),
+            Self::German => write!(f, "German"),
+            Self::French => write!(f, "French"),
+            Self::Spanish => write!(f, "Spanish"),
+            Self::Italian => write!(f, "Italian"),
+            Self::Dutch => write!(f, "Dutch"),
+            Self::SimplifiedChinese => write!(f, "Simplified Chinese"),
+            Self::TraditionalChinese => write!(f, "Traditional Chinese"),
+            Self::Korean => write!(f, "Korean"),
+        }
+    }
+}
+
+#[derive(FromBytes, FromZeroes)]
+#[repr(C, align(4))]
+struct RawImetHeader {
+    magic: [u8; 4],
+    hash_size: U32,
+    version: U32,
+    icon_size: U32,
+    banner_size: U32,
+    sound_size: U32,
+    flags: U32,
+    names: [[U16; NAME_LENGTH]; NUM_LANGUAGES],
+    _pad: [u8; 0x24C],
+    md5: [u8; 16],
+}
+
+static_assert!(size_of::<RawImetHeader>() == 0x5C0);
+
+/// A resource archive (`icon.bin` or `banner.bin`) from a Wii banner.
+#[derive(Debug, Clone)]
+pub struct WiiBannerArchive {
+    /// The archive.
+    pub archive: U8Archive,
+    /// The decompressed archive data.
+    pub data: Box<[u8]>,
+}
+
+impl WiiBannerArchive {
+    fn new(data: &[u8], name: &str) -> Result<Self> {
+        let data = unwrap_resource(data).with_context(|| format!("Reading {}", name))?;
+        let archive = U8Archive::new(&mut Cursor::new(&data[..]))
+            .map_err(|e| Error::DiscFormat(format!("{}: {}", name, e)))?;
+        Ok(Self { archive, data: data.into_boxed_slice() })
+    }
+
+    /// Reads a file from the archive by path.
+    pub fn file(&self, path: &str) -> Option<&[u8]> {
+        let (_, node) = self.archive.find(path)?;
+        let start = node.offset(false) as usize;
+        self.data.get(start..start + node.length() as usize)
+    }
+
+    /// Parses all TPL textures in the archive, with their file names.
+    pub fn textures(&self) -> Result<Vec<(String, Tpl)>> {
+        let mut textures = Vec::new();
+        for (idx, node, name) in self.archive.iter() {
+            let name = name?;
+            if !node.is_file() || !name.to_ascii_lowercase().ends_with(".tpl") {
+                continue;
+            }
+            let start = node.offset(false) as usize;
+            let data = self
+                .data
+                .get(start..start + node.length() as usize)
+                .ok_or_else(|| format!("{}: file data out of bounds", name))?;
+            let path = self.archive.fst().path_of(idx)?;
+            let tpl = Tpl::new(data).map_err(|e| format!("{}: {}", path, e))?;
+            textures.push((path, tpl));
+        }
+        Ok(textures)
+    }
+}
+
+/// A parsed Wii banner. (opening.bnr, or the first content of a channel)
+///
+/// The IMET header contains the channel names, followed by a U8 archive holding
+/// `meta/icon.bin`, `meta/banner.bin` and `meta/sound.bin`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use nod::{Disc, ImetLanguage, PartitionKind, WiiBanner};
+///
+/// fn main() -> nod::Result<()> {
+///     let disc = Disc::new("path/to/file.iso")?;
+///     let mut partition = disc.open_partition_kind(PartitionKind::Data)?;
+///     let meta = partition.meta()?;
+///     if let Some(banner) = WiiBanner::read(partition.as_mut(), &meta)? {
+///         println!("{}", banner.name(ImetLanguage::English));
+///         for (path, tpl) in banner.icon.textures()? {
+///             let rgba = tpl.images[0].to_rgba8();
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WiiBanner {
+    /// Channel names, one per [`ImetLanguage`]. Empty if not provided.
+    pub names: Vec<String>,
+    /// Size of `icon.bin` according to the header.
+    pub icon_size: u32,
+    /// Size of `banner.bin` according to the header.
+    pub banner_size: u32,
+    /// Size of `sound.bin` according to the header.
+    pub sound_size: u32,
+    /// MD5 hash of the IMET header.
+    pub md5: [u8; 16],
+    /// The channel icon resources.
+    pub icon: WiiBannerArchive,
+    /// The banner resources.
+    pub banner: WiiBannerArchive,
+    /// The decompressed banner sound (usually BNS, WAV or AIFF).
+    pub sound: Box<[u8]>,
+}
+
+impl WiiBanner {
+    /// Whether the data contains an IMET header.
+    pub fn is_imet(data: &[u8]) -> bool { find_imet(data).is_some() }
+
+    /// Parses a Wii banner.
+    ///
+    /// The IMET header may be preceded by a 0x40 or 0x80 byte build header, as in disc banners
+    /// and channel contents.
+    pub fn new(data: &[u8]) -> Result<Self> {
+        let offset = find_imet(data).ok_or("Banner: IMET header not found")?;
+        let header = RawImetHeader::ref_from_prefix(&data[offset..])
+            .ok_or("Banner: IMET header out of bounds")?;
+        let names = header
+            .names
+            .iter()
+            .map(|name| {
+                let len = name.iter().position(|c| c.get() == 0).unwrap_or(NAME_LENGTH);
+                String::from_utf16_lossy(&name[..len].iter().map(|c| c.get()).collect::<Vec<_>>())
+            })
+            .collect();
+
+        let archive_data = &data[offset + size_of::<RawImetHeader>()..];
+        let archive = U8Archive::new(&mut Cursor::new(archive_data))
+            .map_err(|e| Error::DiscFormat(format!("Banner: {}", e)))?;
+        let file = |path: &str| {
+            let (_, node) =
+                archive.find(path).ok_or_else(|| format!("Banner: {} not found", path))?;
+            let start = node.offset(false) as usize;
+            archive_data
+                .get(start..start + node.length() as usize)
+                .ok_or_else(|| format!("Banner: {} out of bounds", path))
+        };
+        let icon = WiiBannerArchive::new(file("meta/icon.bin")?, "icon.bin")?;
+        let banner = WiiBannerArchive::new(file("meta/banner.bin")?, "banner.bin")?;
+        let sound = unwrap_resource(file("meta/sound.bin")?).context("Reading sound.bin")?;
+        Ok(Self {
+            names,
+            icon_size: header.icon_size.get(),
+            banner_size: header.banner_size.get(),
+            sound_size: header.sound_size.get(),
+            md5: header.md5,
+            icon,
+            banner,
+            sound: sound.into_boxed_slice(),
+        })
+    }
+
+    /// Finds `opening.bnr` in the partition's file system and parses it.
+    ///
+    /// Returns `None` if the file doesn't exist.
+    pub fn read(partition: &mut dyn PartitionBase, meta: &PartitionMeta) -> Result<Option<Self>> {
+        let fst = meta.fst()?;
+        let Some((_, node)) = fst.find("opening.bnr") else {
+            return Ok(None);
+        };
+        let mut data = Vec::with_capacity(node.length() as usize);
+        partition
+            .open_file(node)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .context("Reading opening.bnr")?;
+        Ok(Some(Self::new(&data)?))
+    }
+
+    /// The channel name for a language. Empty if not provided.
+    pub fn name(&self, language: ImetLanguage) -> &str {
+        self.names.get(language as usize).map_or("", String::as_str)
+    }
+}
+
+/// Finds the IMET header after an optional build header.
+fn find_imet(data: &[u8]) -> Option<usize> {
+    [0x40, 0x80, 0].into_iter().find(|&offset| {
+        data.get(offset..offset + 4) == Some(&IMET_MAGIC)
+            && data.len() >= offset + size_of::<RawImetHeader>()
+    })
+}
+
+/// Strips the IMD5 header and decompresses LZ77 data, if present.
+fn unwrap_resource(data: &[u8]) -> io::Result<Vec<u8>> {
+    let data = match data.strip_prefix(&IMD5_MAGIC) {
+        Some(_) => data.get(IMD5_HEADER_SIZE..).unwrap_or_default(),
+        None => data,
+    };
+    if data.starts_with(b"LZ77") {
+        decompress_lz77(data)
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lz77() {
+        // "abcabcabcX": three literals, a back-reference of 6 bytes at distance 3, a literal
+        let data =
+            [b'L', b'Z', b'7', b'7', 0x10, 10, 0, 0, 0x10, b'a', b'b', b'c', 0x30, 0x02, b'X'];
+        assert_eq!(unwrap_resource(&data).unwrap(), b"abcabcabcX");
+        let mut imd5 = IMD5_MAGIC.to_vec();
+        imd5.resize(IMD5_HEADER_SIZE, 0);
+        imd5.extend_from_slice(&data);
+        assert_eq!(unwrap_resource(&imd5).unwrap(), b"abcabcabcX");
+        assert!(unwrap_resource(&data[..12]).is_err());
+    }
+}
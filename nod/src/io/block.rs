@@ -12,7 +12,8 @@ use crate::{
     },
     io::{aes_decrypt, aes_encrypt, KeyBytes, MagicBytes},
     util::{lfg::LaggedFibonacci, read::read_from},
-    DiscHeader, DiscMeta, Error, PartitionHeader, PartitionKind, Result, ResultContext,
+    DiscHeader, DiscMeta, Error, Format, OpenOptions, PartitionHeader, PartitionKind, Result,
+    ResultContext,
 };
 
 /// Block I/O trait for reading disc images.
@@ -42,21 +43,22 @@ pub trait BlockIO: DynClone + Send + Sync {
             let mut block_idx =
                 ((block as u64 * block_size as u64) / block_size_internal as u64) as u32;
             while offset < block_size as usize {
-                let block = self.read_block_internal(
+                let internal = self.read_block_internal(
                     &mut out[offset..offset + block_size_internal as usize],
                     block_idx,
                     partition,
                 )?;
                 if result.is_none() {
-                    result = Some(block);
-                } else if result != Some(block) {
-                    if block == Block::Zero {
+                    result = Some(internal);
+                } else if result != Some(internal) {
+                    if internal == Block::Zero {
                         out[offset..offset + block_size_internal as usize].fill(0);
                     } else {
-                        return Err(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            "Inconsistent block types in split block",
-                        ));
+                        return Err(Error::InconsistentBlockTypes {
+                            format: self.meta().format,
+                            block,
+                        }
+                        .into());
                     }
                 }
                 offset += block_size_internal as usize;
@@ -79,7 +81,7 @@ pub trait BlockIO: DynClone + Send + Sync {
 dyn_clone::clone_trait_object!(BlockIO);
 
 /// Creates a new [`BlockIO`] instance.
-pub fn open(filename: &Path) -> Result<Box<dyn BlockIO>> {
+pub fn open(filename: &Path, options: &OpenOptions) -> Result<Box<dyn BlockIO>> {
     let path_result = fs::canonicalize(filename);
     if let Err(err) = path_result {
         return Err(Error::Io(format!("Failed to open {}", filename.display()), err));
@@ -103,9 +105,10 @@ pub fn open(filename: &Path) -> Result<Box<dyn BlockIO>> {
         #[cfg(feature = "compress-zlib")]
         crate::io::gcz::GCZ_MAGIC => crate::io::gcz::DiscIOGCZ::new(path)?,
         crate::io::nfs::NFS_MAGIC => match path.parent() {
-            Some(parent) if parent.is_dir() => {
-                crate::io::nfs::DiscIONFS::new(path.parent().unwrap())?
-            }
+            Some(parent) if parent.is_dir() => crate::io::nfs::DiscIONFS::new(
+                path.parent().unwrap(),
+                options.keys.as_ref().and_then(|k| k.nfs_key()).copied(),
+            )?,
             _ => {
                 return Err(Error::DiscFormat("Failed to locate NFS parent directory".to_string()));
             }
@@ -137,6 +140,7 @@ pub fn open(filename: &Path) -> Result<Box<dyn BlockIO>> {
 
 /// Wii partition information.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PartitionInfo {
     /// The partition index.
     pub index: usize,
@@ -149,6 +153,7 @@ pub struct PartitionInfo {
     /// The end sector of the partition's (encrypted) data.
     pub data_end_sector: u32,
     /// The AES key for the partition, also known as the "title key".
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub key: KeyBytes,
     /// The Wii partition header.
     pub header: Box<WiiPartitionHeader>,
@@ -157,6 +162,7 @@ pub struct PartitionInfo {
     /// The partition header within the partition.
     pub partition_header: Box<PartitionHeader>,
     /// The hash table for the partition, if rebuilt.
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub hash_table: Option<HashTable>,
 }
 
@@ -185,15 +191,16 @@ impl Block {
         data: &[u8],
         abs_sector: u32,
         partition: &PartitionInfo,
+        format: Format,
     ) -> io::Result<()> {
         let part_sector = abs_sector - partition.data_start_sector;
         match self {
             Block::Raw => {
-                out.copy_from_slice(block_sector::<SECTOR_SIZE>(data, abs_sector)?);
+                out.copy_from_slice(block_sector::<SECTOR_SIZE>(data, abs_sector, format)?);
                 decrypt_sector(out, partition);
             }
             Block::PartDecrypted { has_hashes } => {
-                out.copy_from_slice(block_sector::<SECTOR_SIZE>(data, abs_sector)?);
+                out.copy_from_slice(block_sector::<SECTOR_SIZE>(data, abs_sector, format)?);
                 if !has_hashes {
                     rebuild_hash_block(out, part_sector, partition);
                 }
@@ -217,14 +224,15 @@ impl Block {
         data: &[u8],
         abs_sector: u32,
         partition: &PartitionInfo,
+        format: Format,
     ) -> io::Result<()> {
         let part_sector = abs_sector - partition.data_start_sector;
         match self {
             Block::Raw => {
-                out.copy_from_slice(block_sector::<SECTOR_SIZE>(data, abs_sector)?);
+                out.copy_from_slice(block_sector::<SECTOR_SIZE>(data, abs_sector, format)?);
             }
             Block::PartDecrypted { has_hashes } => {
-                out.copy_from_slice(block_sector::<SECTOR_SIZE>(data, abs_sector)?);
+                out.copy_from_slice(block_sector::<SECTOR_SIZE>(data, abs_sector, format)?);
                 if !has_hashes {
                     rebuild_hash_block(out, part_sector, partition);
                 }
@@ -251,16 +259,14 @@ impl Block {
         data: &[u8],
         abs_sector: u32,
         disc_header: &DiscHeader,
+        format: Format,
     ) -> io::Result<()> {
         match self {
             Block::Raw => {
-                out.copy_from_slice(block_sector::<SECTOR_SIZE>(data, abs_sector)?);
+                out.copy_from_slice(block_sector::<SECTOR_SIZE>(data, abs_sector, format)?);
             }
             Block::PartDecrypted { .. } => {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "Cannot copy decrypted data as raw",
-                ));
+                return Err(Error::UnexpectedDecryptedData { format, sector: abs_sector }.into());
             }
             Block::Junk => generate_junk(out, abs_sector, None, disc_header),
             Block::Zero => out.fill(0),
@@ -270,28 +276,18 @@ impl Block {
 }
 
 #[inline(always)]
-fn block_sector<const N: usize>(data: &[u8], sector_idx: u32) -> io::Result<&[u8; N]> {
-    if data.len() % N != 0 {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!("Expected block size {} to be a multiple of {}", data.len(), N),
-        ));
+fn block_sector<const N: usize>(
+    data: &[u8],
+    sector_idx: u32,
+    format: Format,
+) -> io::Result<&[u8; N]> {
+    // Block sizes are validated to be a multiple of the sector size on open
+    let sectors = (data.len() / N) as u32;
+    if sectors == 0 {
+        return Err(Error::SectorOutOfRange { format, sector: sector_idx, partition: None }.into());
     }
-    let rel_sector = sector_idx % (data.len() / N) as u32;
-    let offset = rel_sector as usize * N;
-    data.get(offset..offset + N)
-        .ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "Sector {} out of range (block size {}, sector size {})",
-                    rel_sector,
-                    data.len(),
-                    N
-                ),
-            )
-        })
-        .map(|v| unsafe { &*(v as *const [u8] as *const [u8; N]) })
+    let offset = (sector_idx % sectors) as usize * N;
+    Ok(data[offset..offset + N].try_into().unwrap())
 }
 
 fn generate_junk(
@@ -348,3 +344,72 @@ fn decrypt_sector(out: &mut [u8; SECTOR_SIZE], partition: &PartitionInfo) {
     aes_decrypt(&partition.key, [0u8; 16], &mut out[..HASHES_SIZE]);
     aes_decrypt(&partition.key, iv, &mut out[HASHES_SIZE..]);
 }
+
+#[cfg(test)]
+mod tests {
+    use zerocopy::FromZeroes;
+
+    use super::*;
+
+    /// Alternates between raw and junk blocks of half a sector.
+    #[derive(Clone)]
+    struct MixedIO;
+
+    impl BlockIO for MixedIO {
+        fn read_block_internal(
+            &mut self,
+            _out: &mut [u8],
+            block: u32,
+            _partition: Option<&PartitionInfo>,
+        ) -> io::Result<Block> {
+            Ok(if block % 2 == 0 { Block::Raw } else { Block::Junk })
+        }
+
+        fn block_size_internal(&self) -> u32 { SECTOR_SIZE as u32 / 2 }
+
+        fn meta(&self) -> DiscMeta { DiscMeta { format: Format::Ciso, ..Default::default() } }
+    }
+
+    fn unwrap_err(result: io::Result<impl Sized>) -> Error {
+        let err = result.err().expect("expected an error");
+        *err.into_inner().and_then(|e| e.downcast().ok()).expect("expected a structured error")
+    }
+
+    #[test]
+    fn test_inconsistent_block_types() {
+        let mut out = vec![0u8; SECTOR_SIZE];
+        let err = unwrap_err(MixedIO.read_block(&mut out, 3, None));
+        assert!(matches!(err, Error::InconsistentBlockTypes { format: Format::Ciso, block: 3 }));
+    }
+
+    #[test]
+    fn test_block_sector() {
+        let mut data = vec![0u8; SECTOR_SIZE * 2];
+        data[SECTOR_SIZE] = 1;
+        assert_eq!(block_sector::<SECTOR_SIZE>(&data, 5, Format::Wbfs).unwrap()[0], 1);
+        let err = unwrap_err(block_sector::<SECTOR_SIZE>(&data[..0x100], 5, Format::Wbfs));
+        assert!(matches!(err, Error::SectorOutOfRange {
+            format: Format::Wbfs,
+            sector: 5,
+            partition: None,
+        }));
+    }
+
+    #[test]
+    fn test_copy_decrypted_as_raw() {
+        let mut out = [0u8; SECTOR_SIZE];
+        let data = vec![0u8; SECTOR_SIZE];
+        let header = DiscHeader::new_zeroed();
+        let err = unwrap_err(Block::PartDecrypted { has_hashes: true }.copy_raw(
+            &mut out,
+            &data,
+            0x20,
+            &header,
+            Format::Wia,
+        ));
+        assert!(matches!(err, Error::UnexpectedDecryptedData {
+            format: Format::Wia,
+            sector: 0x20
+        }));
+    }
+}
@@ -8,7 +8,6 @@ use std::{
 use adler::adler32_slice;
 use miniz_oxide::{inflate, inflate::core::inflate_flags};
 use zerocopy::{little_endian::*, AsBytes, FromBytes, FromZeroes};
-use zstd::zstd_safe::WriteBuf;
 
 use crate::{
     io::{
@@ -106,24 +105,16 @@ impl BlockIO for DiscIOGCZ {
             ((self.block_map.get(block as usize + 1).unwrap_or(&self.header.compressed_size).get()
                 & !(1 << 63))
                 - file_offset) as usize;
-        if compressed_size > self.block_buf.len() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "Compressed block size exceeds block size: {} > {}",
-                    compressed_size,
-                    self.block_buf.len()
-                ),
-            ));
-        } else if !compressed && compressed_size != self.block_buf.len() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "Uncompressed block size does not match block size: {} != {}",
-                    compressed_size,
-                    self.block_buf.len()
-                ),
-            ));
+        if compressed_size > self.block_buf.len()
+            || (!compressed && compressed_size != self.block_buf.len())
+        {
+            return Err(Error::BlockSizeMismatch {
+                format: Format::Gcz,
+                block,
+                expected: self.block_buf.len() as u32,
+                actual: compressed_size as u32,
+            }
+            .into());
         }
 
         // Read block
@@ -134,13 +125,13 @@ impl BlockIO for DiscIOGCZ {
         let checksum = adler32_slice(&self.block_buf[..compressed_size]);
         let expected_checksum = self.block_hashes[block as usize].get();
         if checksum != expected_checksum {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "Block checksum mismatch: {:#010x} != {:#010x}",
-                    checksum, expected_checksum
-                ),
-            ));
+            return Err(Error::ChecksumMismatch {
+                format: Format::Gcz,
+                block,
+                expected: expected_checksum,
+                actual: checksum,
+            }
+            .into());
         }
 
         if compressed {
@@ -159,17 +150,19 @@ impl BlockIO for DiscIOGCZ {
                 || in_size != compressed_size
                 || out_size != self.block_buf.len()
             {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    format!(
+                return Err(Error::Decompression {
+                    format: Format::Gcz,
+                    group: block,
+                    message: format!(
                         "Deflate decompression failed: {:?} (in: {}, out: {})",
                         status, in_size, out_size
                     ),
-                ));
+                }
+                .into());
             }
         } else {
             // Copy uncompressed block
-            out.copy_from_slice(self.block_buf.as_slice());
+            out.copy_from_slice(&self.block_buf);
         }
         Ok(Block::Raw)
     }
@@ -187,3 +180,44 @@ impl BlockIO for DiscIOGCZ {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_block_size_mismatch() {
+        let mut data = GCZHeader {
+            magic: GCZ_MAGIC,
+            disc_type: U32::new(0),
+            compressed_size: U64::new(0x9000),
+            disc_size: U64::new(0x8000),
+            block_size: U32::new(0x8000),
+            block_count: U32::new(1),
+        }
+        .as_bytes()
+        .to_vec();
+        // Block map entry (uncompressed flag set) and hash
+        data.extend_from_slice(&(1u64 << 63).to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        let path = std::env::temp_dir().join(format!("nod-gcz-{}.gcz", std::process::id()));
+        fs::write(&path, &data).unwrap();
+        let result = DiscIOGCZ::new(&path).map(|mut io| {
+            let mut out = vec![0u8; 0x8000];
+            io.read_block(&mut out, 0, None)
+        });
+        fs::remove_file(&path).unwrap();
+        let err = result.unwrap().unwrap_err();
+        assert!(matches!(
+            Error::from_io(&err),
+            Some(Error::BlockSizeMismatch {
+                format: Format::Gcz,
+                block: 0,
+                expected: 0x8000,
+                actual: 0x9000,
+            })
+        ));
+    }
+}
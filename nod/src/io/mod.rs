@@ -24,6 +24,7 @@ pub(crate) type MagicBytes = [u8; 4];
 
 /// The disc file format.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Format {
     /// ISO / GCM (GameCube master disc)
     #[default]
@@ -58,6 +59,7 @@ impl fmt::Display for Format {
 
 /// The disc file format's compression algorithm.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Compression {
     /// No compression
     #[default]
@@ -92,6 +94,7 @@ impl fmt::Display for Compression {
 
 /// Extra metadata about the underlying disc file format.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DiscMeta {
     /// The disc file format.
     pub format: Format,
@@ -110,8 +113,10 @@ pub struct DiscMeta {
     /// The original disc's CRC32 hash, if stored by the format.
     pub crc32: Option<u32>,
     /// The original disc's MD5 hash, if stored by the format.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex_option"))]
     pub md5: Option<[u8; 16]>,
     /// The original disc's SHA-1 hash, if stored by the format.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex_option"))]
     pub sha1: Option<[u8; 20]>,
     /// The original disc's XXH64 hash, if stored by the format.
     pub xxhash64: Option<u64>,
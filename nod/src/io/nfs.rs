@@ -93,15 +93,16 @@ pub struct DiscIONFS {
 }
 
 impl DiscIONFS {
-    pub fn new(directory: &Path) -> Result<Box<Self>> {
+    /// Opens an NFS image. If `key` is not provided, it's loaded from `htk.bin`.
+    pub fn new(directory: &Path, key: Option<KeyBytes>) -> Result<Box<Self>> {
         let mut disc_io = Box::new(Self {
             inner: SplitFileReader::empty(),
             header: NFSHeader::new_zeroed(),
             raw_size: 0,
             disc_size: 0,
-            key: [0; 16],
+            key: key.unwrap_or_default(),
         });
-        disc_io.load_files(directory)?;
+        disc_io.load_files(directory, key.is_none())?;
         Ok(disc_io)
     }
 }
@@ -172,8 +173,8 @@ fn get_nfs(directory: &Path, num: u32) -> Result<PathBuf> {
 }
 
 impl DiscIONFS {
-    pub fn load_files(&mut self, directory: &Path) -> Result<()> {
-        {
+    pub fn load_files(&mut self, directory: &Path, load_key: bool) -> Result<()> {
+        if load_key {
             // Load key file
             let primary_key_path =
                 get_path(directory, ["..", "code", "htk.bin"].iter().collect::<PathBuf>());
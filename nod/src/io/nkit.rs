@@ -7,7 +7,7 @@ use crate::{
     disc::DL_DVD_SIZE,
     io::MagicBytes,
     util::read::{read_from, read_u16_be, read_u32_be, read_u64_be, read_vec},
-    DiscMeta,
+    DiscMeta, Error,
 };
 
 #[allow(unused)]
@@ -98,21 +98,13 @@ impl NKitHeader {
             || version_string[7] < b'1'
             || version_string[7] > b'9'
         {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Invalid NKit header version string",
-            ));
+            return Err(Error::UnsupportedNKitVersion { version: version_string }.into());
         }
         let version = version_string[7] - b'0';
         let header_size = match version {
             1 => calc_header_size(version, NKIT_HEADER_V1_FLAGS, 0) as u16,
             2 => read_u16_be(reader)?,
-            _ => {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    format!("Unsupported NKit header version: {}", version),
-                ));
-            }
+            _ => return Err(Error::UnsupportedNKitVersion { version: version_string }.into()),
         };
 
         let mut remaining_header_size = header_size as usize - 8;
@@ -167,3 +159,22 @@ impl NKitHeader {
         meta.xxhash64 = self.xxhash64;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_unsupported_version() {
+        for version in [b"NKIT  v3", b"NKIT  v0", b"NKIT vv1"] {
+            let mut data = version.to_vec();
+            data.resize(0x100, 0);
+            let err = NKitHeader::read_from(&mut data.as_slice(), 0x8000, false).unwrap_err();
+            assert!(matches!(
+                Error::from_io(&err),
+                Some(Error::UnsupportedNKitVersion { version: v }) if v == version
+            ));
+        }
+    }
+}
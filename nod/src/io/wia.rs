@@ -29,14 +29,19 @@ use crate::{
     DiscMeta, Error, Result, ResultContext,
 };
 
+/// WIA file magic.
 pub const WIA_MAGIC: MagicBytes = *b"WIA\x01";
+/// RVZ file magic.
 pub const RVZ_MAGIC: MagicBytes = *b"RVZ\x01";
 
 /// This struct is stored at offset 0x0 and is 0x48 bytes long. The wit source code says its format
 /// will never be changed.
 #[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct WIAFileHeader {
+    /// The file magic. ([WIA_MAGIC] or [RVZ_MAGIC])
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub magic: MagicBytes,
     /// The WIA format version.
     ///
@@ -48,28 +53,36 @@ pub struct WIAFileHeader {
     /// // If D != 0x00 && D != 0xff => append: 'beta' D
     /// //-----------------------------------------------------
     /// ```
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub version: U32,
     /// If the reading program supports the version of WIA indicated here, it can read the file.
     ///
     /// [version](Self::version) can be higher than `version_compatible`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub version_compatible: U32,
     /// The size of the [WIADisc] struct.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub disc_size: U32,
     /// The SHA-1 hash of the [WIADisc] struct.
     ///
     /// The number of bytes to hash is determined by [disc_size](Self::disc_size).
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub disc_hash: HashBytes,
     /// The original size of the ISO.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub iso_file_size: U64,
     /// The size of this file.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub wia_file_size: U64,
     /// The SHA-1 hash of this struct, up to but not including `file_head_hash` itself.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub file_head_hash: HashBytes,
 }
 
 static_assert!(size_of::<WIAFileHeader>() == 0x48);
 
 impl WIAFileHeader {
+    /// Checks the magic, header hash and version compatibility.
     pub fn validate(&self) -> Result<()> {
         // Check magic
         if self.magic != WIA_MAGIC && self.magic != RVZ_MAGIC {
@@ -80,19 +93,21 @@ impl WIAFileHeader {
         verify_hash(&bytes[..bytes.len() - size_of::<HashBytes>()], &self.file_head_hash)?;
         // Check version compatibility
         if self.version_compatible.get() < 0x30000 {
-            return Err(Error::DiscFormat(format!(
-                "WIA/RVZ version {:#X} is not supported",
-                self.version_compatible
-            )));
+            return Err(Error::UnsupportedVersion {
+                format: if self.is_rvz() { Format::Rvz } else { Format::Wia },
+                version: self.version_compatible.get(),
+            });
         }
         Ok(())
     }
 
+    /// Whether this is an RVZ file.
     pub fn is_rvz(&self) -> bool { self.magic == RVZ_MAGIC }
 }
 
 /// Disc kind
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DiscKind {
     /// GameCube disc
     GameCube,
@@ -114,10 +129,11 @@ impl TryFrom<u32> for DiscKind {
 
 /// Compression type
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WIACompression {
     /// No compression.
     None,
-    /// (WIA only) See [WIASegment]
+    /// (WIA only) Data is stored as segments of non-zero data.
     Purge,
     /// BZIP2 compression
     Bzip2,
@@ -149,11 +165,14 @@ const DISC_HEAD_SIZE: usize = 0x80;
 
 /// This struct is stored at offset 0x48, immediately after [WIAFileHeader].
 #[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct WIADisc {
     /// The disc type. (1 = GameCube, 2 = Wii)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub disc_type: U32,
     /// The compression type.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub compression: U32,
     /// The compression level used by the compressor.
     ///
@@ -161,7 +180,8 @@ pub struct WIADisc {
     ///
     /// RVZ only:
     /// > This is signed (instead of unsigned) to support negative compression levels in
-    ///   [Zstandard](WIACompression::Zstandard) (RVZ only).
+    /// > [Zstandard](WIACompression::Zstandard) (RVZ only).
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub compression_level: I32,
     /// The size of the chunks that data is divided into.
     ///
@@ -170,39 +190,51 @@ pub struct WIADisc {
     ///
     /// RVZ only:
     /// > Chunk sizes smaller than 2 MiB are supported. The following applies when using a chunk size
-    ///   smaller than 2 MiB:
+    /// > smaller than 2 MiB:
     /// > - The chunk size must be at least 32 KiB and must be a power of two. (Just like with WIA,
-    ///     sizes larger than 2 MiB do not have to be a power of two, they just have to be an integer
-    ///     multiple of 2 MiB.)
+    /// >   sizes larger than 2 MiB do not have to be a power of two, they just have to be an integer
+    /// >   multiple of 2 MiB.)
     /// > - For Wii partition data, each chunk contains one [WIAExceptionList] which contains
-    ///     exceptions for that chunk (and no other chunks). Offset 0 refers to the first hash of the
-    ///     current chunk, not the first hash of the full 2 MiB of data.
+    /// >   exceptions for that chunk (and no other chunks). Offset 0 refers to the first hash of the
+    /// >   current chunk, not the first hash of the full 2 MiB of data.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub chunk_size: U32,
     /// The first 0x80 bytes of the disc image.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub disc_head: [u8; DISC_HEAD_SIZE],
     /// The number of [WIAPartition] structs.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub num_partitions: U32,
     /// The size of one [WIAPartition] struct.
     ///
     /// If this is smaller than the size of [WIAPartition], fill the missing bytes with 0x00.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub partition_type_size: U32,
     /// The offset in the file where the [WIAPartition] structs are stored (uncompressed).
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub partition_offset: U64,
     /// The SHA-1 hash of the [WIAPartition] structs.
     ///
     /// The number of bytes to hash is determined by `num_partitions * partition_type_size`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub partition_hash: HashBytes,
     /// The number of [WIARawData] structs.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub num_raw_data: U32,
     /// The offset in the file where the [WIARawData] structs are stored (compressed).
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub raw_data_offset: U64,
     /// The total compressed size of the [WIARawData] structs.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub raw_data_size: U32,
     /// The number of [WIAGroup] structs.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub num_groups: U32,
     /// The offset in the file where the [WIAGroup] structs are stored (compressed).
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub group_offset: U64,
     /// The total compressed size of the [WIAGroup] structs.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub group_size: U32,
     /// The number of used bytes in the [compr_data](Self::compr_data) array.
     pub compr_data_len: u8,
@@ -218,12 +250,14 @@ pub struct WIADisc {
     /// For [Lzma](WIACompression::Lzma), the data is 5 bytes long. The first byte encodes the `lc`,
     /// `pb`, and `lp` parameters, and the four other bytes encode the dictionary size in little
     /// endian.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub compr_data: [u8; 7],
 }
 
 static_assert!(size_of::<WIADisc>() == 0xDC);
 
 impl WIADisc {
+    /// Checks the disc type, compression type and partition struct size.
     pub fn validate(&self) -> Result<()> {
         DiscKind::try_from(self.disc_type.get())?;
         WIACompression::try_from(self.compression.get())?;
@@ -237,30 +271,40 @@ impl WIADisc {
         Ok(())
     }
 
+    /// The compression type.
+    ///
+    /// Panics if the compression type is invalid. (See [validate](Self::validate))
     pub fn compression(&self) -> WIACompression {
         WIACompression::try_from(self.compression.get()).unwrap()
     }
 }
 
+/// A range of Wii partition data sectors. See [WIAPartition].
 #[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct WIAPartitionData {
     /// The sector on the disc at which this data starts.
     /// One sector is 32 KiB (or 31 KiB excluding hashes).
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub first_sector: U32,
     /// The number of sectors on the disc covered by this struct.
     /// One sector is 32 KiB (or 31 KiB excluding hashes).
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub num_sectors: U32,
     /// The index of the first [WIAGroup] struct that points to the data covered by this struct.
     /// The other [WIAGroup] indices follow sequentially.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub group_index: U32,
     /// The number of [WIAGroup] structs used for this data.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub num_groups: U32,
 }
 
 static_assert!(size_of::<WIAPartitionData>() == 0x10);
 
 impl WIAPartitionData {
+    /// Whether the given disc sector is covered by this struct.
     pub fn contains(&self, sector: u32) -> bool {
         let start = self.first_sector.get();
         sector >= start && sector < start + self.num_sectors.get()
@@ -278,12 +322,14 @@ impl WIAPartitionData {
 /// from scratch (see <https://wiibrew.org/wiki/Wii_Disc>), and must then apply the hash exceptions
 /// which are stored along with the data (see the [WIAExceptionList] section).
 #[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct WIAPartition {
     /// The title key for this partition (128-bit AES), which can be used for re-encrypting the
     /// partition data.
     ///
     /// This key can be used directly, without decrypting it using the Wii common key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub partition_key: KeyBytes,
     /// To quote the wit source code: `segment 0 is small and defined for management data (boot ..
     /// fst). segment 1 takes the remaining data.`
@@ -305,28 +351,38 @@ static_assert!(size_of::<WIAPartition>() == 0x30);
 /// the previous multiple of 0x8000 (and adding the equivalent amount to the size so that the end
 /// offset stays the same), not by special casing the first [WIARawData].
 #[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct WIARawData {
     /// The offset on the disc at which this data starts.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub raw_data_offset: U64,
     /// The number of bytes on the disc covered by this struct.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub raw_data_size: U64,
     /// The index of the first [WIAGroup] struct that points to the data covered by this struct.
     /// The other [WIAGroup] indices follow sequentially.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub group_index: U32,
     /// The number of [WIAGroup] structs used for this data.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub num_groups: U32,
 }
 
 impl WIARawData {
+    /// The disc offset at which this data starts, rounded down to a sector boundary.
     pub fn start_offset(&self) -> u64 { self.raw_data_offset.get() & !(SECTOR_SIZE as u64 - 1) }
 
+    /// The disc sector at which this data starts.
     pub fn start_sector(&self) -> u32 { (self.start_offset() / SECTOR_SIZE as u64) as u32 }
 
+    /// The disc offset at which this data ends.
     pub fn end_offset(&self) -> u64 { self.raw_data_offset.get() + self.raw_data_size.get() }
 
+    /// The disc sector at which this data ends.
     pub fn end_sector(&self) -> u32 { (self.end_offset() / SECTOR_SIZE as u64) as u32 }
 
+    /// Whether the given disc sector is covered by this struct.
     pub fn contains(&self, sector: u32) -> bool {
         sector >= self.start_sector() && sector < self.end_sector()
     }
@@ -343,39 +399,48 @@ impl WIARawData {
 /// or [WIARawData] contains less data than that if `num_sectors * 0x8000` (for [WIAPartitionData])
 /// or `raw_data_size` (for [WIARawData]) is not evenly divisible by `chunk_size`.
 #[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct WIAGroup {
     /// The offset in the file where the compressed data is stored.
     ///
     /// Stored as a `u32`, divided by 4.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub data_offset: U32,
     /// The size of the compressed data, including any [WIAExceptionList] structs. 0 is a special
     /// case meaning that every byte of the decompressed data is 0x00 and the [WIAExceptionList]
     /// structs (if there are supposed to be any) contain 0 exceptions.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub data_size: U32,
 }
 
 /// Compared to [WIAGroup], [RVZGroup] changes the meaning of the most significant bit of
 /// [data_size](Self::data_size) and adds one additional attribute.
 #[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(4))]
 pub struct RVZGroup {
     /// The offset in the file where the compressed data is stored, divided by 4.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub data_offset: U32,
     /// The most significant bit is 1 if the data is compressed using the compression method
     /// indicated in [WIADisc], and 0 if it is not compressed. The lower 31 bits are the size of
     /// the compressed data, including any [WIAExceptionList] structs. The lower 31 bits being 0 is
     /// a special case meaning that every byte of the decompressed and unpacked data is 0x00 and
     /// the [WIAExceptionList] structs (if there are supposed to be any) contain 0 exceptions.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub data_size_and_flag: U32,
     /// The size after decompressing but before decoding the RVZ packing.
     /// If this is 0, RVZ packing is not used for this group.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub rvz_packed_size: U32,
 }
 
 impl RVZGroup {
+    /// The size of the compressed data.
     pub fn data_size(&self) -> u32 { self.data_size_and_flag.get() & 0x7FFFFFFF }
 
+    /// Whether the data is compressed.
     pub fn is_compressed(&self) -> bool { self.data_size_and_flag.get() & 0x80000000 != 0 }
 }
 
@@ -405,6 +470,7 @@ impl From<&WIAGroup> for RVZGroup {
 /// covers the first 20 bytes of the padding area and one which covers the last 20 bytes of the
 /// padding area, generating 12 bytes of overlap between the [WIAException] structs.
 #[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C, align(2))]
 pub struct WIAException {
     /// The offset among the hashes. The offsets 0x0000-0x0400 here map to the offsets 0x0000-0x0400
@@ -412,12 +478,14 @@ pub struct WIAException {
     /// in the full 2 MiB of data, and so on.
     ///
     /// The offsets start over at 0 for each new [WIAExceptionList].
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
     pub offset: U16,
     /// The hash that the automatically generated hash at the given offset needs to be replaced
     /// with.
     ///
     /// The replacement should happen after calculating all hashes for the current 2 MiB of data
     /// but before encrypting the hashes.
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
     pub hash: HashBytes,
 }
 
@@ -439,7 +507,7 @@ pub struct WIAException {
 /// Somewhat ironically, there are exceptions to how [WIAExceptionList] structs are handled:
 ///
 /// For the compression method [Purge](WIACompression::Purge), the [WIAExceptionList] structs are
-/// stored uncompressed (in other words, before the first [WIASegment]). For
+/// stored uncompressed (in other words, before the first purge segment). For
 /// [Bzip2](WIACompression::Bzip2), [Lzma](WIACompression::Lzma) and [Lzma2](WIACompression::Lzma2), they are
 /// compressed along with the rest of the data.
 ///
@@ -447,7 +515,7 @@ pub struct WIAException {
 /// end offset of the last [WIAExceptionList] is not evenly divisible by 4, padding is inserted
 /// after it so that the data afterwards will start at a 4 byte boundary. This padding is not
 /// inserted for the other compression methods.
-type WIAExceptionList = Box<[WIAException]>;
+pub type WIAExceptionList = Box<[WIAException]>;
 
 #[derive(Clone)]
 pub enum Decompressor {
@@ -687,6 +755,7 @@ impl BlockIO for DiscIOWIA {
     ) -> io::Result<Block> {
         let chunk_size = self.disc.chunk_size.get();
         let sectors_per_chunk = chunk_size / SECTOR_SIZE as u32;
+        let format = if self.header.is_rvz() { Format::Rvz } else { Format::Wia };
 
         let (group_index, group_sector, partition_offset) = if let Some(partition) = partition {
             // Find the partition
@@ -718,10 +787,12 @@ impl BlockIO for DiscIOWIA {
 
             // Find the partition data for the sector
             let Some(pd) = wia_part.partition_data.iter().find(|pd| pd.contains(sector)) else {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    format!("Couldn't find WIA/RVZ partition data for sector {}", sector),
-                ));
+                return Err(Error::SectorOutOfRange {
+                    format,
+                    sector,
+                    partition: Some(partition.index),
+                }
+                .into());
             };
 
             // Find the group index for the sector
@@ -729,14 +800,12 @@ impl BlockIO for DiscIOWIA {
             let part_group_index = part_data_sector / sectors_per_chunk;
             let part_group_sector = part_data_sector % sectors_per_chunk;
             if part_group_index >= pd.num_groups.get() {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    format!(
-                        "WIA/RVZ partition group index out of range: {} >= {}",
-                        part_group_index,
-                        pd.num_groups.get()
-                    ),
-                ));
+                return Err(Error::GroupOutOfRange {
+                    format,
+                    group: part_group_index,
+                    count: pd.num_groups.get(),
+                }
+                .into());
             }
 
             // Calculate the group offset within the partition
@@ -747,10 +816,7 @@ impl BlockIO for DiscIOWIA {
             (pd.group_index.get() + part_group_index, part_group_sector, part_group_offset)
         } else {
             let Some(rd) = self.raw_data.iter().find(|d| d.contains(sector)) else {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    format!("Couldn't find WIA/RVZ raw data for sector {}", sector),
-                ));
+                return Err(Error::SectorOutOfRange { format, sector, partition: None }.into());
             };
 
             // Find the group index for the sector
@@ -758,14 +824,12 @@ impl BlockIO for DiscIOWIA {
             let group_index = data_sector / sectors_per_chunk;
             let group_sector = data_sector % sectors_per_chunk;
             if group_index >= rd.num_groups.get() {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    format!(
-                        "WIA/RVZ raw data group index out of range: {} >= {}",
-                        group_index,
-                        rd.num_groups.get()
-                    ),
-                ));
+                return Err(Error::GroupOutOfRange {
+                    format,
+                    group: group_index,
+                    count: rd.num_groups.get(),
+                }
+                .into());
             }
 
             // Calculate the group offset
@@ -775,10 +839,12 @@ impl BlockIO for DiscIOWIA {
 
         // Fetch the group
         let Some(group) = self.groups.get(group_index as usize) else {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("Couldn't find WIA/RVZ group index {}", group_index),
-            ));
+            return Err(Error::GroupOutOfRange {
+                format,
+                group: group_index,
+                count: self.groups.len() as u32,
+            }
+            .into());
         };
 
         // Special case for all-zero data
@@ -815,8 +881,18 @@ impl BlockIO for DiscIOWIA {
                     reader.seek(SeekFrom::Current((4 - rem) as i64))?;
                 }
             }
-            let mut reader: Box<dyn Read> = if group.is_compressed() {
-                self.decompressor.wrap(reader)?
+            // Failures reading from compressed groups are reported as decompression errors
+            let compressed = group.is_compressed();
+            let decode_error = |e: io::Error| -> io::Error {
+                if compressed {
+                    Error::Decompression { format, group: group_index, message: e.to_string() }
+                        .into()
+                } else {
+                    e
+                }
+            };
+            let mut reader: Box<dyn Read> = if compressed {
+                self.decompressor.wrap(reader).map_err(decode_error)?
             } else {
                 Box::new(reader)
             };
@@ -825,7 +901,8 @@ impl BlockIO for DiscIOWIA {
                     reader.as_mut(),
                     partition.is_some(),
                     self.disc.chunk_size.get(),
-                )?;
+                )
+                .map_err(decode_error)?;
             }
 
             if group.rvz_packed_size.get() > 0 {
@@ -837,7 +914,10 @@ impl BlockIO for DiscIOWIA {
                         Ok(_) => {}
                         Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
                         Err(e) => {
-                            return Err(io::Error::new(e.kind(), "Failed to read RVZ packed size"));
+                            return Err(decode_error(io::Error::new(
+                                e.kind(),
+                                "Failed to read RVZ packed size",
+                            )));
                         }
                     }
                     let size = u32::from_be_bytes(size_bytes);
@@ -845,7 +925,7 @@ impl BlockIO for DiscIOWIA {
                     if size & 0x80000000 != 0 {
                         // Junk data
                         let size = size & 0x7FFFFFFF;
-                        lfg.init_with_reader(reader.as_mut())?;
+                        lfg.init_with_reader(reader.as_mut()).map_err(decode_error)?;
                         lfg.skip(
                             ((partition_offset + cur_data_len as u64) % SECTOR_SIZE as u64)
                                 as usize,
@@ -855,12 +935,14 @@ impl BlockIO for DiscIOWIA {
                     } else {
                         // Real data
                         self.group_data.resize(cur_data_len + size as usize, 0);
-                        reader.read_exact(&mut self.group_data[cur_data_len..])?;
+                        reader
+                            .read_exact(&mut self.group_data[cur_data_len..])
+                            .map_err(decode_error)?;
                     }
                 }
             } else {
                 // Read and decompress data
-                reader.read_to_end(&mut self.group_data)?;
+                reader.read_to_end(&mut self.group_data).map_err(decode_error)?;
             }
 
             self.group = group_index;
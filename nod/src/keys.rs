@@ -0,0 +1,262 @@
+//! Wii common key, root key and NFS key storage
+
+use std::{collections::HashMap, fs, mem::size_of, path::Path};
+
+use crate::{io::KeyBytes, Error, PublicKey, Result, ResultContext};
+
+/// Certificate authorities signed by the retail and debug root keys.
+const RVL_CA_PPKI: &str = "Root-CA00000001";
+const RVL_CA_DPKI: &str = "Root-CA00000002";
+
+/// Size of an RSA-4096 root key modulus.
+const ROOT_KEY_SIZE: usize = 0x200;
+/// Public exponent of the root keys.
+const ROOT_KEY_EXPONENT: u32 = 0x10001;
+
+// ppki (Retail)
+const RVL_CERT_ISSUER_PPKI_TICKET: &str = "Root-CA00000001-XS00000003";
+#[rustfmt::skip]
+const RETAIL_COMMON_KEYS: [KeyBytes; 3] = [
+    /* RVL_KEY_RETAIL */
+    [0xeb, 0xe4, 0x2a, 0x22, 0x5e, 0x85, 0x93, 0xe4, 0x48, 0xd9, 0xc5, 0x45, 0x73, 0x81, 0xaa, 0xf7],
+    /* RVL_KEY_KOREAN */
+    [0x63, 0xb8, 0x2b, 0xb4, 0xf4, 0x61, 0x4e, 0x2e, 0x13, 0xf2, 0xfe, 0xfb, 0xba, 0x4c, 0x9b, 0x7e],
+    /* vWii_KEY_RETAIL */
+    [0x30, 0xbf, 0xc7, 0x6e, 0x7c, 0x19, 0xaf, 0xbb, 0x23, 0x16, 0x33, 0x30, 0xce, 0xd7, 0xc2, 0x8d],
+];
+
+// dpki (Debug)
+const RVL_CERT_ISSUER_DPKI_TICKET: &str = "Root-CA00000002-XS00000006";
+#[rustfmt::skip]
+const DEBUG_COMMON_KEYS: [KeyBytes; 3] = [
+    /* RVL_KEY_DEBUG */
+    [0xa1, 0x60, 0x4a, 0x6a, 0x71, 0x23, 0xb5, 0x29, 0xae, 0x8b, 0xec, 0x32, 0xc8, 0x16, 0xfc, 0xaa],
+    /* RVL_KEY_KOREAN_DEBUG */
+    [0x67, 0x45, 0x8b, 0x6b, 0xc6, 0x23, 0x7b, 0x32, 0x69, 0x98, 0x3c, 0x64, 0x73, 0x48, 0x33, 0x66],
+    /* vWii_KEY_DEBUG */
+    [0x2f, 0x5c, 0x1b, 0x29, 0x44, 0xe7, 0xfd, 0x6f, 0xc3, 0x97, 0x96, 0x4b, 0x05, 0x76, 0x91, 0xfa],
+];
+
+/// Names of the well-known keys in a keys file, with their issuer and common key index.
+const KEY_NAMES: [(&str, &str, u8); 6] = [
+    ("common_key", RVL_CERT_ISSUER_PPKI_TICKET, 0),
+    ("korean_key", RVL_CERT_ISSUER_PPKI_TICKET, 1),
+    ("vwii_common_key", RVL_CERT_ISSUER_PPKI_TICKET, 2),
+    ("debug_common_key", RVL_CERT_ISSUER_DPKI_TICKET, 0),
+    ("debug_korean_key", RVL_CERT_ISSUER_DPKI_TICKET, 1),
+    ("debug_vwii_common_key", RVL_CERT_ISSUER_DPKI_TICKET, 2),
+];
+
+/// Size of a Wii OTP dump. (otp.bin)
+pub const OTP_SIZE: usize = 0x80;
+/// Offset of the common key in a Wii OTP dump.
+const OTP_COMMON_KEY_OFFSET: usize = 0x14;
+
+/// Keys used to decrypt Wii title keys and NFS (Wii U VC) images, and to verify certificate
+/// chains.
+///
+/// Common keys are looked up by the ticket's signature issuer
+/// (e.g. `Root-CA00000001-XS00000003`) and common key index. Root public keys are looked up
+/// by the certificate authority prefix of a signature issuer (e.g. `Root-CA00000001`).
+///
+/// [`KeyStore::default`] contains the retail and debug common keys. Root keys are not
+/// included and must be loaded to verify certificates issued by Root.
+///
+/// # Examples
+///
+/// Loading additional keys and opening a disc with them:
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// use nod::{Disc, KeyStore, OpenOptions};
+///
+/// fn main() -> nod::Result<()> {
+///     let mut keys = KeyStore::default();
+///     keys.load_file("path/to/keys.txt")?;
+///     let options = OpenOptions { keys: Some(Arc::new(keys)), ..Default::default() };
+///     let disc = Disc::new_with_options("path/to/file.iso", &options)?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyStore {
+    common_keys: HashMap<(String, u8), KeyBytes>,
+    root_keys: HashMap<String, Box<[u8]>>,
+    nfs_key: Option<KeyBytes>,
+}
+
+impl Default for KeyStore {
+    fn default() -> Self {
+        let mut store = Self::empty();
+        for (idx, key) in RETAIL_COMMON_KEYS.iter().enumerate() {
+            store.insert_common_key(RVL_CERT_ISSUER_PPKI_TICKET, idx as u8, *key);
+        }
+        for (idx, key) in DEBUG_COMMON_KEYS.iter().enumerate() {
+            store.insert_common_key(RVL_CERT_ISSUER_DPKI_TICKET, idx as u8, *key);
+        }
+        store
+    }
+}
+
+impl KeyStore {
+    /// Creates a key store without any keys.
+    pub fn empty() -> Self {
+        Self { common_keys: HashMap::new(), root_keys: HashMap::new(), nfs_key: None }
+    }
+
+    /// Adds or replaces a common key for a ticket issuer and common key index.
+    pub fn insert_common_key(&mut self, issuer: &str, common_key_idx: u8, key: KeyBytes) {
+        self.common_keys.insert((issuer.to_string(), common_key_idx), key);
+    }
+
+    /// Looks up the common key for a ticket issuer and common key index.
+    pub fn common_key(&self, issuer: &str, common_key_idx: u8) -> Option<&KeyBytes> {
+        self.common_keys.get(&(issuer.to_string(), common_key_idx))
+    }
+
+    /// Adds or replaces the RSA-4096 root public key for a certificate authority.
+    ///
+    /// `ca` is the issuer prefix of data signed under the root key, e.g. `Root-CA00000001`
+    /// (retail) or `Root-CA00000002` (debug).
+    pub fn insert_root_key(&mut self, ca: &str, modulus: &[u8]) {
+        self.root_keys.insert(ca.to_string(), modulus.into());
+    }
+
+    /// Looks up the root public key for a signature issuer
+    /// (e.g. `Root-CA00000001-CP00000004`) by its certificate authority prefix.
+    pub fn root_key(&self, issuer: &str) -> Option<PublicKey<'_>> {
+        let ca_len = issuer.match_indices('-').nth(1).map_or(issuer.len(), |(idx, _)| idx);
+        self.root_keys
+            .get(&issuer[..ca_len])
+            .map(|modulus| PublicKey::Rsa { modulus, exponent: ROOT_KEY_EXPONENT })
+    }
+
+    /// Sets the NFS title key. (htk.bin)
+    ///
+    /// When set, NFS images are decrypted with this key instead of searching for `htk.bin`.
+    pub fn set_nfs_key(&mut self, key: KeyBytes) { self.nfs_key = Some(key); }
+
+    /// The NFS title key, if set.
+    pub fn nfs_key(&self) -> Option<&KeyBytes> { self.nfs_key.as_ref() }
+
+    /// Loads keys from a file.
+    ///
+    /// Files of exactly [`OTP_SIZE`] bytes are loaded as a retail Wii OTP dump (see
+    /// [`load_otp`](Self::load_otp)). 16-byte files named `htk.bin` are loaded as the NFS
+    /// key. Anything else is loaded as a text keys file (see [`load_str`](Self::load_str)).
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let data =
+            fs::read(path).with_context(|| format!("Reading keys file {}", path.display()))?;
+        let result = if data.len() == OTP_SIZE {
+            self.load_otp(&data, RVL_CERT_ISSUER_PPKI_TICKET)
+        } else if data.len() == 16
+            && path.file_name().is_some_and(|n| n.eq_ignore_ascii_case("htk.bin"))
+        {
+            self.set_nfs_key(data.try_into().unwrap());
+            Ok(())
+        } else {
+            std::str::from_utf8(&data)
+                .map_err(|_| "not a valid text file".to_string())
+                .and_then(|text| self.load_str(text))
+        };
+        result.map_err(|e| Error::Other(format!("Loading keys file {}: {}", path.display(), e)))
+    }
+
+    /// Loads keys from a text keys file.
+    ///
+    /// Each line has the form `name = hex`. Empty lines and lines starting with `#` or `;`
+    /// are ignored. Recognized names:
+    /// - `common_key`, `korean_key`, `vwii_common_key`: retail common keys 0-2
+    /// - `debug_common_key`, `debug_korean_key`, `debug_vwii_common_key`: debug common keys 0-2
+    /// - `root_key`, `debug_root_key`: the retail and debug RSA-4096 root key moduli
+    /// - `nfs_key`: the NFS title key (htk.bin)
+    /// - `<issuer>:<index>`: a common key for any issuer,
+    ///   e.g. `Root-CA00000001-XS00000003:0`
+    ///
+    /// Other names are ignored, so files shared with other tools can be used.
+    pub fn load_str(&mut self, text: &str) -> Result<(), String> {
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected `name = value`", line_num + 1));
+            };
+            let name = name.trim();
+            let parse_key = || {
+                let mut key = KeyBytes::default();
+                base16ct::mixed::decode(value.trim(), &mut key)
+                    .ok()
+                    .filter(|k| k.len() == size_of::<KeyBytes>())
+                    .ok_or_else(|| format!("line {}: invalid key for {}", line_num + 1, name))?;
+                Ok::<_, String>(key)
+            };
+            if name.eq_ignore_ascii_case("nfs_key") {
+                self.set_nfs_key(parse_key()?);
+            } else if let Some(ca) = [("root_key", RVL_CA_PPKI), ("debug_root_key", RVL_CA_DPKI)]
+                .iter()
+                .find_map(|&(n, ca)| n.eq_ignore_ascii_case(name).then_some(ca))
+            {
+                let mut modulus = [0u8; ROOT_KEY_SIZE];
+                base16ct::mixed::decode(value.trim(), &mut modulus)
+                    .ok()
+                    .filter(|k| k.len() == ROOT_KEY_SIZE)
+                    .ok_or_else(|| format!("line {}: invalid key for {}", line_num + 1, name))?;
+                self.insert_root_key(ca, &modulus);
+            } else if let Some(&(_, issuer, idx)) =
+                KEY_NAMES.iter().find(|(n, _, _)| n.eq_ignore_ascii_case(name))
+            {
+                self.insert_common_key(issuer, idx, parse_key()?);
+            } else if let Some((issuer, idx)) = name.rsplit_once(':') {
+                let idx = idx
+                    .trim()
+                    .parse::<u8>()
+                    .map_err(|_| format!("line {}: invalid common key index", line_num + 1))?;
+                self.insert_common_key(issuer.trim(), idx, parse_key()?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the common key from a Wii OTP dump (otp.bin) for the given ticket issuer.
+    pub fn load_otp(&mut self, data: &[u8], issuer: &str) -> Result<(), String> {
+        if data.len() != OTP_SIZE {
+            return Err(format!("invalid OTP size {:#X}, expected {:#X}", data.len(), OTP_SIZE));
+        }
+        let key: KeyBytes =
+            data[OTP_COMMON_KEY_OFFSET..OTP_COMMON_KEY_OFFSET + 16].try_into().unwrap();
+        self.insert_common_key(issuer, 0, key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_str() {
+        let mut keys = KeyStore::empty();
+        keys.load_str(
+            "# Keys\n\
+             common_key = EBE42A225E8593E448D9C5457381AAF7\n\
+             Root-CA00000003-XS0000000c:1 = 000102030405060708090a0b0c0d0e0f\n\
+             nfs_key = ffffffffffffffffffffffffffffffff\n\
+             sd_key = 00\n",
+        )
+        .unwrap();
+        keys.load_str(&format!("debug_root_key = {}", "a5".repeat(ROOT_KEY_SIZE))).unwrap();
+        assert_eq!(keys.common_key(RVL_CERT_ISSUER_PPKI_TICKET, 0), Some(&RETAIL_COMMON_KEYS[0]));
+        assert_eq!(keys.common_key("Root-CA00000003-XS0000000c", 1).unwrap()[15], 0x0F);
+        assert_eq!(keys.nfs_key(), Some(&[0xFF; 16]));
+        assert!(keys.root_key("Root-CA00000001-CP00000004").is_none());
+        assert!(matches!(
+            keys.root_key("Root-CA00000002-CP00000007"),
+            Some(PublicKey::Rsa { modulus, exponent: 0x10001 }) if modulus == [0xA5; ROOT_KEY_SIZE]
+        ));
+        assert!(keys.load_str("common_key = 0011").is_err());
+    }
+}
@@ -47,7 +47,12 @@
 //!
 //! ```no_run
 //! // Enable `rebuild_encryption` to ensure the output is a valid ISO.
-//! let options = nod::OpenOptions { rebuild_encryption: true, ..Default::default() };
+//! // Enable `read_ahead` to decode upcoming blocks in parallel.
+//! let options = nod::OpenOptions {
+//!     rebuild_encryption: true,
+//!     read_ahead: true,
+//!     ..Default::default()
+//! };
 //! let mut disc = nod::Disc::new_with_options("path/to/file.rvz", &options)
 //!     .expect("Failed to open disc");
 //!
@@ -61,23 +66,85 @@
 use std::{
     io::{Read, Seek},
     path::Path,
+    sync::Arc,
 };
 
+pub use apploader::{Apploader, ApploaderDate, ApploaderVariant, KnownApploader, KnownApploaders};
+#[cfg(feature = "tokio")]
+pub use async_io::{AsyncDisc, AsyncPartition, AsyncReadStream};
+pub use banner::{Banner, BannerKind, BannerLanguage, BannerText, BANNER_HEIGHT, BANNER_WIDTH};
+pub use cert::{
+    CertChain, Certificate, PartitionSignatures, PublicKey, SignatureStatus, SignatureType,
+};
 pub use disc::{
-    ApploaderHeader, DiscHeader, DolHeader, PartitionBase, PartitionHeader, PartitionKind,
+    wii::{SignedHeader, Ticket, TicketTimeLimit, TmdHeader},
+    ApploaderHeader, Bi2, DiscHeader, DolHeader, PartitionBase, PartitionHeader, PartitionKind,
     PartitionMeta, BI2_SIZE, BOOT_SIZE, SECTOR_SIZE,
 };
-pub use fst::{Fst, Node, NodeKind};
-pub use io::{block::PartitionInfo, Compression, DiscMeta, Format};
-pub use streams::ReadStream;
+pub use dol::{Dol, DolSection, DolSectionKind};
+pub use fst::{Fst, FstEntry, FstIter, FstTree, NameMatch, Node, NodeKind};
+pub use imet::{ImetLanguage, WiiBanner, WiiBannerArchive, IMET_MAGIC};
+pub use io::{
+    block::PartitionInfo,
+    wia::{
+        DiscKind, RVZGroup, WIACompression, WIADisc, WIAException, WIAExceptionList, WIAFileHeader,
+        WIAGroup, WIAPartition, WIAPartitionData, WIARawData, RVZ_MAGIC, WIA_MAGIC,
+    },
+    Compression, DiscMeta, Format,
+};
+pub use keys::{KeyStore, OTP_SIZE};
+pub use patch::write_partition_header;
+pub use progress::{Progress, ProgressHandler, ProgressStage};
+pub use rarc::{Rarc, RarcDirectory, RarcEntry, RarcHeader, RarcIter, RARC_MAGIC};
+pub use region::{RatingBoard, Ratings, Region, RegionInfo, RegionSource};
+#[cfg(feature = "regex")]
+pub use search::PathRegex;
+pub use search::{FstMatch, Glob};
+pub use streams::{OwnedWindowedReadStream, ReadStream, SharedWindowedReadStream};
+pub use tmd::{AccessRights, ContentRecord, Tmd, TmdRegion};
+pub use tpl::{Tpl, TplFormat, TplImage, TplPalette, TplPaletteFormat, TPL_MAGIC};
+pub use u8_archive::{U8Archive, U8Header, U8_MAGIC};
+pub use validate::{FstIssue, FstIssueKind, PartitionLayout, SystemFile};
+pub use wad::{
+    system_menu_version, write_wad, write_wad_with, Wad, WadHeader, SYSTEM_MENU_TITLE_ID,
+    WAD_ALIGNMENT, WAD_TYPE_BOOT2, WAD_TYPE_INSTALLABLE,
+};
+pub use yaz0::{
+    compress_yay0, compress_yaz0, decompress, DecompressedStream, FileCompression, Yay0Reader,
+    Yaz0Reader, YAY0_MAGIC, YAZ0_MAGIC,
+};
 
+mod apploader;
+#[cfg(feature = "tokio")]
+mod async_io;
+mod banner;
+mod cert;
 mod disc;
+mod dol;
 mod fst;
+mod imet;
 mod io;
+mod keys;
+mod patch;
+mod progress;
+mod rarc;
+mod region;
+mod search;
 mod streams;
+mod tmd;
+mod tpl;
+mod u8_archive;
 mod util;
+mod validate;
+mod wad;
+mod yaz0;
 
 /// Error types for nod.
+///
+/// Errors raised while reading through [`Read`] implementations (e.g. [`Disc`] or partition
+/// streams) are returned as [`std::io::Error`]. When the cause is one of the structured
+/// variants below, the original [`Error`] can be recovered with
+/// [`Error::from_io`].
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     /// An error for disc format related issues.
@@ -86,11 +153,169 @@ pub enum Error {
     /// A general I/O error.
     #[error("I/O error: {0}")]
     Io(String, #[source] std::io::Error),
+    /// The operation was cancelled by a [`ProgressHandler`].
+    #[error("operation cancelled")]
+    Cancelled,
+    /// Wii: A partition data hash did not match the data it covers.
+    #[error(
+        "{level} hash mismatch in partition {partition}, sector {sector} (index {index}): \
+        expected {}, got {}",
+        hex(.expected),
+        hex(.actual)
+    )]
+    HashMismatch {
+        /// The level of the hash that failed.
+        level: HashLevel,
+        /// The index of the partition.
+        partition: usize,
+        /// The sector within the partition data.
+        sector: u32,
+        /// The index of the hash within its level's table. (H0: block within the sector,
+        /// H1: sector within the subgroup, H2: subgroup within the group, H3: group)
+        index: u32,
+        /// The stored hash.
+        expected: [u8; 20],
+        /// The hash of the data.
+        actual: [u8; 20],
+    },
+    /// A checksum stored by the disc file format did not match the data it covers.
+    #[error(
+        "{format} block {block} checksum mismatch: expected {expected:#010x}, got {actual:#010x}"
+    )]
+    ChecksumMismatch {
+        /// The disc file format.
+        format: Format,
+        /// The index of the block.
+        block: u32,
+        /// The stored checksum.
+        expected: u32,
+        /// The checksum of the data.
+        actual: u32,
+    },
+    /// The disc file format version is not supported.
+    #[error("{format} version {version:#X} is not supported")]
+    UnsupportedVersion {
+        /// The disc file format.
+        format: Format,
+        /// The version stored in the file.
+        version: u32,
+    },
+    /// A compressed group or block could not be decompressed.
+    #[error("{format} group {group} decompression failed: {message}")]
+    Decompression {
+        /// The disc file format.
+        format: Format,
+        /// The index of the group (WIA/RVZ) or block (GCZ).
+        group: u32,
+        /// A description of the failure.
+        message: String,
+    },
+    /// A sector is not covered by the data stored in the disc image.
+    #[error("{format} sector {sector} out of range")]
+    SectorOutOfRange {
+        /// The disc file format.
+        format: Format,
+        /// The sector, relative to the start of the disc or partition data.
+        sector: u32,
+        /// The index of the partition, if the sector is within partition data.
+        partition: Option<usize>,
+    },
+    /// A group index is outside of the groups stored in the disc image.
+    #[error("{format} group {group} out of range (count {count})")]
+    GroupOutOfRange {
+        /// The disc file format.
+        format: Format,
+        /// The index of the group.
+        group: u32,
+        /// The number of groups available.
+        count: u32,
+    },
+    /// The size of a block does not match the size expected by the disc file format.
+    #[error("{format} block {block} size mismatch: expected {expected:#X}, got {actual:#X}")]
+    BlockSizeMismatch {
+        /// The disc file format.
+        format: Format,
+        /// The index of the block.
+        block: u32,
+        /// The expected (or maximum) size in bytes.
+        expected: u32,
+        /// The size in bytes stored in or read from the disc image.
+        actual: u32,
+    },
+    /// A block spanning several of the format's internal blocks combines different block types.
+    #[error("{format} block {block} combines inconsistent block types")]
+    InconsistentBlockTypes {
+        /// The disc file format.
+        format: Format,
+        /// The index of the block.
+        block: u32,
+    },
+    /// Decrypted Wii partition data was found outside of a partition.
+    #[error("{format} sector {sector} contains decrypted partition data outside of a partition")]
+    UnexpectedDecryptedData {
+        /// The disc file format.
+        format: Format,
+        /// The sector, relative to the start of the disc.
+        sector: u32,
+    },
+    /// The NKit header's version string is invalid or names an unsupported version.
+    #[error("NKit header version {:?} is not supported", String::from_utf8_lossy(.version))]
+    UnsupportedNKitVersion {
+        /// The version string stored in the header.
+        version: [u8; 8],
+    },
     /// An unknown error.
     #[error("error: {0}")]
     Other(String),
 }
 
+impl Error {
+    /// Returns the structured error carried by an [`std::io::Error`], if any.
+    pub fn from_io(err: &std::io::Error) -> Option<&Error> {
+        err.get_ref().and_then(|e| e.downcast_ref::<Error>())
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> std::io::Error {
+        let kind = match &err {
+            Error::Io(_, e) => e.kind(),
+            Error::Cancelled | Error::Other(_) => std::io::ErrorKind::Other,
+            _ => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, err)
+    }
+}
+
+/// The level of a Wii partition data hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashLevel {
+    /// Hash of a 0x400 byte block within a sector.
+    H0,
+    /// Hash of a sector's H0 hashes.
+    H1,
+    /// Hash of a subgroup's (8 sectors) H1 hashes.
+    H2,
+    /// Hash of a group's (64 sectors) H2 hashes.
+    H3,
+}
+
+impl std::fmt::Display for HashLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashLevel::H0 => write!(f, "H0"),
+            HashLevel::H1 => write!(f, "H1"),
+            HashLevel::H2 => write!(f, "H2"),
+            HashLevel::H3 => write!(f, "H3"),
+        }
+    }
+}
+
+fn hex(bytes: &[u8; 20]) -> String {
+    let mut buf = [0u8; 40];
+    base16ct::lower::encode_str(bytes, &mut buf).unwrap().to_string() // Safe: fixed buffer size
+}
+
 impl From<&str> for Error {
     fn from(s: &str) -> Error { Error::Other(s.to_string()) }
 }
@@ -109,7 +334,14 @@ pub trait ErrorContext {
 }
 
 impl ErrorContext for std::io::Error {
-    fn context(self, context: impl Into<String>) -> Error { Error::Io(context.into(), self) }
+    fn context(self, context: impl Into<String>) -> Error {
+        if Error::from_io(&self).is_some() {
+            // Safe: checked above
+            *self.into_inner().unwrap().downcast::<Error>().unwrap()
+        } else {
+            Error::Io(context.into(), self)
+        }
+    }
 }
 
 /// Helper trait for adding context to result errors.
@@ -143,6 +375,19 @@ pub struct OpenOptions {
     pub rebuild_encryption: bool,
     /// Wii: Validate partition data hashes while reading the disc image.
     pub validate_hashes: bool,
+    /// Decode upcoming blocks in parallel on a dedicated thread pool while reading the disc
+    /// image sequentially through [`Disc`]'s [`Read`] implementation.
+    ///
+    /// This decompresses, regenerates junk data, rebuilds hashes and re-encrypts ahead of
+    /// the current position, which greatly speeds up conversion and verification of
+    /// compressed formats (e.g. WIA/RVZ, GCZ). Partition streams are unaffected.
+    pub read_ahead: bool,
+    /// Receives progress updates and allows cancelling long-running operations, such as
+    /// rebuilding Wii partition hashes on open or reading the disc image stream.
+    pub progress: Option<Arc<dyn ProgressHandler>>,
+    /// Wii: Common keys used to decrypt partition title keys, and the NFS title key.
+    /// If not set, the built-in retail and debug common keys are used.
+    pub keys: Option<Arc<KeyStore>>,
 }
 
 /// An open disc image and read stream.
@@ -151,8 +396,13 @@ pub struct OpenOptions {
 pub struct Disc {
     reader: disc::reader::DiscReader,
     options: OpenOptions,
+    /// Stream position of the last [`ProgressStage::Read`] update.
+    progress_pos: u64,
 }
 
+/// Minimum number of bytes read between [`ProgressStage::Read`] updates.
+const READ_PROGRESS_INTERVAL: u64 = 0x100000;
+
 impl Disc {
     /// Opens a disc image from a file path.
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Disc> {
@@ -161,9 +411,9 @@ impl Disc {
 
     /// Opens a disc image from a file path with custom options.
     pub fn new_with_options<P: AsRef<Path>>(path: P, options: &OpenOptions) -> Result<Disc> {
-        let io = io::block::open(path.as_ref())?;
+        let io = io::block::open(path.as_ref(), options)?;
         let reader = disc::reader::DiscReader::new(io, options)?;
-        Ok(Disc { reader, options: options.clone() })
+        Ok(Disc { reader, options: options.clone(), progress_pos: 0 })
     }
 
     /// The disc's primary header.
@@ -197,9 +447,84 @@ impl Disc {
 }
 
 impl Read for Disc {
-    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> { self.reader.read(buf) }
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.reader.read(buf)?;
+        let Some(progress) = &self.options.progress else {
+            return Ok(len);
+        };
+        // Report every READ_PROGRESS_INTERVAL bytes and at the end of the stream,
+        // rather than for every (possibly small) read
+        let pos = self.reader.stream_position()?;
+        let total = self.reader.disc_size();
+        if len == 0 || pos >= total || pos.abs_diff(self.progress_pos) >= READ_PROGRESS_INTERVAL {
+            self.progress_pos = pos;
+            if progress.is_cancelled() {
+                return Err(Error::Cancelled.into());
+            }
+            progress.on_progress(Progress { stage: ProgressStage::Read, processed: pos, total });
+        }
+        Ok(len)
+    }
 }
 
 impl Seek for Disc {
     fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> { self.reader.seek(pos) }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex,
+        },
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Handler {
+        updates: Mutex<Vec<Progress>>,
+        cancelled: AtomicBool,
+    }
+
+    impl ProgressHandler for Handler {
+        fn on_progress(&self, progress: Progress) { self.updates.lock().unwrap().push(progress); }
+
+        fn is_cancelled(&self) -> bool { self.cancelled.load(Ordering::Relaxed) }
+    }
+
+    #[test]
+    fn test_read_progress() {
+        let mut data = vec![0u8; 0x280000];
+        // GameCube magic
+        data[0x1C..0x20].copy_from_slice(&0xC2339F3Du32.to_be_bytes());
+        let path = std::env::temp_dir().join(format!("nod-progress-{}.iso", std::process::id()));
+        fs::write(&path, &data).unwrap();
+        let handler = Arc::new(Handler::default());
+        let options = OpenOptions { progress: Some(handler.clone()), ..Default::default() };
+        let disc = Disc::new_with_options(&path, &options);
+        fs::remove_file(&path).unwrap();
+        let mut disc = disc.unwrap();
+
+        let mut buf = [0u8; 0x100];
+        while disc.read(&mut buf).unwrap() != 0 {}
+        let processed = handler
+            .updates
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|p| {
+                assert_eq!((p.stage, p.total), (ProgressStage::Read, data.len() as u64));
+                p.processed
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(processed, [0x100000, 0x200000, 0x280000, 0x280000]);
+
+        handler.cancelled.store(true, Ordering::Relaxed);
+        disc.seek(std::io::SeekFrom::Start(0)).unwrap();
+        let err = (0..0x1000).try_for_each(|_| disc.read(&mut buf).map(|_| ())).unwrap_err();
+        assert!(matches!(Error::from_io(&err), Some(Error::Cancelled)));
+    }
+}
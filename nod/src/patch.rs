@@ -0,0 +1,273 @@
+//! Ticket and TMD editing
+
+use std::{
+    io,
+    io::{Seek, SeekFrom, Write},
+    mem::size_of,
+};
+
+use sha1::{Digest, Sha1};
+use zerocopy::{AsBytes, FromBytes};
+
+use crate::{
+    disc::wii::{Ticket, TmdHeader},
+    AccessRights, Error, KeyStore, PartitionInfo, PartitionMeta, Result, ResultContext, Tmd,
+    TmdRegion, SECTOR_SIZE,
+};
+
+/// Offset of the signed data in a ticket or TMD, following the RSA-2048 signature block.
+const SIGNED_DATA_OFFSET: usize = 0x140;
+/// Offset of the reserved bytes brute-forced when fakesigning a ticket.
+const TICKET_NONCE_OFFSET: usize = 0x21E;
+/// Offset of the reserved bytes brute-forced when fakesigning a TMD.
+const TMD_NONCE_OFFSET: usize = 0x1D4;
+
+impl Ticket {
+    /// Changes the common key index, re-encrypting the title key with the new built-in
+    /// common key.
+    pub fn set_common_key_idx(&mut self, common_key_idx: u8) -> Result<()> {
+        self.set_common_key_idx_with(common_key_idx, &KeyStore::default())
+    }
+
+    /// Changes the common key index, re-encrypting the title key with the new common key
+    /// from `keys`.
+    pub fn set_common_key_idx_with(&mut self, common_key_idx: u8, keys: &KeyStore) -> Result<()> {
+        let title_key = self.decrypt_title_key_with(keys)?;
+        let old_idx = self.common_key_idx;
+        self.common_key_idx = common_key_idx;
+        if let Err(e) = self.encrypt_title_key_with(title_key, keys) {
+            self.common_key_idx = old_idx;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Changes the title ID, re-encrypting the title key with the new title ID as its IV,
+    /// using the built-in common key.
+    pub fn set_title_id(&mut self, title_id: u64) -> Result<()> {
+        self.set_title_id_with(title_id, &KeyStore::default())
+    }
+
+    /// Changes the title ID, re-encrypting the title key with the new title ID as its IV,
+    /// using the common key from `keys`.
+    pub fn set_title_id_with(&mut self, title_id: u64, keys: &KeyStore) -> Result<()> {
+        let title_key = self.decrypt_title_key_with(keys)?;
+        self.title_id = title_id.to_be_bytes();
+        self.encrypt_title_key_with(title_key, keys)
+    }
+
+    /// Clears the signature and brute-forces reserved bytes until the SHA-1 hash of the
+    /// signed data starts with 0x00.
+    ///
+    /// See [`SignatureStatus::Fakesigned`](crate::SignatureStatus::Fakesigned).
+    pub fn fakesign(&mut self) -> Result<()> {
+        fakesign(self.as_bytes_mut(), TICKET_NONCE_OFFSET)
+            .map_err(|e| Error::Other(format!("Failed to fakesign ticket: {}", e)))
+    }
+}
+
+impl TmdHeader {
+    /// Sets the required IOS version. (e.g. 36 for IOS36)
+    pub fn set_ios_version(&mut self, ios: u32) {
+        self.ios_id = (0x1_0000_0000u64 | ios as u64).to_be_bytes();
+    }
+
+    /// Sets the region.
+    pub fn set_region(&mut self, region: TmdRegion) { self.region.set(region.into()) }
+
+    /// Sets the access rights.
+    pub fn set_access_rights(&mut self, access_rights: AccessRights) {
+        self.access_flags.set(access_rights.0)
+    }
+
+    /// Sets the title ID.
+    pub fn set_title_id(&mut self, title_id: u64) { self.title_id = title_id.to_be_bytes() }
+}
+
+impl Tmd<'_> {
+    /// Fakesigns a raw TMD, including its content records.
+    ///
+    /// Clears the signature and brute-forces reserved bytes until the SHA-1 hash of the
+    /// signed data starts with 0x00.
+    ///
+    /// See [`SignatureStatus::Fakesigned`](crate::SignatureStatus::Fakesigned).
+    pub fn fakesign(data: &mut [u8]) -> Result<()> {
+        let size = Tmd::new(data).map_err(Error::DiscFormat)?.size();
+        fakesign(&mut data[..size], TMD_NONCE_OFFSET)
+            .map_err(|e| Error::Other(format!("Failed to fakesign TMD: {}", e)))
+    }
+}
+
+impl PartitionMeta {
+    /// A mutable view into the ticket. (Wii only)
+    pub fn ticket_mut(&mut self) -> Option<&mut Ticket> {
+        self.raw_ticket.as_mut().and_then(|v| Ticket::mut_from(v))
+    }
+
+    /// A mutable view into the TMD header. (Wii only)
+    pub fn tmd_header_mut(&mut self) -> Option<&mut TmdHeader> {
+        self.raw_tmd.as_mut().and_then(|v| TmdHeader::mut_from_prefix(v))
+    }
+
+    /// Fakesigns the ticket and TMD. (Wii only)
+    pub fn fakesign(&mut self) -> Result<()> {
+        if let Some(ticket) = self.ticket_mut() {
+            ticket.fakesign()?;
+        }
+        if let Some(raw_tmd) = &mut self.raw_tmd {
+            Tmd::fakesign(raw_tmd)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the ticket and TMD from `meta` into the partition header area of a raw ISO image.
+///
+/// The TMD must be the same size as the original. Compressed formats are not supported,
+/// since they store the partition header separately.
+///
+/// # Examples
+///
+/// Making a partition region free:
+///
+/// ```no_run
+/// use std::fs::OpenOptions;
+///
+/// use nod::{Disc, PartitionKind, TmdRegion};
+///
+/// fn main() -> nod::Result<()> {
+///     let disc = Disc::new("path/to/file.iso")?;
+///     let info = disc.partitions().iter().find(|p| p.kind == PartitionKind::Data).unwrap();
+///     let mut meta = disc.open_partition(info.index)?.meta()?;
+///     meta.tmd_header_mut().unwrap().set_region(TmdRegion::RegionFree);
+///     meta.fakesign()?;
+///
+///     let mut file =
+///         OpenOptions::new().write(true).open("path/to/file.iso").expect("Failed to open file");
+///     nod::write_partition_header(&mut file, info, &meta)?;
+///     Ok(())
+/// }
+/// ```
+pub fn write_partition_header<W>(
+    out: &mut W,
+    info: &PartitionInfo,
+    meta: &PartitionMeta,
+) -> Result<()>
+where
+    W: Write + Seek + ?Sized,
+{
+    let (Some(raw_ticket), Some(raw_tmd)) = (meta.raw_ticket.as_deref(), meta.raw_tmd.as_deref())
+    else {
+        return Err(Error::Other("Partition has no ticket or TMD".to_string()));
+    };
+    if raw_ticket.len() != size_of::<Ticket>() {
+        return Err(Error::DiscFormat(format!("Invalid ticket size {:#X}", raw_ticket.len())));
+    }
+    if raw_tmd.len() as u64 != info.header.tmd_size() {
+        return Err(Error::DiscFormat(format!(
+            "TMD size {:#X} does not match partition header ({:#X})",
+            raw_tmd.len(),
+            info.header.tmd_size()
+        )));
+    }
+    let offset = info.start_sector as u64 * SECTOR_SIZE as u64;
+    write_at(out, offset, raw_ticket).context("Writing ticket")?;
+    write_at(out, offset + info.header.tmd_off(), raw_tmd).context("Writing TMD")?;
+    Ok(())
+}
+
+fn write_at<W>(out: &mut W, offset: u64, data: &[u8]) -> io::Result<()>
+where W: Write + Seek + ?Sized {
+    out.seek(SeekFrom::Start(offset))?;
+    out.write_all(data)
+}
+
+/// Clears the signature of an RSA-2048 signed blob and brute-forces the 4 bytes at
+/// `nonce_offset` until the SHA-1 hash of the signed data starts with 0x00.
+fn fakesign(data: &mut [u8], nonce_offset: usize) -> Result<(), String> {
+    if data.len() < SIGNED_DATA_OFFSET || nonce_offset + 4 > data.len() {
+        return Err(format!("data too small ({:#X} bytes)", data.len()));
+    }
+    data[4..SIGNED_DATA_OFFSET].fill(0);
+    for nonce in 0..=u32::MAX {
+        data[nonce_offset..nonce_offset + 4].copy_from_slice(&nonce.to_be_bytes());
+        if Sha1::digest(&data[SIGNED_DATA_OFFSET..])[0] == 0 {
+            return Ok(());
+        }
+    }
+    Err("no matching hash found".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use zerocopy::{big_endian::*, FromZeroes};
+
+    use super::*;
+    use crate::{CertChain, SignatureStatus};
+
+    #[test]
+    fn test_fakesign() {
+        let mut data = TmdHeader::new_zeroed().as_bytes().to_vec();
+        data.extend_from_slice(&[0xAA; 0x24]);
+        let tmd = TmdHeader::mut_from_prefix(&mut data).unwrap();
+        tmd.header.sig_type = U32::new(0x10001);
+        tmd.header.sig = [0xFF; 256];
+        tmd.num_contents = U16::new(1);
+        tmd.set_ios_version(58);
+        tmd.set_region(TmdRegion::RegionFree);
+        Tmd::fakesign(&mut data).unwrap();
+
+        let tmd = Tmd::new(&data).unwrap();
+        assert_eq!(tmd.ios_version(), Some(58));
+        assert_eq!(tmd.region(), TmdRegion::RegionFree);
+        assert_eq!(tmd.contents[0].content_id.get(), 0xAAAAAAAA);
+        let chain = CertChain { certs: vec![] };
+        assert_eq!(chain.verify_signed(&data), Ok(SignatureStatus::Fakesigned));
+    }
+
+    #[test]
+    fn test_ticket_title_id() {
+        let mut ticket = Ticket::new_zeroed();
+        ticket.header.sig_type = U32::new(0x10001);
+        ticket.sig_issuer[..26].copy_from_slice(b"Root-CA00000001-XS00000003");
+        ticket.title_id = 0x0001000052534245u64.to_be_bytes();
+        let title_key = [0x5A; 16];
+        ticket.encrypt_title_key(title_key).unwrap();
+        let encrypted = ticket.title_key;
+
+        ticket.set_title_id(0x0001000152534245).unwrap();
+        assert_eq!(ticket.title_id, 0x0001000152534245u64.to_be_bytes());
+        assert_ne!(ticket.title_key, encrypted);
+        assert_eq!(ticket.decrypt_title_key().unwrap(), title_key);
+
+        ticket.fakesign().unwrap();
+        let chain = CertChain { certs: vec![] };
+        assert_eq!(chain.verify_signed(ticket.as_bytes()), Ok(SignatureStatus::Fakesigned));
+    }
+
+    #[test]
+    fn test_ticket_keys_with() {
+        const ISSUER: &str = "Root-CA00000003-XS0000000c";
+        let mut keys = KeyStore::empty();
+        keys.insert_common_key(ISSUER, 0, [0x11; 16]);
+        keys.insert_common_key(ISSUER, 1, [0x22; 16]);
+
+        let mut ticket = Ticket::new_zeroed();
+        ticket.sig_issuer[..ISSUER.len()].copy_from_slice(ISSUER.as_bytes());
+        let title_key = [0x5A; 16];
+        ticket.encrypt_title_key_with(title_key, &keys).unwrap();
+        // Not in the default key store
+        assert!(ticket.set_common_key_idx(1).is_err());
+        assert_eq!(ticket.common_key_idx, 0);
+
+        ticket.set_common_key_idx_with(1, &keys).unwrap();
+        ticket.set_title_id_with(0x0001000152534245, &keys).unwrap();
+        assert_eq!(ticket.common_key_idx, 1);
+        assert_eq!(ticket.decrypt_title_key_with(&keys).unwrap(), title_key);
+        // Unknown key index leaves the ticket unchanged
+        let encrypted = ticket.title_key;
+        assert!(ticket.set_common_key_idx_with(2, &keys).is_err());
+        assert_eq!(ticket.common_key_idx, 1);
+        assert_eq!(ticket.title_key, encrypted);
+    }
+}
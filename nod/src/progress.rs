@@ -0,0 +1,87 @@
+//! Progress reporting and cancellation
+
+use std::fmt;
+
+/// The operation being reported by a [`ProgressHandler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressStage {
+    /// Wii: Rebuilding partition data hashes while opening the disc image.
+    ///
+    /// Progress is reported in hash groups (64 sectors, 2 MiB of data).
+    RebuildHashes,
+    /// Reading the disc image stream (e.g. conversion or verification).
+    ///
+    /// Progress is reported in bytes, once per MiB read and at the end of the stream.
+    Read,
+}
+
+impl fmt::Display for ProgressStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RebuildHashes => write!(f, "Rebuilding hashes"),
+            Self::Read => write!(f, "Reading"),
+        }
+    }
+}
+
+/// A progress update passed to [`ProgressHandler::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The operation in progress.
+    pub stage: ProgressStage,
+    /// The amount of work processed so far, in the stage's unit.
+    pub processed: u64,
+    /// The total amount of work, in the stage's unit.
+    pub total: u64,
+}
+
+/// Receives progress updates from long-running operations and allows them to be cancelled.
+///
+/// Handlers may be called from multiple threads at once, and should return quickly.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::{
+///     atomic::{AtomicBool, Ordering},
+///     Arc,
+/// };
+///
+/// use nod::{Progress, ProgressHandler};
+///
+/// #[derive(Default)]
+/// struct Handler {
+///     cancelled: AtomicBool,
+/// }
+///
+/// impl ProgressHandler for Handler {
+///     fn on_progress(&self, progress: Progress) {
+///         println!("{}: {}/{}", progress.stage, progress.processed, progress.total);
+///     }
+///
+///     fn is_cancelled(&self) -> bool { self.cancelled.load(Ordering::Relaxed) }
+/// }
+///
+/// let options = nod::OpenOptions {
+///     rebuild_encryption: true,
+///     progress: Some(Arc::new(Handler::default())),
+///     ..Default::default()
+/// };
+/// match nod::Disc::new_with_options("path/to/file.rvz", &options) {
+///     Ok(_) => {}
+///     Err(nod::Error::Cancelled) => println!("Cancelled"),
+///     Err(e) => panic!("Failed to open disc: {}", e),
+/// }
+/// ```
+pub trait ProgressHandler: Send + Sync {
+    /// Called when an operation makes progress.
+    fn on_progress(&self, progress: Progress);
+
+    /// Polled periodically by running operations. Returning `true` aborts the operation
+    /// with [`Error::Cancelled`](crate::Error::Cancelled).
+    fn is_cancelled(&self) -> bool { false }
+}
+
+impl fmt::Debug for dyn ProgressHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("ProgressHandler") }
+}
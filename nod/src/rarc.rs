@@ -0,0 +1,547 @@
+//! RARC archive types
+
+use std::{
+    borrow::Cow,
+    ffi::CStr,
+    io,
+    io::{Read, SeekFrom},
+    mem::size_of,
+};
+
+use encoding_rs::SHIFT_JIS;
+use zerocopy::{big_endian::*, AsBytes, FromBytes, FromZeroes};
+
+use crate::{
+    static_assert,
+    util::read::{read_box_slice, read_from},
+    Error, Fst, FstTree, ReadStream, Result, ResultContext, SharedWindowedReadStream,
+};
+
+/// RARC archive magic.
+pub const RARC_MAGIC: [u8; 4] = *b"RARC";
+
+/// Offset of the info block. All offsets in the info block are relative to it.
+const INFO_OFFSET: u64 = 0x20;
+
+/// RARC archive header, including the info block.
+#[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C, align(4))]
+pub struct RarcHeader {
+    /// Magic bytes ([`RARC_MAGIC`])
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
+    pub magic: [u8; 4],
+    /// Size of the archive
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub file_size: U32,
+    /// Size of the header (0x20)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub header_size: U32,
+    /// Offset of the data section, relative to the info block
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub data_offset: U32,
+    /// Size of the data section
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub data_size: U32,
+    /// Size of the data loaded into main memory
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub mram_size: U32,
+    /// Size of the data loaded into auxiliary memory
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub aram_size: U32,
+    /// Padding
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
+    _pad1: [u8; 4],
+    /// Number of directories
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub num_directories: U32,
+    /// Offset of the directory table, relative to the info block
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub directory_offset: U32,
+    /// Number of entries (files and directories, including `.` and `..`)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub num_entries: U32,
+    /// Offset of the entry table, relative to the info block
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub entry_offset: U32,
+    /// Size of the string table
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub string_table_size: U32,
+    /// Offset of the string table, relative to the info block
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub string_table_offset: U32,
+    /// The next free file ID
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub next_file_id: U16,
+    /// Whether file IDs match entry indices
+    pub sync_file_ids: u8,
+    /// Padding
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
+    _pad2: [u8; 5],
+}
+
+static_assert!(size_of::<RarcHeader>() == 0x40);
+
+/// A RARC directory.
+#[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C, align(4))]
+pub struct RarcDirectory {
+    /// The first four characters of the name in uppercase, or `ROOT`
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
+    pub kind: [u8; 4],
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    name_offset: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    name_hash: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    num_entries: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    first_entry: U32,
+}
+
+static_assert!(size_of::<RarcDirectory>() == 0x10);
+
+impl RarcDirectory {
+    /// Offset of the name in the string table.
+    pub fn name_offset(&self) -> u32 { self.name_offset.get() }
+
+    /// Hash of the name.
+    pub fn name_hash(&self) -> u16 { self.name_hash.get() }
+
+    /// Number of entries in the directory, including `.` and `..`.
+    pub fn num_entries(&self) -> usize { self.num_entries.get() as usize }
+
+    /// Index of the directory's first entry.
+    pub fn first_entry(&self) -> usize { self.first_entry.get() as usize }
+}
+
+/// A RARC file or directory entry.
+#[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C, align(4))]
+pub struct RarcEntry {
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    id: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    name_hash: U16,
+    flags: u8,
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
+    _pad1: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    name_offset: U16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    offset: U32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    length: U32,
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
+    _pad2: [u8; 4],
+}
+
+static_assert!(size_of::<RarcEntry>() == 0x14);
+
+impl RarcEntry {
+    /// File is loaded into auxiliary memory.
+    pub const FLAG_ARAM: u8 = 0x20;
+    /// File data is compressed.
+    pub const FLAG_COMPRESSED: u8 = 0x04;
+    /// Entry is a directory.
+    pub const FLAG_DIRECTORY: u8 = 0x02;
+    /// File is read from disc on demand.
+    pub const FLAG_DVD: u8 = 0x40;
+    /// Entry is a file.
+    pub const FLAG_FILE: u8 = 0x01;
+    /// File is loaded into main memory.
+    pub const FLAG_MRAM: u8 = 0x10;
+    /// Compressed file data is Yaz0. (Otherwise Yay0)
+    pub const FLAG_YAZ0: u8 = 0x80;
+
+    /// The file ID. `0xFFFF` for directories.
+    pub fn id(&self) -> u16 { self.id.get() }
+
+    /// Hash of the name.
+    pub fn name_hash(&self) -> u16 { self.name_hash.get() }
+
+    /// Entry flags. (See `FLAG_*`)
+    pub fn flags(&self) -> u8 { self.flags }
+
+    /// Whether the entry is a file.
+    pub fn is_file(&self) -> bool { self.flags & Self::FLAG_DIRECTORY == 0 }
+
+    /// Whether the entry is a directory, including `.` and `..`.
+    pub fn is_dir(&self) -> bool { self.flags & Self::FLAG_DIRECTORY != 0 }
+
+    /// Whether the file data is compressed.
+    pub fn is_compressed(&self) -> bool { self.flags & Self::FLAG_COMPRESSED != 0 }
+
+    /// Offset of the name in the string table.
+    pub fn name_offset(&self) -> u32 { self.name_offset.get() as u32 }
+
+    /// For files, the offset of the data relative to the data section.
+    pub fn data_offset(&self) -> u32 { self.offset.get() }
+
+    /// For directories, the index of the directory. `u32::MAX` for `..` in the root directory.
+    pub fn directory_index(&self) -> u32 { self.offset.get() }
+
+    /// For files, the size of the data.
+    pub fn length(&self) -> u64 { self.length.get() as u64 }
+}
+
+/// A RARC archive, read from any [`ReadStream`].
+///
+/// # Examples
+///
+/// Listing the contents of a Yaz0-compressed archive inside a disc partition:
+///
+/// ```no_run
+/// use std::io::{Cursor, Read};
+///
+/// use nod::{Disc, PartitionKind, Rarc};
+///
+/// fn main() -> nod::Result<()> {
+///     let disc = Disc::new("path/to/file.iso")?;
+///     let mut partition = disc.open_partition_kind(PartitionKind::Data)?;
+///     let meta = partition.meta()?;
+///     let fst = meta.fst()?;
+///     let (_, node) = fst.find("/Stage/Map1.arc").expect("Archive not found");
+///     let mut data = Vec::new();
+///     partition
+///         .open_file_decompressed(node)
+///         .and_then(|mut file| file.read_to_end(&mut data))
+///         .expect("Failed to read archive");
+///     let archive = Rarc::new(&mut Cursor::new(&data))?;
+///     for (path, entry) in archive.iter() {
+///         println!("{}: {} bytes", path, entry.length());
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Rarc {
+    /// The archive header.
+    pub header: RarcHeader,
+    directories: Box<[RarcDirectory]>,
+    entries: Box<[RarcEntry]>,
+    string_table: Box<[u8]>,
+}
+
+impl Rarc {
+    /// Whether the data starts with the RARC magic.
+    pub fn is_rarc(data: &[u8]) -> bool { data.starts_with(&RARC_MAGIC) }
+
+    /// Reads the header, directory and entry tables from a stream positioned anywhere;
+    /// offsets are relative to the start of the stream.
+    pub fn new<R>(reader: &mut R) -> Result<Self>
+    where R: ReadStream + ?Sized {
+        reader.seek(SeekFrom::Start(0)).context("Seeking to RARC header")?;
+        let header: RarcHeader = read_from(reader).context("Reading RARC header")?;
+        if header.magic != RARC_MAGIC {
+            return Err(Error::DiscFormat(format!("Invalid RARC magic: {:02X?}", header.magic)));
+        }
+        reader
+            .seek(SeekFrom::Start(INFO_OFFSET + header.directory_offset.get() as u64))
+            .context("Seeking to RARC directory table")?;
+        let directories: Box<[RarcDirectory]> =
+            read_box_slice(reader, header.num_directories.get() as usize)
+                .context("Reading RARC directory table")?;
+        reader
+            .seek(SeekFrom::Start(INFO_OFFSET + header.entry_offset.get() as u64))
+            .context("Seeking to RARC entry table")?;
+        let entries: Box<[RarcEntry]> = read_box_slice(reader, header.num_entries.get() as usize)
+            .context("Reading RARC entry table")?;
+        reader
+            .seek(SeekFrom::Start(INFO_OFFSET + header.string_table_offset.get() as u64))
+            .context("Seeking to RARC string table")?;
+        let string_table: Box<[u8]> =
+            read_box_slice(reader, header.string_table_size.get() as usize)
+                .context("Reading RARC string table")?;
+
+        if directories.is_empty() {
+            return Err(Error::DiscFormat("RARC: root directory not found".to_string()));
+        }
+        for (idx, dir) in directories.iter().enumerate() {
+            if dir.first_entry() + dir.num_entries() > entries.len() {
+                return Err(Error::DiscFormat(format!(
+                    "RARC: directory {} entries out of bounds",
+                    idx
+                )));
+            }
+        }
+        Ok(Self { header, directories, entries, string_table })
+    }
+
+    /// All directories. The first is the root directory.
+    pub fn directories(&self) -> &[RarcDirectory] { &self.directories }
+
+    /// All entries, in table order.
+    pub fn entries(&self) -> &[RarcEntry] { &self.entries }
+
+    /// The root directory.
+    pub fn root(&self) -> &RarcDirectory { &self.directories[0] }
+
+    /// The entries of a directory, including `.` and `..`.
+    pub fn dir_entries(&self, dir: &RarcDirectory) -> &[RarcEntry] {
+        &self.entries[dir.first_entry()..dir.first_entry() + dir.num_entries()]
+    }
+
+    /// Get the raw (Shift-JIS encoded) name at an offset in the string table, without the
+    /// null terminator.
+    pub fn get_raw_name(&self, name_offset: u32) -> Result<&[u8], String> {
+        let name_buf = self.string_table.get(name_offset as usize..).ok_or_else(|| {
+            format!(
+                "RARC: name offset {} out of bounds (string table size: {})",
+                name_offset,
+                self.string_table.len()
+            )
+        })?;
+        let c_string = CStr::from_bytes_until_nul(name_buf)
+            .map_err(|_| format!("RARC: name at offset {} not null-terminated", name_offset))?;
+        Ok(c_string.to_bytes())
+    }
+
+    /// Get the name at an offset in the string table.
+    pub fn get_name(&self, name_offset: u32) -> Result<Cow<'_, str>, String> {
+        let (decoded, _, errors) = SHIFT_JIS.decode(self.get_raw_name(name_offset)?);
+        if errors {
+            return Err(format!("RARC: Failed to decode name at offset {}", name_offset));
+        }
+        Ok(decoded)
+    }
+
+    /// The subdirectory an entry points to, if it's a directory other than `.` and `..`.
+    pub fn subdirectory(&self, entry: &RarcEntry) -> Option<&RarcDirectory> {
+        if !entry.is_dir() || self.is_special(entry) {
+            return None;
+        }
+        self.directories.get(entry.directory_index() as usize)
+    }
+
+    /// Finds a particular file or directory by path, relative to the root directory.
+    ///
+    /// Names are compared ASCII case-insensitively.
+    pub fn find(&self, path: &str) -> Option<&RarcEntry> {
+        let mut dir = self.root();
+        let mut components = path.split('/').filter(|s| !s.is_empty()).peekable();
+        while let Some(name) = components.next() {
+            let entry = self.dir_entries(dir).iter().find(|entry| {
+                !self.is_special(entry)
+                    && self
+                        .get_name(entry.name_offset())
+                        .is_ok_and(|n| n.eq_ignore_ascii_case(name))
+            })?;
+            if components.peek().is_none() {
+                return Some(entry);
+            }
+            dir = self.subdirectory(entry)?;
+        }
+        None
+    }
+
+    /// Iterates over all files and directories with their paths relative to the root,
+    /// skipping `.` and `..`. Directories are visited before their contents.
+    pub fn iter(&self) -> RarcIter<'_> {
+        RarcIter {
+            rarc: self,
+            stack: vec![(String::new(), self.dir_entries(self.root()).iter())],
+            visited: self.visited(),
+        }
+    }
+
+    /// Builds a file system table tree from the directory structure, so the archive can be
+    /// browsed like a disc's file system or a U8 archive.
+    ///
+    /// File node offsets are relative to the start of the archive (see
+    /// [`file_offset`](Self::file_offset)) and never shifted, so use `node.offset(false)`.
+    pub fn tree(&self) -> Result<FstTree, String> {
+        // Raw FST nodes: kind and name offset, offset or parent index, length or end index
+        let mut nodes = vec![[1 << 24, 0u32, 0u32]];
+        let mut strings = vec![0u8];
+        let mut visited = self.visited();
+        // Directory node indices with their remaining entries
+        let mut stack = vec![(0usize, self.dir_entries(self.root()).iter())];
+        while let Some((parent, iter)) = stack.last_mut() {
+            let parent = *parent;
+            let Some(entry) = iter.next() else {
+                nodes[parent][2] = nodes.len() as u32;
+                stack.pop();
+                continue;
+            };
+            if self.is_special(entry) {
+                continue;
+            }
+            let name_offset = strings.len() as u32;
+            if name_offset >= 1 << 24 {
+                return Err("RARC: string table too large".to_string());
+            }
+            strings.extend_from_slice(self.get_raw_name(entry.name_offset())?);
+            strings.push(0);
+            if entry.is_dir() {
+                let subdirectory = self.subdirectory(entry).ok_or_else(|| {
+                    format!("RARC: directory index {} out of bounds", entry.directory_index())
+                })?;
+                let dir_index = entry.directory_index() as usize;
+                if std::mem::replace(&mut visited[dir_index], true) {
+                    return Err(format!("RARC: directory {} referenced more than once", dir_index));
+                }
+                stack.push((nodes.len(), self.dir_entries(subdirectory).iter()));
+                nodes.push([1 << 24 | name_offset, parent as u32, 0]);
+            } else {
+                let offset = self.file_offset(entry);
+                let offset = u32::try_from(offset)
+                    .map_err(|_| format!("RARC: file offset {:#X} out of bounds", offset))?;
+                nodes.push([name_offset, offset, entry.length() as u32]);
+            }
+        }
+        let mut raw = nodes.iter().flatten().flat_map(|v| v.to_be_bytes()).collect::<Vec<u8>>();
+        raw.extend_from_slice(&strings);
+        Fst::new(&raw)?.tree()
+    }
+
+    /// Tracks entered directories, starting with the root. Each directory is entered at most
+    /// once, so malformed archives can't loop or reference a subtree many times over.
+    fn visited(&self) -> Vec<bool> {
+        let mut visited = vec![false; self.directories.len()];
+        visited[0] = true;
+        visited
+    }
+
+    /// The offset of a file's data relative to the start of the archive.
+    pub fn file_offset(&self, entry: &RarcEntry) -> u64 {
+        INFO_OFFSET + self.header.data_offset.get() as u64 + entry.data_offset() as u64
+    }
+
+    /// Opens a file in the archive as a windowed stream over `reader`.
+    pub fn open_file<'a, R>(
+        &self,
+        reader: &'a mut R,
+        entry: &RarcEntry,
+    ) -> io::Result<SharedWindowedReadStream<'a>>
+    where
+        R: ReadStream + ?Sized,
+    {
+        if !entry.is_file() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Not a file"));
+        }
+        reader.new_window(self.file_offset(entry), entry.length())
+    }
+
+    /// Reads a file in the archive into memory.
+    pub fn read_file<R>(&self, reader: &mut R, entry: &RarcEntry) -> io::Result<Vec<u8>>
+    where R: ReadStream + ?Sized {
+        let mut data = Vec::with_capacity(entry.length() as usize);
+        self.open_file(reader, entry)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn is_special(&self, entry: &RarcEntry) -> bool {
+        entry.is_dir() && matches!(self.get_raw_name(entry.name_offset()), Ok(b".") | Ok(b".."))
+    }
+}
+
+/// Iterator over the files and directories in a RARC archive. (See [`Rarc::iter`])
+pub struct RarcIter<'a> {
+    rarc: &'a Rarc,
+    stack: Vec<(String, std::slice::Iter<'a, RarcEntry>)>,
+    visited: Vec<bool>,
+}
+
+impl<'a> Iterator for RarcIter<'a> {
+    type Item = (String, &'a RarcEntry);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (prefix, iter) = self.stack.last_mut()?;
+            let Some(entry) = iter.next() else {
+                self.stack.pop();
+                continue;
+            };
+            if self.rarc.is_special(entry) {
+                continue;
+            }
+            let name = self.rarc.get_name(entry.name_offset()).unwrap_or_default();
+            let path =
+                if prefix.is_empty() { name.into_owned() } else { format!("{}/{}", prefix, name) };
+            if let Some(dir) = self.rarc.subdirectory(entry) {
+                // Directories referenced more than once are only entered the first time
+                if !std::mem::replace(&mut self.visited[entry.directory_index() as usize], true) {
+                    self.stack.push((path.clone(), self.rarc.dir_entries(dir).iter()));
+                }
+            }
+            return Some((path, entry));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_rarc() {
+        // root: a.bin, sub/, sub/b.txt
+        let strings = b".\0..\0root\0a.bin\0sub\0b.txt\0";
+        let make_entry = |id: u16, flags: u8, name: u16, offset: u32, length: u32| {
+            let mut e = id.to_be_bytes().to_vec();
+            e.extend_from_slice(&[0, 0, flags, 0]);
+            e.extend_from_slice(&name.to_be_bytes());
+            e.extend_from_slice(&offset.to_be_bytes());
+            e.extend_from_slice(&length.to_be_bytes());
+            e.extend_from_slice(&[0; 4]);
+            e
+        };
+        let mut data = RARC_MAGIC.to_vec();
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0x20, 0, 0, 1, 0, 0, 0, 0, 0x20]);
+        data.resize(0x20, 0);
+        for v in [2u32, 0x20, 7, 0x40, strings.len() as u32, 0xE0] {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        data.resize(0x40, 0);
+        // Directories
+        data.extend_from_slice(b"ROOT\0\0\0\x05\0\0\0\x04\0\0\0\0");
+        data.extend_from_slice(b"SUB \0\0\0\x10\0\0\0\x03\0\0\0\x04");
+        data.resize(0x60, 0);
+        // Entries
+        data.extend(make_entry(0, RarcEntry::FLAG_FILE, 10, 0, 4));
+        data.extend(make_entry(0xFFFF, RarcEntry::FLAG_DIRECTORY, 16, 1, 0));
+        data.extend(make_entry(0xFFFF, RarcEntry::FLAG_DIRECTORY, 0, 0, 0));
+        data.extend(make_entry(0xFFFF, RarcEntry::FLAG_DIRECTORY, 2, u32::MAX, 0));
+        data.extend(make_entry(1, RarcEntry::FLAG_FILE, 20, 0x10, 2));
+        data.extend(make_entry(0xFFFF, RarcEntry::FLAG_DIRECTORY, 0, 1, 0));
+        data.extend(make_entry(0xFFFF, RarcEntry::FLAG_DIRECTORY, 2, 0, 0));
+        data.resize(0x100, 0);
+        data.extend_from_slice(strings);
+        data.resize(0x120, 0);
+        data.extend_from_slice(b"AAAA");
+        data.resize(0x130, 0);
+        data.extend_from_slice(b"hi");
+
+        let mut reader = Cursor::new(data);
+        let rarc = Rarc::new(&mut reader).unwrap();
+        assert_eq!(rarc.get_name(rarc.root().name_offset()).unwrap(), "root");
+        let paths = rarc.iter().map(|(path, _)| path).collect::<Vec<_>>();
+        assert_eq!(paths, ["a.bin", "sub", "sub/b.txt"]);
+        let entry = rarc.find("SUB/b.txt").unwrap();
+        assert_eq!(entry.id(), 1);
+        assert_eq!(rarc.read_file(&mut reader, entry).unwrap(), b"hi");
+        assert!(rarc.find("sub/..").is_none());
+        assert!(rarc.find("missing").is_none());
+
+        let tree = rarc.tree().unwrap();
+        let paths = tree.iter().map(|e| (e.path.as_str(), e.depth, e.parent)).collect::<Vec<_>>();
+        assert_eq!(paths, [("a.bin", 1, Some(0)), ("sub", 1, Some(0)), ("sub/b.txt", 2, Some(2))]);
+        assert_eq!(tree.root().children, [1, 2]);
+        let file = tree.get(3).unwrap();
+        assert_eq!((file.node.offset(false), file.node.length()), (0x130, 2));
+        assert_eq!(tree.get(1).unwrap().node.offset(false), 0x120);
+
+        // sub/b.txt replaced with sub/sub, a reference back to its own directory
+        let mut data = reader.into_inner();
+        data[0xB0..0xC4].copy_from_slice(&make_entry(0xFFFF, RarcEntry::FLAG_DIRECTORY, 16, 1, 0));
+        let rarc = Rarc::new(&mut Cursor::new(data)).unwrap();
+        let paths = rarc.iter().map(|(path, _)| path).collect::<Vec<_>>();
+        assert_eq!(paths, ["a.bin", "sub", "sub/sub"]);
+        assert!(rarc.tree().is_err());
+    }
+}
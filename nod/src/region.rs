@@ -0,0 +1,267 @@
+//! Region and age rating decoding
+
+use std::fmt;
+
+use crate::{PartitionMeta, Tmd, TmdRegion};
+
+/// Disc region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Region {
+    /// Japan and Taiwan (NTSC-J)
+    NtscJ,
+    /// Americas (NTSC-U)
+    NtscU,
+    /// Europe and Australia (PAL)
+    Pal,
+    /// Korea (KOR)
+    Korea,
+    /// Region free
+    RegionFree,
+}
+
+impl Region {
+    /// Detects the region from the region letter of a game ID. (e.g. `E` in `RSBE01`)
+    pub fn from_game_id(game_id: &[u8]) -> Option<Self> {
+        match game_id.get(3)? {
+            b'J' | b'W' => Some(Self::NtscJ),
+            b'E' | b'N' | b'B' => Some(Self::NtscU),
+            b'D' | b'F' | b'H' | b'I' | b'L' | b'M' | b'P' | b'R' | b'S' | b'U' | b'V' | b'X'
+            | b'Y' | b'Z' => Some(Self::Pal),
+            b'K' | b'Q' | b'T' => Some(Self::Korea),
+            b'A' => Some(Self::RegionFree),
+            _ => None,
+        }
+    }
+
+    /// Detects the region from a bi2.bin country code.
+    /// (0 = Japan, 1 = USA, 2 = Europe, 4 = Korea)
+    pub fn from_country_code(country_code: u32) -> Option<Self> {
+        match country_code {
+            0 => Some(Self::NtscJ),
+            1 => Some(Self::NtscU),
+            2 => Some(Self::Pal),
+            4 => Some(Self::Korea),
+            _ => None,
+        }
+    }
+
+    /// Detects the region from a TMD region.
+    pub fn from_tmd_region(region: TmdRegion) -> Option<Self> {
+        match region {
+            TmdRegion::Japan => Some(Self::NtscJ),
+            TmdRegion::Usa => Some(Self::NtscU),
+            TmdRegion::Europe => Some(Self::Pal),
+            TmdRegion::RegionFree => Some(Self::RegionFree),
+            TmdRegion::Korea => Some(Self::Korea),
+            TmdRegion::Unknown(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NtscJ => write!(f, "NTSC-J"),
+            Self::NtscU => write!(f, "NTSC-U"),
+            Self::Pal => write!(f, "PAL"),
+            Self::Korea => write!(f, "KOR"),
+            Self::RegionFree => write!(f, "Region free"),
+        }
+    }
+}
+
+/// Where a region was detected from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionSource {
+    /// The region letter of the game ID.
+    GameId,
+    /// The bi2.bin country code.
+    Bi2,
+    /// The TMD region. (Wii only)
+    Tmd,
+}
+
+impl fmt::Display for RegionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GameId => write!(f, "Game ID"),
+            Self::Bi2 => write!(f, "bi2.bin"),
+            Self::Tmd => write!(f, "TMD"),
+        }
+    }
+}
+
+/// Regions detected from each source in a partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RegionInfo {
+    /// Region from the game ID's region letter.
+    pub game_id: Option<Region>,
+    /// Region from the bi2.bin country code.
+    pub bi2: Option<Region>,
+    /// Region from the TMD. (Wii only)
+    pub tmd: Option<Region>,
+}
+
+impl RegionInfo {
+    /// All detected regions with their sources.
+    pub fn sources(&self) -> impl Iterator<Item = (RegionSource, Region)> {
+        [
+            (RegionSource::Tmd, self.tmd),
+            (RegionSource::Bi2, self.bi2),
+            (RegionSource::GameId, self.game_id),
+        ]
+        .into_iter()
+        .filter_map(|(source, region)| region.map(|r| (source, r)))
+    }
+
+    /// The effective region.
+    ///
+    /// The TMD region is preferred since it's enforced by the Wii system menu, followed by
+    /// the bi2.bin country code (enforced by the GameCube IPL), then the game ID.
+    pub fn region(&self) -> Option<Region> { self.sources().next().map(|(_, r)| r) }
+
+    /// Sources that disagree with the effective [`region`](Self::region).
+    pub fn conflicts(&self) -> Vec<(RegionSource, Region)> {
+        let Some(region) = self.region() else {
+            return vec![];
+        };
+        self.sources().filter(|&(_, r)| r != region).collect()
+    }
+}
+
+/// Age rating organisation, in the order of the TMD rating bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RatingBoard {
+    /// CERO (Japan)
+    Cero,
+    /// ESRB (North America)
+    Esrb,
+    /// USK (Germany)
+    Usk,
+    /// PEGI (Europe)
+    Pegi,
+    /// PEGI (Finland)
+    PegiFinland,
+    /// PEGI (Portugal)
+    PegiPortugal,
+    /// BBFC (United Kingdom)
+    Bbfc,
+    /// ACB (Australia)
+    Acb,
+    /// GRB (Korea)
+    Grb,
+}
+
+impl RatingBoard {
+    /// All rating organisations.
+    pub const ALL: [RatingBoard; 9] = [
+        Self::Cero,
+        Self::Esrb,
+        Self::Usk,
+        Self::Pegi,
+        Self::PegiFinland,
+        Self::PegiPortugal,
+        Self::Bbfc,
+        Self::Acb,
+        Self::Grb,
+    ];
+
+    /// Index of the organisation's byte in the TMD ratings. (Index 2 is unused.)
+    pub fn index(self) -> usize {
+        match self {
+            Self::Cero => 0,
+            Self::Esrb => 1,
+            Self::Usk => 3,
+            Self::Pegi => 4,
+            Self::PegiFinland => 5,
+            Self::PegiPortugal => 6,
+            Self::Bbfc => 7,
+            Self::Acb => 8,
+            Self::Grb => 9,
+        }
+    }
+}
+
+impl fmt::Display for RatingBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cero => write!(f, "CERO"),
+            Self::Esrb => write!(f, "ESRB"),
+            Self::Usk => write!(f, "USK"),
+            Self::Pegi => write!(f, "PEGI"),
+            Self::PegiFinland => write!(f, "PEGI (Finland)"),
+            Self::PegiPortugal => write!(f, "PEGI (Portugal)"),
+            Self::Bbfc => write!(f, "BBFC"),
+            Self::Acb => write!(f, "ACB"),
+            Self::Grb => write!(f, "GRB"),
+        }
+    }
+}
+
+/// Decoded TMD age ratings.
+///
+/// Each byte holds the minimum age in the low 5 bits. Bit 7 marks the rating as unused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ratings(pub [u8; 16]);
+
+impl Ratings {
+    const AGE_MASK: u8 = 0x1F;
+    const UNUSED: u8 = 0x80;
+
+    /// The minimum age for an organisation, if rated.
+    pub fn get(&self, board: RatingBoard) -> Option<u8> {
+        let value = self.0[board.index()];
+        (value & Self::UNUSED == 0).then_some(value & Self::AGE_MASK)
+    }
+
+    /// All rated organisations with their minimum age.
+    pub fn iter(&self) -> impl Iterator<Item = (RatingBoard, u8)> + '_ {
+        RatingBoard::ALL.into_iter().filter_map(|board| self.get(board).map(|age| (board, age)))
+    }
+}
+
+impl Tmd<'_> {
+    /// Age ratings.
+    pub fn ratings(&self) -> Ratings { Ratings(self.header.ratings) }
+}
+
+impl PartitionMeta {
+    /// Detects the region from the game ID, bi2.bin and TMD.
+    pub fn region_info(&self) -> Result<RegionInfo, String> {
+        Ok(RegionInfo {
+            game_id: Region::from_game_id(&self.header().game_id),
+            bi2: Region::from_country_code(self.bi2().country_code()),
+            tmd: self.tmd()?.and_then(|tmd| Region::from_tmd_region(tmd.region())),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region_info() {
+        let info = RegionInfo {
+            game_id: Region::from_game_id(b"RSBE01"),
+            bi2: Region::from_country_code(2),
+            tmd: Region::from_tmd_region(TmdRegion::Usa),
+        };
+        assert_eq!(info.region(), Some(Region::NtscU));
+        assert_eq!(info.conflicts(), vec![(RegionSource::Bi2, Region::Pal)]);
+        assert_eq!(RegionInfo::default().region(), None);
+    }
+
+    #[test]
+    fn test_ratings() {
+        let mut raw = [0x80; 16];
+        raw[RatingBoard::Cero.index()] = 12;
+        raw[RatingBoard::Pegi.index()] = 0x20 | 7;
+        let ratings = Ratings(raw);
+        assert_eq!(ratings.get(RatingBoard::Esrb), None);
+        assert_eq!(ratings.iter().collect::<Vec<_>>(), vec![
+            (RatingBoard::Cero, 12),
+            (RatingBoard::Pegi, 7)
+        ]);
+    }
+}
@@ -0,0 +1,382 @@
+//! File system table search
+
+use std::fmt;
+
+use crate::{
+    disc::{
+        wii::{HASHES_SIZE, SECTOR_DATA_SIZE},
+        SECTOR_SIZE,
+    },
+    FstEntry, FstTree, PartitionInfo,
+};
+
+/// A file system entry matched by a search, with the location of its data.
+#[derive(Clone, Debug)]
+pub struct FstMatch<'a> {
+    /// The matched entry.
+    pub entry: &'a FstEntry,
+    /// For files, the offset of the file data within the partition's (decrypted) data.
+    /// Zero for directories. See [`FstMatch::disc_offset`] for the offset on disc.
+    pub partition_offset: u64,
+    /// For files, the byte size of the file. Zero for directories.
+    pub size: u64,
+}
+
+impl<'a> FstMatch<'a> {
+    fn new(entry: &'a FstEntry, is_wii: bool) -> Self {
+        if entry.node.is_file() {
+            Self { entry, partition_offset: entry.node.offset(is_wii), size: entry.node.length() }
+        } else {
+            Self { entry, partition_offset: 0, size: 0 }
+        }
+    }
+
+    /// The full path of the matched entry, without a leading slash.
+    pub fn path(&self) -> &'a str { &self.entry.path }
+
+    /// For files, the offset of the start of the file data in the disc image. Zero for
+    /// directories.
+    ///
+    /// `partition` is the Wii partition the entry belongs to, or `None` for GameCube discs.
+    /// Wii partition data is stored in 0x7C00 byte blocks following each sector's hash block,
+    /// so file data is only contiguous on disc up to the end of its first sector.
+    pub fn disc_offset(&self, partition: Option<&PartitionInfo>) -> u64 {
+        match partition {
+            Some(info) if self.entry.node.is_file() => {
+                let sector = self.partition_offset / SECTOR_DATA_SIZE as u64;
+                (info.data_start_sector as u64 + sector) * SECTOR_SIZE as u64
+                    + HASHES_SIZE as u64
+                    + self.partition_offset % SECTOR_DATA_SIZE as u64
+            }
+            _ => self.partition_offset,
+        }
+    }
+}
+
+impl FstTree {
+    /// Finds all entries (files and directories) matching a predicate, in FST order.
+    ///
+    /// `is_wii` is used to calculate file data offsets. (See [`Node::offset`](crate::Node::offset))
+    pub fn search<'a, P>(
+        &'a self,
+        is_wii: bool,
+        mut predicate: P,
+    ) -> impl Iterator<Item = FstMatch<'a>> + 'a
+    where
+        P: FnMut(&FstEntry) -> bool + 'a,
+    {
+        self.iter()
+            .filter(move |entry| predicate(entry))
+            .map(move |entry| FstMatch::new(entry, is_wii))
+    }
+
+    /// Finds all entries whose full paths match a glob pattern.
+    pub fn glob<'a>(
+        &'a self,
+        is_wii: bool,
+        glob: &'a Glob,
+    ) -> impl Iterator<Item = FstMatch<'a>> + 'a {
+        self.search(is_wii, move |entry| glob.is_match(&entry.path))
+    }
+
+    /// Finds all entries whose full paths match a regular expression.
+    ///
+    /// Paths do not include a leading slash. The expression is not anchored: use `^` and `$`
+    /// to match the whole path.
+    #[cfg(feature = "regex")]
+    pub fn regex<'a>(
+        &'a self,
+        is_wii: bool,
+        regex: &'a PathRegex,
+    ) -> impl Iterator<Item = FstMatch<'a>> + 'a {
+        self.search(is_wii, move |entry| regex.is_match(&entry.path))
+    }
+}
+
+/// A glob pattern for matching file system paths.
+///
+/// Patterns are matched against the full path from the root, and a leading `/` is optional.
+/// Matching is ASCII case-insensitive, like [`Fst::find`](crate::Fst::find).
+///
+/// Supported syntax:
+/// - `*` matches any number of characters within a path component.
+/// - `?` matches a single character within a path component.
+/// - `[abc]`, `[a-z]` and `[!a-z]` match a single character in (or not in) a set.
+/// - `**` as a whole path component matches any number of components, including none.
+///
+/// # Examples
+///
+/// ```
+/// use nod::Glob;
+///
+/// let glob = Glob::new("**/*.szs").unwrap();
+/// assert!(glob.is_match("Race/Course/castle_course.szs"));
+/// assert!(glob.is_match("Boot.szs"));
+///
+/// let glob = Glob::new("/audio/*.ast").unwrap();
+/// assert!(glob.is_match("audio/title.ast"));
+/// assert!(!glob.is_match("audio/bgm/title.ast"));
+/// ```
+#[derive(Clone)]
+pub struct Glob {
+    pattern: String,
+    components: Vec<Component>,
+}
+
+#[derive(Clone, Debug)]
+enum Component {
+    /// `**`
+    AnyComponents,
+    Tokens(Vec<Token>),
+}
+
+#[derive(Clone, Debug)]
+enum Token {
+    Char(char),
+    AnyChar,
+    AnyChars,
+    Set { negated: bool, ranges: Vec<(char, char)> },
+}
+
+impl Glob {
+    /// Parses a glob pattern.
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let components = pattern
+            .trim_start_matches('/')
+            .split('/')
+            .map(|component| {
+                if component == "**" {
+                    Ok(Component::AnyComponents)
+                } else {
+                    parse_tokens(component).map(Component::Tokens)
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(|e| format!("Invalid glob pattern {:?}: {}", pattern, e))?;
+        Ok(Self { pattern: pattern.to_string(), components })
+    }
+
+    /// The original pattern.
+    pub fn as_str(&self) -> &str { &self.pattern }
+
+    /// Whether a path matches the pattern.
+    pub fn is_match(&self, path: &str) -> bool {
+        let path = path.trim_start_matches('/').split('/').collect::<Vec<_>>();
+        match_components(&self.components, &path)
+    }
+}
+
+impl fmt::Debug for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Glob").field(&self.pattern).finish()
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.pattern) }
+}
+
+/// A regular expression for matching file system paths.
+///
+/// Paths do not include a leading slash. The expression is not anchored: use `^` and `$`
+/// to match the whole path.
+///
+/// # Examples
+///
+/// ```
+/// use nod::PathRegex;
+///
+/// let regex = PathRegex::new(r"^audio/.*\.ast$").unwrap();
+/// assert!(regex.is_match("audio/bgm/title.ast"));
+/// assert!(!regex.is_match("audio/title.ast.bak"));
+/// ```
+#[cfg(feature = "regex")]
+#[derive(Clone)]
+pub struct PathRegex(regex::Regex);
+
+#[cfg(feature = "regex")]
+impl PathRegex {
+    /// Parses a regular expression.
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        regex::Regex::new(pattern).map(Self).map_err(|e| e.to_string())
+    }
+
+    /// The original pattern.
+    pub fn as_str(&self) -> &str { self.0.as_str() }
+
+    /// Whether the expression matches anywhere in the path.
+    pub fn is_match(&self, path: &str) -> bool { self.0.is_match(path) }
+}
+
+#[cfg(feature = "regex")]
+impl fmt::Debug for PathRegex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PathRegex").field(&self.as_str()).finish()
+    }
+}
+
+#[cfg(feature = "regex")]
+impl fmt::Display for PathRegex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.as_str()) }
+}
+
+fn parse_tokens(component: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = component.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '*' => {
+                // Consecutive wildcards are equivalent to one
+                if matches!(tokens.last(), Some(Token::AnyChars)) {
+                    continue;
+                }
+                Token::AnyChars
+            }
+            '?' => Token::AnyChar,
+            '[' => {
+                let mut negated = false;
+                let mut ranges = Vec::new();
+                let mut closed = false;
+                let mut first = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '!' | '^' if first => negated = true,
+                        ']' if !ranges.is_empty() => {
+                            closed = true;
+                            break;
+                        }
+                        c => {
+                            let mut lookahead = chars.clone();
+                            if lookahead.next() == Some('-') {
+                                match lookahead.next() {
+                                    Some(end) if end != ']' => {
+                                        chars = lookahead;
+                                        ranges.push((c, end));
+                                    }
+                                    _ => ranges.push((c, c)),
+                                }
+                            } else {
+                                ranges.push((c, c));
+                            }
+                        }
+                    }
+                    first = false;
+                }
+                if !closed {
+                    return Err("unterminated character set".to_string());
+                }
+                Token::Set { negated, ranges }
+            }
+            c => Token::Char(c),
+        });
+    }
+    Ok(tokens)
+}
+
+fn match_components(components: &[Component], path: &[&str]) -> bool {
+    match_wildcard(
+        components,
+        path,
+        |component| matches!(component, Component::AnyComponents),
+        |component, name| match component {
+            Component::Tokens(tokens) => match_tokens(tokens, &name.chars().collect::<Vec<_>>()),
+            Component::AnyComponents => unreachable!(),
+        },
+    )
+}
+
+fn match_tokens(tokens: &[Token], name: &[char]) -> bool {
+    match_wildcard(
+        tokens,
+        name,
+        |token| matches!(token, Token::AnyChars),
+        |token, &c| match_char(token, c),
+    )
+}
+
+/// Matches a sequence against a pattern containing wildcards that match any number of items,
+/// using the iterative two-pointer algorithm. On a mismatch, only the most recent wildcard is
+/// retried with one more item, which keeps matching polynomial.
+fn match_wildcard<P, T>(
+    pattern: &[P],
+    input: &[T],
+    is_wildcard: impl Fn(&P) -> bool,
+    matches: impl Fn(&P, &T) -> bool,
+) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // Pattern position after the last wildcard, and the input position it was retried at
+    let mut retry = None;
+    while i < input.len() {
+        if p < pattern.len() && is_wildcard(&pattern[p]) {
+            p += 1;
+            retry = Some((p, i));
+        } else if p < pattern.len() && matches(&pattern[p], &input[i]) {
+            p += 1;
+            i += 1;
+        } else if let Some((retry_p, retry_i)) = retry {
+            p = retry_p;
+            i = retry_i + 1;
+            retry = Some((retry_p, i));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(is_wildcard)
+}
+
+fn match_char(token: &Token, c: char) -> bool {
+    match token {
+        Token::Char(expected) => expected.eq_ignore_ascii_case(&c),
+        Token::AnyChar => true,
+        Token::AnyChars => unreachable!(),
+        Token::Set { negated, ranges } => {
+            let lower = c.to_ascii_lowercase();
+            let upper = c.to_ascii_uppercase();
+            let found = ranges.iter().any(|&(start, end)| {
+                (start..=end).contains(&lower) || (start..=end).contains(&upper)
+            });
+            found != *negated
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob() {
+        let cases = [
+            ("**/*.szs", "Boot.szs", true),
+            ("**/*.szs", "Race/Course/castle_course.SZS", true),
+            ("**/*.szs", "Race/Course/castle_course.szs.bak", false),
+            ("/audio/*.ast", "audio/title.ast", true),
+            ("/audio/*.ast", "audio/bgm/title.ast", false),
+            ("audio/**", "audio/bgm/title.ast", true),
+            ("audio/**/title.ast", "audio/title.ast", true),
+            ("stage/st??.arc", "stage/st01.arc", true),
+            ("stage/st??.arc", "stage/st1.arc", false),
+            ("stage/st[0-4]*.arc", "stage/st31.arc", true),
+            ("stage/st[!0-4]*.arc", "stage/st31.arc", false),
+            ("[]]x", "]x", true),
+            ("**/a/**/b", "x/a/y/a/z/b", true),
+            ("**/a/**/b", "x/a/y/b/c", false),
+            ("*a*b*c", "xaybzc", true),
+            ("*a*b*c", "xaybzcd", false),
+        ];
+        for (pattern, path, expected) in cases {
+            let glob = Glob::new(pattern).unwrap();
+            assert_eq!(glob.is_match(path), expected, "{} -> {}", pattern, path);
+        }
+        assert!(Glob::new("stage/st[0-4").is_err());
+    }
+
+    #[test]
+    fn test_glob_backtracking() {
+        // Exponential with recursive backtracking
+        let glob = Glob::new(&format!("{}b", "*a".repeat(32))).unwrap();
+        assert!(!glob.is_match(&"a".repeat(64)));
+        let glob = Glob::new(&format!("{}b", "**/a/".repeat(32))).unwrap();
+        assert!(!glob.is_match(&"a/".repeat(64)));
+    }
+}
@@ -10,7 +10,7 @@ pub trait ReadStream: Read + Seek {
     /// Creates a windowed read sub-stream with offset and size.
     ///
     /// Seeks underlying stream immediately.
-    fn new_window(&mut self, offset: u64, size: u64) -> io::Result<SharedWindowedReadStream> {
+    fn new_window(&mut self, offset: u64, size: u64) -> io::Result<SharedWindowedReadStream<'_>> {
         self.seek(SeekFrom::Start(offset))?;
         Ok(SharedWindowedReadStream { base: self.as_dyn(), begin: offset, end: offset + size })
     }
@@ -64,7 +64,7 @@ impl<'a> Seek for SharedWindowedReadStream<'a> {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         let result = self.base.seek(match pos {
             SeekFrom::Start(p) => SeekFrom::Start(self.begin + p),
-            SeekFrom::End(p) => SeekFrom::End(self.end as i64 + p),
+            SeekFrom::End(p) => SeekFrom::Start(self.end.saturating_add_signed(p)),
             SeekFrom::Current(_) => pos,
         })?;
         if result < self.begin || result > self.end {
@@ -78,3 +78,70 @@ impl<'a> Seek for SharedWindowedReadStream<'a> {
         Ok(self.base.stream_position()? - self.begin)
     }
 }
+
+/// A window into an owned [`ReadStream`].
+///
+/// Unlike [`SharedWindowedReadStream`], this can be moved independently of the
+/// stream it was created from.
+#[derive(Clone)]
+pub struct OwnedWindowedReadStream<T> {
+    /// The base stream.
+    pub base: T,
+    /// The beginning of the window in bytes.
+    pub begin: u64,
+    /// The end of the window in bytes.
+    pub end: u64,
+}
+
+impl<T> OwnedWindowedReadStream<T>
+where T: ReadStream
+{
+    /// Creates a new windowed stream with offset and size.
+    ///
+    /// Seeks underlying stream immediately.
+    pub fn new(mut base: T, offset: u64, size: u64) -> io::Result<Self> {
+        base.seek(SeekFrom::Start(offset))?;
+        Ok(Self { base, begin: offset, end: offset + size })
+    }
+
+    /// Returns the base stream.
+    pub fn into_inner(self) -> T { self.base }
+}
+
+impl<T> Read for OwnedWindowedReadStream<T>
+where T: ReadStream
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let pos = self.stream_position()?;
+        let size = self.end - self.begin;
+        if pos >= size {
+            return Ok(0);
+        }
+        self.base.read(if pos + buf.len() as u64 > size {
+            &mut buf[..(size - pos) as usize]
+        } else {
+            buf
+        })
+    }
+}
+
+impl<T> Seek for OwnedWindowedReadStream<T>
+where T: ReadStream
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let result = self.base.seek(match pos {
+            SeekFrom::Start(p) => SeekFrom::Start(self.begin.saturating_add(p)),
+            SeekFrom::End(p) => SeekFrom::Start(self.end.saturating_add_signed(p)),
+            SeekFrom::Current(_) => pos,
+        })?;
+        if result < self.begin {
+            Err(io::Error::from(io::ErrorKind::UnexpectedEof))
+        } else {
+            Ok(result - self.begin)
+        }
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.base.stream_position()? - self.begin)
+    }
+}
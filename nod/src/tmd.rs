@@ -0,0 +1,285 @@
+//! Title metadata (TMD) types
+
+use std::{
+    fmt,
+    mem::{size_of, size_of_val},
+};
+
+use sha1::{Digest, Sha1};
+use zerocopy::{big_endian::*, AsBytes, FromBytes, FromZeroes};
+
+use crate::{disc::wii::TmdHeader, static_assert, PartitionMeta};
+
+/// A content record following the TMD header.
+#[derive(Debug, Clone, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[repr(C)]
+pub struct ContentRecord {
+    /// Content ID
+    pub content_id: U32,
+    /// Content index
+    pub index: U16,
+    /// Content type flags (See [`ContentRecord::CONTENT_TYPE_NORMAL`] etc.)
+    pub content_type: U16,
+    /// Content size
+    pub size: U64,
+    /// SHA-1 hash of the decrypted content
+    pub hash: [u8; 20],
+}
+
+static_assert!(size_of::<ContentRecord>() == 0x24);
+
+impl ContentRecord {
+    /// Normal content.
+    pub const CONTENT_TYPE_NORMAL: u16 = 0x0001;
+    /// Optional content. (DLC)
+    pub const CONTENT_TYPE_OPTIONAL: u16 = 0x4000;
+    /// Content shared between titles. (shared1 / content.map)
+    pub const CONTENT_TYPE_SHARED: u16 = 0x8000;
+
+    /// Content size in bytes.
+    pub fn size(&self) -> u64 { self.size.get() }
+
+    /// Whether the content is optional. (DLC)
+    pub fn is_optional(&self) -> bool { self.content_type.get() & Self::CONTENT_TYPE_OPTIONAL != 0 }
+
+    /// Whether the content is shared between titles.
+    pub fn is_shared(&self) -> bool { self.content_type.get() & Self::CONTENT_TYPE_SHARED != 0 }
+
+    /// Whether the SHA-1 hash of the decrypted content data matches the record.
+    pub fn verify(&self, data: &[u8]) -> bool { Sha1::digest(data).as_slice() == self.hash }
+}
+
+/// TMD region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TmdRegion {
+    /// Japan
+    Japan,
+    /// USA
+    Usa,
+    /// Europe
+    Europe,
+    /// Region free
+    RegionFree,
+    /// Korea
+    Korea,
+    /// Unknown region value
+    Unknown(u16),
+}
+
+impl From<u16> for TmdRegion {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => Self::Japan,
+            1 => Self::Usa,
+            2 => Self::Europe,
+            3 => Self::RegionFree,
+            4 => Self::Korea,
+            v => Self::Unknown(v),
+        }
+    }
+}
+
+impl From<TmdRegion> for u16 {
+    fn from(value: TmdRegion) -> Self {
+        match value {
+            TmdRegion::Japan => 0,
+            TmdRegion::Usa => 1,
+            TmdRegion::Europe => 2,
+            TmdRegion::RegionFree => 3,
+            TmdRegion::Korea => 4,
+            TmdRegion::Unknown(v) => v,
+        }
+    }
+}
+
+impl fmt::Display for TmdRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Japan => write!(f, "Japan"),
+            Self::Usa => write!(f, "USA"),
+            Self::Europe => write!(f, "Europe"),
+            Self::RegionFree => write!(f, "Region free"),
+            Self::Korea => write!(f, "Korea"),
+            Self::Unknown(v) => write!(f, "Unknown ({})", v),
+        }
+    }
+}
+
+/// Decoded TMD access rights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessRights(pub u32);
+
+impl AccessRights {
+    /// Whether the title runs with AHBPROT disabled, allowing direct hardware access.
+    pub fn ahb_access(&self) -> bool { self.0 & 1 != 0 }
+
+    /// Whether the title can read DVD video discs.
+    pub fn dvd_video_access(&self) -> bool { self.0 & 2 != 0 }
+}
+
+impl fmt::Display for AccessRights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rights = Vec::new();
+        if self.ahb_access() {
+            rights.push("AHB");
+        }
+        if self.dvd_video_access() {
+            rights.push("DVD video");
+        }
+        if rights.is_empty() {
+            write!(f, "None ({:#X})", self.0)
+        } else {
+            write!(f, "{} ({:#X})", rights.join(", "), self.0)
+        }
+    }
+}
+
+/// A view into a TMD, including the content records.
+///
+/// # Examples
+///
+/// ```no_run
+/// use nod::{Disc, PartitionKind};
+///
+/// fn main() -> nod::Result<()> {
+///     let disc = Disc::new("path/to/file.iso")?;
+///     let mut partition = disc.open_partition_kind(PartitionKind::Data)?;
+///     let meta = partition.meta()?;
+///     if let Some(tmd) = meta.tmd()? {
+///         println!("Requires IOS{}", tmd.ios_version().unwrap_or_default());
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tmd<'a> {
+    /// The TMD header.
+    pub header: &'a TmdHeader,
+    /// The content records.
+    pub contents: &'a [ContentRecord],
+}
+
+impl<'a> Tmd<'a> {
+    /// Creates a new TMD view, checking that all content records are present.
+    ///
+    /// The header and content records are unaligned, so any buffer can be viewed.
+    pub fn new(data: &'a [u8]) -> Result<Self, String> {
+        let Some(header) = TmdHeader::ref_from_prefix(data) else {
+            return Err(format!("TMD: invalid header ({:#X} bytes)", data.len()));
+        };
+        let num_contents = header.num_contents.get() as usize;
+        let contents =
+            ContentRecord::slice_from_prefix(&data[size_of::<TmdHeader>()..], num_contents)
+                .map(|(contents, _)| contents)
+                .ok_or_else(|| {
+                    format!(
+                        "TMD: {} content records exceed TMD size {:#X}",
+                        num_contents,
+                        data.len()
+                    )
+                })?;
+        Ok(Self { header, contents })
+    }
+
+    /// The size of the TMD in bytes, including the content records.
+    pub fn size(&self) -> usize { size_of::<TmdHeader>() + size_of_val(self.contents) }
+
+    /// Title ID.
+    pub fn title_id(&self) -> u64 { u64::from_be_bytes(self.header.title_id) }
+
+    /// Title ID of the required IOS.
+    pub fn ios_title_id(&self) -> u64 { u64::from_be_bytes(self.header.ios_id) }
+
+    /// The required IOS version (e.g. 36 for IOS36), if the IOS title ID is a system title.
+    pub fn ios_version(&self) -> Option<u32> {
+        let ios = self.ios_title_id();
+        ((ios >> 32) == 1 && (ios as u32) > 2 && (ios as u32) < 256).then_some(ios as u32)
+    }
+
+    /// Title type.
+    pub fn title_type(&self) -> u32 { self.header.title_type.get() }
+
+    /// Title version.
+    pub fn title_version(&self) -> u16 { self.header.title_version.get() }
+
+    /// Region.
+    pub fn region(&self) -> TmdRegion { self.header.region.get().into() }
+
+    /// Access rights.
+    pub fn access_rights(&self) -> AccessRights { AccessRights(self.header.access_flags.get()) }
+
+    /// The content record hashing the H3 table of a disc partition: the content with index 0.
+    ///
+    /// Disc partition TMDs contain a single content record, hashing the H3 table which in turn
+    /// covers all partition data.
+    pub fn h3_content(&self) -> Option<&'a ContentRecord> {
+        self.contents.iter().find(|c| c.index.get() == 0)
+    }
+
+    /// The content record of the boot content.
+    pub fn boot_content(&self) -> Option<&'a ContentRecord> {
+        let boot_idx = self.header.boot_idx.get();
+        self.contents.iter().find(|c| c.index.get() == boot_idx)
+    }
+}
+
+impl PartitionMeta {
+    /// A view into the TMD, including the content records. (Wii only)
+    pub fn tmd(&self) -> Result<Option<Tmd<'_>>, String> {
+        self.raw_tmd.as_deref().map(Tmd::new).transpose()
+    }
+
+    /// Checks the TMD content hash against the partition's H3 hash table. (Wii only)
+    ///
+    /// See [`Tmd::h3_content`]. Returns `None` for GameCube partitions.
+    pub fn verify_tmd_content(&self) -> Result<Option<bool>, String> {
+        let (Some(tmd), Some(h3_table)) = (self.tmd()?, self.raw_h3_table.as_deref()) else {
+            return Ok(None);
+        };
+        let content = tmd.h3_content().ok_or("TMD: no content record with index 0")?;
+        Ok(Some(content.verify(h3_table)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tmd() {
+        let mut header = TmdHeader::new_zeroed();
+        header.ios_id = 0x0000000100000024u64.to_be_bytes();
+        header.title_id = 0x0001000052534245u64.to_be_bytes();
+        header.region = U16::new(1);
+        header.access_flags = U32::new(3);
+        header.num_contents = U16::new(1);
+        let h3_table = [0xAAu8; 0x100];
+        let content = ContentRecord {
+            content_id: U32::new(0),
+            index: U16::new(0),
+            content_type: U16::new(ContentRecord::CONTENT_TYPE_NORMAL),
+            size: U64::new(0x100),
+            hash: Sha1::digest(h3_table).into(),
+        };
+        let mut data = header.as_bytes().to_vec();
+        data.extend_from_slice(content.as_bytes());
+
+        let tmd = Tmd::new(&data).unwrap();
+        assert_eq!(tmd.ios_version(), Some(36));
+        assert_eq!(tmd.title_id(), 0x0001000052534245);
+        assert_eq!(tmd.region(), TmdRegion::Usa);
+        assert!(tmd.access_rights().ahb_access() && tmd.access_rights().dvd_video_access());
+        assert_eq!(tmd.size(), data.len());
+        assert!(tmd.contents[0].verify(&h3_table));
+        assert!(!tmd.contents[0].verify(&h3_table[1..]));
+        assert!(Tmd::new(&data[..data.len() - 1]).is_err());
+        assert_eq!(tmd.h3_content(), Some(&tmd.contents[0]));
+
+        // Buffers at any alignment can be viewed
+        let mut unaligned = vec![0u8];
+        unaligned.extend_from_slice(&data);
+        let tmd = Tmd::new(&unaligned[1..]).unwrap();
+        assert_eq!(tmd.title_id(), 0x0001000052534245);
+        assert!(tmd.contents[0].verify(&h3_table));
+    }
+}
@@ -0,0 +1,339 @@
+//! TPL texture types
+
+use std::{fmt, mem::size_of};
+
+use zerocopy::{big_endian::*, FromBytes, FromZeroes};
+
+use crate::{
+    static_assert,
+    util::image::{
+        decode_ci14x2, decode_ci4, decode_ci8, decode_cmpr, decode_i4, decode_i8, decode_ia4,
+        decode_ia8, decode_rgb565, decode_rgb5a3, decode_rgba8, ia8_to_rgba8, rgb565_to_rgba8,
+        rgb5a3_to_rgba8,
+    },
+};
+
+/// TPL magic.
+pub const TPL_MAGIC: [u8; 4] = [0x00, 0x20, 0xAF, 0x30];
+
+/// Texture pixel format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TplFormat {
+    /// 4-bit intensity
+    I4,
+    /// 8-bit intensity
+    I8,
+    /// 4-bit intensity, 4-bit alpha
+    IA4,
+    /// 8-bit intensity, 8-bit alpha
+    IA8,
+    /// 16-bit RGB
+    RGB565,
+    /// 16-bit RGB555 or ARGB3444
+    RGB5A3,
+    /// 32-bit RGBA
+    RGBA8,
+    /// 4-bit palette index
+    CI4,
+    /// 8-bit palette index
+    CI8,
+    /// 14-bit palette index
+    CI14X2,
+    /// S3TC (DXT1) compressed
+    CMPR,
+}
+
+impl TplFormat {
+    /// Parses the format ID.
+    pub fn from_u32(value: u32) -> Option<Self> {
+        Some(match value {
+            0 => Self::I4,
+            1 => Self::I8,
+            2 => Self::IA4,
+            3 => Self::IA8,
+            4 => Self::RGB565,
+            5 => Self::RGB5A3,
+            6 => Self::RGBA8,
+            8 => Self::CI4,
+            9 => Self::CI8,
+            10 => Self::CI14X2,
+            14 => Self::CMPR,
+            _ => return None,
+        })
+    }
+
+    /// Whether the format uses a palette.
+    pub fn is_indexed(self) -> bool { matches!(self, Self::CI4 | Self::CI8 | Self::CI14X2) }
+
+    /// The tile width and height in pixels, and the number of bits per pixel.
+    fn block_size(self) -> (usize, usize, usize) {
+        match self {
+            Self::I4 | Self::CI4 | Self::CMPR => (8, 8, 4),
+            Self::I8 | Self::IA4 | Self::CI8 => (8, 4, 8),
+            Self::IA8 | Self::RGB565 | Self::RGB5A3 | Self::CI14X2 => (4, 4, 16),
+            Self::RGBA8 => (4, 4, 32),
+        }
+    }
+
+    /// The size of an image in bytes, with the dimensions rounded up to whole tiles.
+    pub fn data_size(self, width: usize, height: usize) -> usize {
+        let (tile_width, tile_height, bits) = self.block_size();
+        width.next_multiple_of(tile_width) * height.next_multiple_of(tile_height) * bits / 8
+    }
+}
+
+impl fmt::Display for TplFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::I4 => write!(f, "I4"),
+            Self::I8 => write!(f, "I8"),
+            Self::IA4 => write!(f, "IA4"),
+            Self::IA8 => write!(f, "IA8"),
+            Self::RGB565 => write!(f, "RGB565"),
+            Self::RGB5A3 => write!(f, "RGB5A3"),
+            Self::RGBA8 => write!(f, "RGBA8"),
+            Self::CI4 => write!(f, "CI4"),
+            Self::CI8 => write!(f, "CI8"),
+            Self::CI14X2 => write!(f, "CI14X2"),
+            Self::CMPR => write!(f, "CMPR"),
+        }
+    }
+}
+
+/// Palette entry format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TplPaletteFormat {
+    /// 8-bit intensity, 8-bit alpha
+    IA8,
+    /// 16-bit RGB
+    RGB565,
+    /// 16-bit RGB555 or ARGB3444
+    RGB5A3,
+}
+
+impl TplPaletteFormat {
+    /// Parses the palette format ID.
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Self::IA8),
+            1 => Some(Self::RGB565),
+            2 => Some(Self::RGB5A3),
+            _ => None,
+        }
+    }
+}
+
+#[derive(FromBytes, FromZeroes)]
+#[repr(C, align(4))]
+struct RawTplHeader {
+    magic: [u8; 4],
+    image_count: U32,
+    image_table_offset: U32,
+}
+
+static_assert!(size_of::<RawTplHeader>() == 0xC);
+
+#[derive(FromBytes, FromZeroes)]
+#[repr(C, align(4))]
+struct RawTplImageEntry {
+    image_header_offset: U32,
+    palette_header_offset: U32,
+}
+
+static_assert!(size_of::<RawTplImageEntry>() == 0x8);
+
+#[derive(FromBytes, FromZeroes)]
+#[repr(C, align(4))]
+struct RawTplImageHeader {
+    height: U16,
+    width: U16,
+    format: U32,
+    data_offset: U32,
+    wrap_s: U32,
+    wrap_t: U32,
+    min_filter: U32,
+    mag_filter: U32,
+    lod_bias: U32,
+    edge_lod_enable: u8,
+    min_lod: u8,
+    max_lod: u8,
+    unpacked: u8,
+}
+
+static_assert!(size_of::<RawTplImageHeader>() == 0x24);
+
+#[derive(FromBytes, FromZeroes)]
+#[repr(C, align(4))]
+struct RawTplPaletteHeader {
+    entry_count: U16,
+    unpacked: u8,
+    _pad: u8,
+    format: U32,
+    data_offset: U32,
+}
+
+static_assert!(size_of::<RawTplPaletteHeader>() == 0xC);
+
+/// A texture palette.
+#[derive(Debug, Clone)]
+pub struct TplPalette {
+    /// The entry format.
+    pub format: TplPaletteFormat,
+    /// Raw palette data, 16 bits per entry.
+    pub data: Box<[u8]>,
+}
+
+impl TplPalette {
+    /// Decodes the palette entries to RGBA8.
+    pub fn to_rgba8(&self) -> Vec<[u8; 4]> {
+        self.data
+            .chunks_exact(2)
+            .map(|c| {
+                let v = u16::from_be_bytes([c[0], c[1]]);
+                match self.format {
+                    TplPaletteFormat::IA8 => ia8_to_rgba8(v),
+                    TplPaletteFormat::RGB565 => rgb565_to_rgba8(v),
+                    TplPaletteFormat::RGB5A3 => rgb5a3_to_rgba8(v),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A texture in a TPL file.
+#[derive(Debug, Clone)]
+pub struct TplImage {
+    /// Width in pixels
+    pub width: u16,
+    /// Height in pixels
+    pub height: u16,
+    /// Pixel format
+    pub format: TplFormat,
+    /// Raw tiled image data for the base level. (See [`TplFormat::data_size`])
+    pub data: Box<[u8]>,
+    /// The palette, for indexed formats.
+    pub palette: Option<TplPalette>,
+}
+
+impl TplImage {
+    /// Decodes the image to RGBA8, [`TplImage::width`] x [`TplImage::height`] pixels.
+    ///
+    /// Indexed images without a palette decode as transparent.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        let (data, width, height) = (&self.data[..], self.width as usize, self.height as usize);
+        let palette = || self.palette.as_ref().map(TplPalette::to_rgba8).unwrap_or_default();
+        match self.format {
+            TplFormat::I4 => decode_i4(data, width, height),
+            TplFormat::I8 => decode_i8(data, width, height),
+            TplFormat::IA4 => decode_ia4(data, width, height),
+            TplFormat::IA8 => decode_ia8(data, width, height),
+            TplFormat::RGB565 => decode_rgb565(data, width, height),
+            TplFormat::RGB5A3 => decode_rgb5a3(data, width, height),
+            TplFormat::RGBA8 => decode_rgba8(data, width, height),
+            TplFormat::CI4 => decode_ci4(data, width, height, &palette()),
+            TplFormat::CI8 => decode_ci8(data, width, height, &palette()),
+            TplFormat::CI14X2 => decode_ci14x2(data, width, height, &palette()),
+            TplFormat::CMPR => decode_cmpr(data, width, height),
+        }
+    }
+}
+
+/// A parsed TPL texture file.
+///
+/// Only the base level of mipmapped textures is read.
+#[derive(Debug, Clone)]
+pub struct Tpl {
+    /// The textures in the file.
+    pub images: Vec<TplImage>,
+}
+
+impl Tpl {
+    /// Whether the data starts with the TPL magic.
+    pub fn is_tpl(data: &[u8]) -> bool { data.starts_with(&TPL_MAGIC) }
+
+    /// Parses a TPL file.
+    pub fn new(data: &[u8]) -> Result<Self, String> {
+        let header = RawTplHeader::read_from_prefix(data).ok_or("TPL: header out of bounds")?;
+        if header.magic != TPL_MAGIC {
+            return Err(format!("TPL: invalid magic {:02X?}", header.magic));
+        }
+        let entries = data
+            .get(header.image_table_offset.get() as usize..)
+            .and_then(|d| RawTplImageEntry::slice_from_prefix(d, header.image_count.get() as usize))
+            .map(|(entries, _)| entries)
+            .ok_or("TPL: image table out of bounds")?;
+        let images = entries
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                read_image(data, entry).map_err(|e| format!("TPL: image {}: {}", idx, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { images })
+    }
+}
+
+fn read_image(data: &[u8], entry: &RawTplImageEntry) -> Result<TplImage, String> {
+    let header = data
+        .get(entry.image_header_offset.get() as usize..)
+        .and_then(RawTplImageHeader::read_from_prefix)
+        .ok_or("header out of bounds")?;
+    let format = TplFormat::from_u32(header.format.get())
+        .ok_or_else(|| format!("unknown format {}", header.format.get()))?;
+    let (width, height) = (header.width.get(), header.height.get());
+    let start = header.data_offset.get() as usize;
+    let size = format.data_size(width as usize, height as usize);
+    let image_data = data.get(start..start + size).ok_or("data out of bounds")?;
+    let palette = match entry.palette_header_offset.get() {
+        0 => None,
+        offset => Some(read_palette(data, offset as usize)?),
+    };
+    Ok(TplImage { width, height, format, data: image_data.into(), palette })
+}
+
+fn read_palette(data: &[u8], offset: usize) -> Result<TplPalette, String> {
+    let header = data
+        .get(offset..)
+        .and_then(RawTplPaletteHeader::read_from_prefix)
+        .ok_or("palette header out of bounds")?;
+    let format = TplPaletteFormat::from_u32(header.format.get())
+        .ok_or_else(|| format!("unknown palette format {}", header.format.get()))?;
+    let start = header.data_offset.get() as usize;
+    let size = header.entry_count.get() as usize * 2;
+    let palette_data = data.get(start..start + size).ok_or("palette data out of bounds")?;
+    Ok(TplPalette { format, data: palette_data.into() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tpl() {
+        // One 4x4 CI8 image with a two-entry RGB5A3 palette
+        let mut data = TPL_MAGIC.to_vec();
+        data.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0xC]);
+        data.extend_from_slice(&[0, 0, 0, 0x14, 0, 0, 0, 0x38]);
+        let mut image = [0u8; 0x24];
+        image[..4].copy_from_slice(&[0, 4, 0, 4]);
+        image[4..8].copy_from_slice(&9u32.to_be_bytes());
+        image[8..12].copy_from_slice(&0x60u32.to_be_bytes());
+        data.extend_from_slice(&image);
+        data.extend_from_slice(&[0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0x44]);
+        data.extend_from_slice(&[0x80, 0x00, 0xFC, 0x00]);
+        data.resize(0x60, 0);
+        data.extend_from_slice(&[1; 32]);
+
+        let tpl = Tpl::new(&data).unwrap();
+        assert_eq!(tpl.images.len(), 1);
+        let image = &tpl.images[0];
+        assert_eq!((image.width, image.height, image.format), (4, 4, TplFormat::CI8));
+        assert_eq!(image.data.len(), TplFormat::CI8.data_size(4, 4));
+        let rgba = image.to_rgba8();
+        assert_eq!(rgba.len(), 4 * 4 * 4);
+        assert_eq!(rgba[..4], [0xFF, 0x00, 0x00, 0xFF]);
+
+        data[0x18] = 7;
+        assert!(Tpl::new(&data).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+//! U8 archive types
+
+use std::{
+    io,
+    io::{Read, SeekFrom},
+    mem::size_of,
+};
+
+use zerocopy::{big_endian::*, AsBytes, FromBytes, FromZeroes};
+
+use crate::{
+    static_assert,
+    util::read::{read_box_slice, read_from},
+    Error, Fst, FstIter, Node, ReadStream, Result, ResultContext, SharedWindowedReadStream,
+};
+
+/// U8 archive magic.
+pub const U8_MAGIC: [u8; 4] = [0x55, 0xAA, 0x38, 0x2D];
+
+/// U8 archive header.
+#[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[repr(C, align(4))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct U8Header {
+    /// Magic bytes ([`U8_MAGIC`])
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
+    pub magic: [u8; 4],
+    /// Offset of the root node
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub root_node_offset: U32,
+    /// Size of the node table and string table
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub header_size: U32,
+    /// Offset of the file data
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub data_offset: U32,
+    /// Padding
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
+    _pad: [u8; 16],
+}
+
+static_assert!(size_of::<U8Header>() == 0x20);
+
+/// A U8 archive, read from any [`ReadStream`].
+///
+/// The node table has the same layout as the disc's file system table, so it's exposed as an
+/// [`Fst`]. File offsets are relative to the start of the archive and never shifted,
+/// so use `node.offset(false)`.
+///
+/// # Examples
+///
+/// Listing the contents of an archive inside a disc partition:
+///
+/// ```no_run
+/// use nod::{Disc, PartitionKind, U8Archive};
+///
+/// fn main() -> nod::Result<()> {
+///     let disc = Disc::new("path/to/file.iso")?;
+///     let mut partition = disc.open_partition_kind(PartitionKind::Data)?;
+///     let meta = partition.meta()?;
+///     let fst = meta.fst()?;
+///     let (_, node) = fst.find("/files/Common.arc").expect("Archive not found");
+///     let mut stream = partition.open_file(node).expect("Failed to open archive");
+///     let archive = U8Archive::new(&mut stream)?;
+///     for (_, node, name) in archive.iter() {
+///         println!("{}: {} bytes", name?, node.length());
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct U8Archive {
+    /// The archive header.
+    pub header: U8Header,
+    raw_fst: Box<[u8]>,
+}
+
+impl U8Archive {
+    /// Whether the data starts with the U8 magic.
+    pub fn is_u8(data: &[u8]) -> bool { data.starts_with(&U8_MAGIC) }
+
+    /// Reads the header and node table from a stream positioned anywhere; offsets are
+    /// relative to the start of the stream.
+    pub fn new<R>(reader: &mut R) -> Result<Self>
+    where R: ReadStream + ?Sized {
+        reader.seek(SeekFrom::Start(0)).context("Seeking to U8 header")?;
+        let header: U8Header = read_from(reader).context("Reading U8 header")?;
+        if header.magic != U8_MAGIC {
+            return Err(Error::DiscFormat(format!("Invalid U8 magic: {:02X?}", header.magic)));
+        }
+        reader
+            .seek(SeekFrom::Start(header.root_node_offset.get() as u64))
+            .context("Seeking to U8 node table")?;
+        let raw_fst: Box<[u8]> = read_box_slice(reader, header.header_size.get() as usize)
+            .context("Reading U8 node table")?;
+        Fst::new(&raw_fst).map_err(|e| Error::DiscFormat(format!("U8: {}", e)))?;
+        Ok(Self { header, raw_fst })
+    }
+
+    /// A view into the node table.
+    pub fn fst(&self) -> Fst<'_> { Fst::new(&self.raw_fst).unwrap() }
+
+    /// Iterates over all nodes except the root, in order.
+    pub fn iter(&self) -> FstIter<'_> { self.fst().iter() }
+
+    /// Finds a file or directory by path. (See [`Fst::find`])
+    pub fn find(&self, path: &str) -> Option<(usize, &Node)> { self.fst().find(path) }
+
+    /// Opens a file in the archive as a windowed stream over `reader`.
+    pub fn open_file<'a, R>(
+        &self,
+        reader: &'a mut R,
+        node: &Node,
+    ) -> io::Result<SharedWindowedReadStream<'a>>
+    where
+        R: ReadStream + ?Sized,
+    {
+        if !node.is_file() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Not a file"));
+        }
+        reader.new_window(node.offset(false), node.length())
+    }
+
+    /// Reads a file in the archive into memory.
+    pub fn read_file<R>(&self, reader: &mut R, node: &Node) -> io::Result<Vec<u8>>
+    where R: ReadStream + ?Sized {
+        let mut data = Vec::with_capacity(node.length() as usize);
+        self.open_file(reader, node)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_nested() {
+        // inner.arc: /b.bin ("hi")
+        let inner = u8_archive(&[(1, b"", 0, 2), (0, b"b.bin", 0x40, 2)], b"hi");
+        // outer.arc: /dir/inner.arc
+        let outer =
+            u8_archive(&[(1, b"", 0, 3), (1, b"dir", 0, 3), (0, b"inner.arc", 0x60, 0)], &inner);
+
+        let mut reader = Cursor::new(outer);
+        let archive = U8Archive::new(&mut reader).unwrap();
+        assert_eq!(archive.iter().count(), 2);
+        let (_, node) = archive.find("/dir/inner.arc").unwrap();
+        let mut stream = archive.open_file(&mut reader, node).unwrap();
+        let inner = U8Archive::new(&mut stream).unwrap();
+        let (_, node) = inner.find("b.bin").unwrap();
+        assert_eq!(inner.read_file(&mut stream, node).unwrap(), b"hi");
+        assert!(U8Archive::new(&mut Cursor::new(vec![0u8; 0x40])).is_err());
+    }
+
+    /// Builds an archive with the data of the last file at `data_offset`.
+    fn u8_archive(nodes: &[(u8, &[u8], u32, u32)], data: &[u8]) -> Vec<u8> {
+        let mut strings = Vec::new();
+        let mut table = Vec::new();
+        let data_offset = nodes.iter().find(|n| n.0 == 0).unwrap().2;
+        for &(kind, name, offset, length) in nodes {
+            let name_offset = strings.len() as u32;
+            strings.extend_from_slice(name);
+            strings.push(0);
+            let length = if kind == 0 { data.len() as u32 } else { length };
+            table.push(kind as u32);
+            table.push(name_offset);
+            table.push(offset);
+            table.push(length);
+        }
+        let mut out = U8_MAGIC.to_vec();
+        let node_bytes: Vec<u8> = table
+            .chunks(4)
+            .flat_map(|n| {
+                let mut b = ((n[0] << 24) | n[1]).to_be_bytes().to_vec();
+                b.extend_from_slice(&n[2].to_be_bytes());
+                b.extend_from_slice(&n[3].to_be_bytes());
+                b
+            })
+            .collect();
+        let header_size = (node_bytes.len() + strings.len()) as u32;
+        out.extend_from_slice(&0x20u32.to_be_bytes());
+        out.extend_from_slice(&header_size.to_be_bytes());
+        out.extend_from_slice(&data_offset.to_be_bytes());
+        out.resize(0x20, 0);
+        out.extend_from_slice(&node_bytes);
+        out.extend_from_slice(&strings);
+        out.resize(data_offset as usize, 0);
+        out.extend_from_slice(data);
+        out
+    }
+}
@@ -90,3 +90,55 @@ where
     let stream = liblzma::stream::Stream::new_raw_decoder(&filters).map_err(io::Error::from)?;
     Ok(liblzma::read::XzDecoder::new_stream(reader, stream))
 }
+
+/// Decompresses Nintendo LZ77 data (LZ10 or LZ11), with or without the `LZ77` magic.
+///
+/// The header is a type byte (`0x10` or `0x11`) followed by the 24-bit little-endian
+/// decompressed size.
+pub fn decompress_lz77(data: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("LZ77: {}", msg));
+    let data = data.strip_prefix(b"LZ77").unwrap_or(data);
+    let Some(&[kind, a, b, c]) = data.get(..4) else {
+        return Err(invalid("header out of bounds"));
+    };
+    if kind != 0x10 && kind != 0x11 {
+        return Err(invalid(&format!("unsupported type {:#04X}", kind)));
+    }
+    let size = u32::from_le_bytes([a, b, c, 0]) as usize;
+    let mut out = Vec::with_capacity(size);
+    let mut input = data[4..].iter().copied();
+    let mut next = || input.next().ok_or_else(|| invalid("unexpected end of data"));
+    while out.len() < size {
+        let flags = next()?;
+        for bit in (0..8).rev() {
+            if out.len() >= size {
+                break;
+            }
+            if flags & (1 << bit) == 0 {
+                out.push(next()?);
+                continue;
+            }
+            let b0 = next()? as usize;
+            let (length, b1) = match (kind, b0 >> 4) {
+                (0x11, 0) => {
+                    let b1 = next()? as usize;
+                    (((b0 & 0xF) << 4 | b1 >> 4) + 0x11, b1)
+                }
+                (0x11, 1) => {
+                    let (b1, b2) = (next()? as usize, next()? as usize);
+                    (((b0 & 0xF) << 12 | b1 << 4 | b2 >> 4) + 0x111, b2)
+                }
+                (0x11, n) => (n + 1, b0),
+                (_, n) => (n + 3, b0),
+            };
+            let distance = ((b1 & 0xF) << 8 | next()? as usize) + 1;
+            if distance > out.len() {
+                return Err(invalid("back-reference out of bounds"));
+            }
+            for _ in 0..length.min(size - out.len()) {
+                out.push(out[out.len() - distance]);
+            }
+        }
+    }
+    Ok(out)
+}
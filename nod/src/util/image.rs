@@ -0,0 +1,250 @@
+//! GameCube / Wii texture decoding.
+
+/// Decodes a tiled RGB5A3 texture to RGBA8.
+///
+/// RGB5A3 is stored in 4x4 pixel tiles of big-endian 16-bit pixels. If the top bit is set,
+/// the pixel is opaque RGB555; otherwise it's ARGB3444.
+pub(crate) fn decode_rgb5a3(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    decode_tiled(data, width, height, 4, 4, 2, |src, dst| {
+        dst.copy_from_slice(&rgb5a3_to_rgba8(u16::from_be_bytes([src[0], src[1]])))
+    })
+}
+
+/// Converts a single RGB5A3 pixel to RGBA8.
+pub(crate) fn rgb5a3_to_rgba8(v: u16) -> [u8; 4] {
+    if v & 0x8000 != 0 {
+        [expand5((v >> 10) as u8), expand5((v >> 5) as u8), expand5(v as u8), 0xFF]
+    } else {
+        [
+            expand4((v >> 8) as u8),
+            expand4((v >> 4) as u8),
+            expand4(v as u8),
+            expand3((v >> 12) as u8),
+        ]
+    }
+}
+
+/// Decodes a tiled I4 texture to RGBA8. Alpha is set to the intensity.
+pub(crate) fn decode_i4(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    decode_tiled(&split_nibbles(data), width, height, 8, 8, 1, |src, dst| dst.fill(expand4(src[0])))
+}
+
+/// Decodes a tiled I8 texture to RGBA8. Alpha is set to the intensity.
+pub(crate) fn decode_i8(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    decode_tiled(data, width, height, 8, 4, 1, |src, dst| dst.fill(src[0]))
+}
+
+/// Decodes a tiled IA4 texture (alpha in the high nibble) to RGBA8.
+pub(crate) fn decode_ia4(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    decode_tiled(data, width, height, 8, 4, 1, |src, dst| {
+        let i = expand4(src[0]);
+        dst.copy_from_slice(&[i, i, i, expand4(src[0] >> 4)])
+    })
+}
+
+/// Decodes a tiled IA8 texture (alpha in the first byte) to RGBA8.
+pub(crate) fn decode_ia8(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    decode_tiled(data, width, height, 4, 4, 2, |src, dst| {
+        dst.copy_from_slice(&ia8_to_rgba8(u16::from_be_bytes([src[0], src[1]])))
+    })
+}
+
+/// Decodes a tiled RGB565 texture to RGBA8.
+pub(crate) fn decode_rgb565(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    decode_tiled(data, width, height, 4, 4, 2, |src, dst| {
+        dst.copy_from_slice(&rgb565_to_rgba8(u16::from_be_bytes([src[0], src[1]])))
+    })
+}
+
+/// Decodes a tiled RGBA8 texture.
+///
+/// Each 4x4 tile is stored as 16 AR pairs followed by 16 GB pairs.
+pub(crate) fn decode_rgba8(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let pixels = data
+        .chunks_exact(64)
+        .flat_map(|tile| {
+            (0..16).flat_map(move |i| {
+                [tile[i * 2 + 1], tile[32 + i * 2], tile[32 + i * 2 + 1], tile[i * 2]]
+            })
+        })
+        .collect::<Vec<u8>>();
+    decode_tiled(&pixels, width, height, 4, 4, 4, |src, dst| dst.copy_from_slice(src))
+}
+
+/// Decodes a tiled CI4 texture to RGBA8 using a decoded palette.
+pub(crate) fn decode_ci4(data: &[u8], width: usize, height: usize, palette: &[[u8; 4]]) -> Vec<u8> {
+    decode_tiled(&split_nibbles(data), width, height, 8, 8, 1, |src, dst| {
+        dst.copy_from_slice(&palette.get(src[0] as usize).copied().unwrap_or_default())
+    })
+}
+
+/// Decodes a tiled CI8 texture to RGBA8 using a decoded palette.
+pub(crate) fn decode_ci8(data: &[u8], width: usize, height: usize, palette: &[[u8; 4]]) -> Vec<u8> {
+    decode_tiled(data, width, height, 8, 4, 1, |src, dst| {
+        dst.copy_from_slice(&palette.get(src[0] as usize).copied().unwrap_or_default())
+    })
+}
+
+/// Decodes a tiled CI14X2 texture to RGBA8 using a decoded palette.
+pub(crate) fn decode_ci14x2(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    palette: &[[u8; 4]],
+) -> Vec<u8> {
+    decode_tiled(data, width, height, 4, 4, 2, |src, dst| {
+        let index = u16::from_be_bytes([src[0], src[1]]) & 0x3FFF;
+        dst.copy_from_slice(&palette.get(index as usize).copied().unwrap_or_default())
+    })
+}
+
+/// Decodes a CMPR (S3TC / DXT1) texture to RGBA8.
+///
+/// Each 8x8 tile contains four 4x4 DXT1 blocks in Z order, with big-endian colors and
+/// the most significant bits selecting the first pixel.
+pub(crate) fn decode_cmpr(data: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut out = vec![0u8; width * height * 4];
+    let mut blocks = data.chunks_exact(8);
+    for tile_y in (0..height).step_by(8) {
+        for tile_x in (0..width).step_by(8) {
+            for (block_x, block_y) in [(0, 0), (4, 0), (0, 4), (4, 4)] {
+                let Some(block) = blocks.next() else {
+                    return out;
+                };
+                let colors = dxt1_palette(block);
+                for y in 0..4 {
+                    let row = block[4 + y];
+                    for x in 0..4 {
+                        let (px, py) = (tile_x + block_x + x, tile_y + block_y + y);
+                        if px < width && py < height {
+                            let color = colors[(row >> (6 - x * 2)) as usize & 3];
+                            let offset = (py * width + px) * 4;
+                            out[offset..offset + 4].copy_from_slice(&color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Builds the four colors of a DXT1 block.
+fn dxt1_palette(block: &[u8]) -> [[u8; 4]; 4] {
+    let c0 = u16::from_be_bytes([block[0], block[1]]);
+    let c1 = u16::from_be_bytes([block[2], block[3]]);
+    let (a, b) = (rgb565_to_rgba8(c0), rgb565_to_rgba8(c1));
+    let mix = |wa: u16, wb: u16, div: u16| {
+        let mut out = [0xFF; 4];
+        for i in 0..3 {
+            out[i] = ((a[i] as u16 * wa + b[i] as u16 * wb) / div) as u8;
+        }
+        out
+    };
+    if c0 > c1 {
+        [a, b, mix(2, 1, 3), mix(1, 2, 3)]
+    } else {
+        [a, b, mix(1, 1, 2), [0; 4]]
+    }
+}
+
+/// Converts a single IA8 pixel (alpha in the high byte) to RGBA8.
+pub(crate) fn ia8_to_rgba8(v: u16) -> [u8; 4] {
+    let i = v as u8;
+    [i, i, i, (v >> 8) as u8]
+}
+
+/// Converts a single RGB565 pixel to RGBA8.
+pub(crate) fn rgb565_to_rgba8(v: u16) -> [u8; 4] {
+    [expand5((v >> 11) as u8), expand6((v >> 5) as u8), expand5(v as u8), 0xFF]
+}
+
+/// Splits 4-bit pixels into one byte per pixel, high nibble first.
+fn split_nibbles(data: &[u8]) -> Vec<u8> { data.iter().flat_map(|&b| [b >> 4, b & 0xF]).collect() }
+
+/// Decodes a tiled texture, calling `pixel` with the source bytes and destination RGBA8 pixel.
+///
+/// Pixels outside `width` x `height` (when the size isn't a multiple of the tile size) are
+/// skipped. Missing source data leaves the remaining pixels transparent.
+pub(crate) fn decode_tiled(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    tile_width: usize,
+    tile_height: usize,
+    bytes_per_pixel: usize,
+    mut pixel: impl FnMut(&[u8], &mut [u8]),
+) -> Vec<u8> {
+    let mut out = vec![0u8; width * height * 4];
+    let mut pixels = data.chunks_exact(bytes_per_pixel);
+    for tile_y in (0..height).step_by(tile_height) {
+        for tile_x in (0..width).step_by(tile_width) {
+            for y in tile_y..tile_y + tile_height {
+                for x in tile_x..tile_x + tile_width {
+                    let Some(src) = pixels.next() else {
+                        return out;
+                    };
+                    if x < width && y < height {
+                        let offset = (y * width + x) * 4;
+                        pixel(src, &mut out[offset..offset + 4]);
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+#[inline]
+fn expand6(v: u8) -> u8 {
+    let v = v & 0x3F;
+    (v << 2) | (v >> 4)
+}
+
+#[inline]
+fn expand3(v: u8) -> u8 {
+    let v = v & 0x7;
+    (v << 5) | (v << 2) | (v >> 1)
+}
+
+#[inline]
+fn expand4(v: u8) -> u8 { (v & 0xF) * 0x11 }
+
+#[inline]
+fn expand5(v: u8) -> u8 {
+    let v = v & 0x1F;
+    (v << 3) | (v >> 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgb5a3() {
+        assert_eq!(rgb5a3_to_rgba8(0xFFFF), [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(rgb5a3_to_rgba8(0xFC00), [0xFF, 0x00, 0x00, 0xFF]);
+        assert_eq!(rgb5a3_to_rgba8(0x7F00), [0xFF, 0x00, 0x00, 0xFF]);
+        assert_eq!(rgb5a3_to_rgba8(0x0000), [0x00, 0x00, 0x00, 0x00]);
+
+        // 8x4 image: two 4x4 tiles, the second one opaque white
+        let mut data = vec![0u8; 16 * 2];
+        data.extend_from_slice(&[0xFF; 16 * 2]);
+        let image = decode_rgb5a3(&data, 8, 4);
+        assert_eq!(image[..4], [0, 0, 0, 0]);
+        assert_eq!(image[4 * 4..4 * 5], [0xFF; 4]);
+    }
+
+    #[test]
+    fn test_cmpr() {
+        // 8x8 tile: red / blue endpoints, the first block all color 0, the rest all color 1
+        let mut data = Vec::new();
+        for indices in [0x00, 0x55, 0x55, 0x55] {
+            data.extend_from_slice(&[0xF8, 0x00, 0x00, 0x1F, indices, indices, indices, indices]);
+        }
+        let image = decode_cmpr(&data, 8, 8);
+        assert_eq!(image[..4], [0xFF, 0x00, 0x00, 0xFF]);
+        assert_eq!(image[4 * 4..4 * 5], [0x00, 0x00, 0xFF, 0xFF]);
+        assert_eq!(image[(4 * 8) * 4..(4 * 8 + 1) * 4], [0x00, 0x00, 0xFF, 0xFF]);
+    }
+}
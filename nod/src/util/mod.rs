@@ -1,8 +1,12 @@
 use std::ops::{Div, Rem};
 
 pub(crate) mod compress;
+pub(crate) mod image;
 pub(crate) mod lfg;
 pub(crate) mod read;
+pub(crate) mod rsa;
+#[cfg(feature = "serde")]
+pub(crate) mod serde;
 pub(crate) mod take_seek;
 
 #[inline(always)]
@@ -0,0 +1,76 @@
+//! RSA public key operations for signature verification.
+
+use rsa::{BigUint, Pkcs1v15Sign, RsaPublicKey};
+use sha1::Sha1;
+
+/// Verifies an RSA PKCS #1 v1.5 signature over a SHA-1 hash.
+pub(crate) fn verify_pkcs1_sha1(
+    modulus: &[u8],
+    exponent: u32,
+    signature: &[u8],
+    hash: &[u8; 20],
+) -> bool {
+    let Ok(key) = RsaPublicKey::new(BigUint::from_bytes_be(modulus), BigUint::from(exponent))
+    else {
+        return false;
+    };
+    key.verify(Pkcs1v15Sign::new::<Sha1>(), hash, signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use sha1::Digest;
+
+    use super::*;
+
+    // RSA-1024 test key
+    const MODULUS: [&str; 4] = [
+        "b1d8acbeb64706ee5fc40b601aa4e9fcf80c99cad6907119b4fb3455839707dc",
+        "dcb08dabfe331a6ccbce36d77f019aa4269fbd3741d7fcc3237af899d8299ee0",
+        "fe514b8cbe21703e091a033a6f51e0e4000a0bf4e652ccaf723004d23a2e6080",
+        "c0f13268f1a32419842dec4e43aafb323313010ac9c27c6400d12253802dc7a1",
+    ];
+    // Signature over SHA-1("nod")
+    const SIGNATURE: [&str; 4] = [
+        "89c9d0d574fb6e2285f01b2c78af348a309fa19b8d1b37499f81a3e1862f2bec",
+        "59b40dd696d7c83fe3edde0ba2d08eb2dcb1878f9fe16afb19589fbb910d88ad",
+        "861007f53d454ea7e820b1f2177a4d6c3fabf3c2245f4cec12e003b5cbb9991d",
+        "ece4c0d754e3a68a4b10db8107672646a5b1bcd60aff5e5023e092668ebd837b",
+    ];
+    // Same digest, but padded with block type 2 instead of 1
+    const BAD_PADDING: [&str; 4] = [
+        "45a4fd383b7f04a20f3237a711ade764eb582b00dec7075ad059d924b079c131",
+        "6a667922f1bed692d731aeb936828d2e941dd09c3835f056695ee3618fd5363b",
+        "a99a23f9e4c1ab54819dd23dc8e6dec85ede4b655920dcd0349983b48e832334",
+        "062bcee014172b1e30b5ad2539737ffcf381fb4a62a5669905f8df279db76550",
+    ];
+
+    fn decode(hex: &[&str]) -> [u8; 128] {
+        let mut out = [0u8; 128];
+        base16ct::lower::decode(hex.concat(), &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_verify_pkcs1_sha1() {
+        let modulus = decode(&MODULUS);
+        let signature = decode(&SIGNATURE);
+        let hash: [u8; 20] = Sha1::digest(b"nod").into();
+        assert!(verify_pkcs1_sha1(&modulus, 65537, &signature, &hash));
+
+        // Wrong hash
+        let other: [u8; 20] = Sha1::digest(b"don").into();
+        assert!(!verify_pkcs1_sha1(&modulus, 65537, &signature, &other));
+        // Wrong exponent
+        assert!(!verify_pkcs1_sha1(&modulus, 3, &signature, &hash));
+        // Wrong padding block type
+        assert!(!verify_pkcs1_sha1(&modulus, 65537, &decode(&BAD_PADDING), &hash));
+        // Signature not smaller than the modulus
+        assert!(!verify_pkcs1_sha1(&modulus, 65537, &modulus, &hash));
+        assert!(!verify_pkcs1_sha1(&modulus, 65537, &[0xFF; 128], &hash));
+        // Signature length differs from the modulus
+        assert!(!verify_pkcs1_sha1(&modulus, 65537, &signature[1..], &hash));
+        // Exponents the key can't use
+        assert!(!verify_pkcs1_sha1(&modulus, 1, &signature, &hash));
+    }
+}
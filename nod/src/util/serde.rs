@@ -0,0 +1,302 @@
+//! Serde helpers for on-disc structures.
+//!
+//! Used with `#[serde(with = "...")]` on fields that don't implement serde traits directly.
+
+use std::fmt;
+
+use ::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use zerocopy::{big_endian::*, FromZeroes};
+
+/// Default for skipped padding fields.
+pub(crate) fn zeroed<T: FromZeroes>() -> T { T::new_zeroed() }
+
+/// Big-endian integer types, serialized as their native value.
+pub(crate) trait BigEndian: Copy {
+    type Native: Serialize + for<'de> Deserialize<'de>;
+
+    fn to_native(self) -> Self::Native;
+
+    fn from_native(value: Self::Native) -> Self;
+}
+
+macro_rules! impl_big_endian {
+    ($($ty:ty => $native:ty),*) => {
+        $(impl BigEndian for $ty {
+            type Native = $native;
+
+            fn to_native(self) -> $native { self.get() }
+
+            fn from_native(value: $native) -> Self { Self::new(value) }
+        })*
+    };
+}
+
+impl_big_endian!(U16 => u16, U32 => u32, U64 => u64, I32 => i32);
+
+/// Big-endian integers. (`U16`, `U32`, `U64`, `I32`)
+pub(crate) mod be {
+    use super::*;
+
+    pub(crate) fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: BigEndian,
+        S: Serializer,
+    {
+        value.to_native().serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: BigEndian,
+        D: Deserializer<'de>,
+    {
+        T::Native::deserialize(deserializer).map(T::from_native)
+    }
+}
+
+/// Arrays of big-endian integers.
+pub(crate) mod be_array {
+    use super::*;
+
+    pub(crate) fn serialize<T, S, const N: usize>(
+        value: &[T; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        T: BigEndian,
+        S: Serializer,
+    {
+        serializer.collect_seq(value.iter().map(|v| v.to_native()))
+    }
+
+    pub(crate) fn deserialize<'de, T, D, const N: usize>(
+        deserializer: D,
+    ) -> Result<[T; N], D::Error>
+    where
+        T: BigEndian,
+        D: Deserializer<'de>,
+    {
+        let values = Vec::<T::Native>::deserialize(deserializer)?;
+        let len = values.len();
+        let values = values.into_iter().map(T::from_native).collect::<Vec<T>>();
+        values.try_into().map_err(|_| de::Error::invalid_length(len, &N.to_string().as_str()))
+    }
+}
+
+/// 24-bit big-endian integers stored as `[u8; 3]`.
+pub(crate) mod u24 {
+    use super::*;
+
+    pub(crate) fn serialize<S>(value: &[u8; 3], serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        u32::from_be_bytes([0, value[0], value[1], value[2]]).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<[u8; 3], D::Error>
+    where D: Deserializer<'de> {
+        let value = u32::deserialize(deserializer)?;
+        if value > 0xFFFFFF {
+            return Err(de::Error::custom(format!("{:#X} does not fit in 24 bits", value)));
+        }
+        let [_, a, b, c] = value.to_be_bytes();
+        Ok([a, b, c])
+    }
+}
+
+/// Byte arrays (keys, hashes, IDs), serialized as a lowercase hex string in human-readable
+/// formats and as bytes otherwise.
+pub(crate) mod hex {
+    use super::*;
+
+    pub(crate) fn serialize<S, const N: usize>(
+        value: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.collect_str(&format_args!("{:x}", base16ct::HexDisplay(value)))
+        } else {
+            serializer.serialize_bytes(value)
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error>
+    where D: Deserializer<'de> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(HexVisitor::<N>)
+        } else {
+            deserializer.deserialize_bytes(HexVisitor::<N>)
+        }
+    }
+
+    struct HexVisitor<const N: usize>;
+
+    impl<'de, const N: usize> de::Visitor<'de> for HexVisitor<N> {
+        type Value = [u8; N];
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a hex string or byte array of {} bytes", N)
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where E: de::Error {
+            let mut out = [0u8; N];
+            match base16ct::mixed::decode(v, &mut out) {
+                Ok(decoded) if decoded.len() == N => Ok(out),
+                _ => Err(E::invalid_value(de::Unexpected::Str(v), &self)),
+            }
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+        where E: de::Error {
+            v.try_into().map_err(|_| E::invalid_length(v.len(), &self))
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where A: de::SeqAccess<'de> {
+            let mut out = [0u8; N];
+            for (i, b) in out.iter_mut().enumerate() {
+                *b = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(i, &self))?;
+            }
+            Ok(out)
+        }
+    }
+}
+
+/// Optional byte arrays. See [`hex`].
+pub(crate) mod hex_option {
+    use super::*;
+
+    pub(crate) fn serialize<S, const N: usize>(
+        value: &Option<[u8; N]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        struct Wrapper<'a, const N: usize>(&'a [u8; N]);
+
+        impl<const N: usize> Serialize for Wrapper<'_, N> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where S: Serializer {
+                hex::serialize(self.0, serializer)
+            }
+        }
+
+        value.as_ref().map(Wrapper).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D, const N: usize>(
+        deserializer: D,
+    ) -> Result<Option<[u8; N]>, D::Error>
+    where D: Deserializer<'de> {
+        struct Wrapper<const N: usize>([u8; N]);
+
+        impl<'de, const N: usize> Deserialize<'de> for Wrapper<N> {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where D: Deserializer<'de> {
+                hex::deserialize(deserializer).map(Wrapper)
+            }
+        }
+
+        Ok(Option::<Wrapper<N>>::deserialize(deserializer)?.map(|w| w.0))
+    }
+}
+
+/// Fixed-size, NUL-padded strings (game IDs, titles, issuers), serialized as a string.
+///
+/// Bytes after the first NUL are dropped, and invalid UTF-8 is replaced.
+pub(crate) mod cstr {
+    use super::*;
+
+    pub(crate) fn serialize<S, const N: usize>(
+        value: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let len = value.iter().position(|&b| b == 0).unwrap_or(N);
+        serializer.serialize_str(&String::from_utf8_lossy(&value[..len]))
+    }
+
+    pub(crate) fn deserialize<'de, D, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error>
+    where D: Deserializer<'de> {
+        deserializer.deserialize_str(CStrVisitor::<N>)
+    }
+
+    struct CStrVisitor<const N: usize>;
+
+    impl<'de, const N: usize> de::Visitor<'de> for CStrVisitor<N> {
+        type Value = [u8; N];
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a string of at most {} bytes", N)
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where E: de::Error {
+            if v.len() > N {
+                return Err(E::invalid_length(v.len(), &self));
+            }
+            let mut out = [0u8; N];
+            out[..v.len()].copy_from_slice(v.as_bytes());
+            Ok(out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zerocopy::{AsBytes, FromZeroes};
+
+    use crate::{DiscHeader, DiscMeta, Ticket, TmdHeader};
+
+    #[test]
+    fn test_round_trip() {
+        let mut header = DiscHeader::new_zeroed();
+        header.game_id = *b"RSBE01";
+        header.game_title[..15].copy_from_slice(b"Super Smash Bro");
+        header.wii_magic.set(0x5D1C9EA3);
+        let json = serde_json::to_value(&header).unwrap();
+        assert_eq!(json["game_id"], "RSBE01");
+        assert_eq!(json["game_title"], "Super Smash Bro");
+        assert_eq!(json["wii_magic"], 0x5D1C9EA3);
+        assert!(json.get("_pad1").is_none());
+        let decoded: DiscHeader = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.as_bytes(), header.as_bytes());
+
+        // Reserved areas of signed structures are preserved, e.g. fakesign nonces
+        let mut ticket = Ticket::new_zeroed();
+        ticket.title_key = [0xAB; 16];
+        let bytes = ticket.as_bytes_mut();
+        bytes[0x21E..0x222].copy_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+        bytes[0x1E4] = 0xCD;
+        let json = serde_json::to_value(&ticket).unwrap();
+        assert_eq!(json["title_key"], "abababababababababababababababab");
+        assert_eq!(json["_pad3"], "cd00");
+        assert!(json["_pad4"].as_str().unwrap().ends_with("12345678"));
+        let decoded: Ticket = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.as_bytes(), ticket.as_bytes());
+
+        let mut tmd = TmdHeader::new_zeroed();
+        tmd.as_bytes_mut()[0x1D4..0x1D8].copy_from_slice(&[0x9A, 0xBC, 0xDE, 0xF0]);
+        tmd.as_bytes_mut()[0x19A] = 0x01;
+        let json = serde_json::to_value(&tmd).unwrap();
+        let decoded: TmdHeader = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.as_bytes(), tmd.as_bytes());
+
+        let meta = DiscMeta { md5: Some([0x01; 16]), ..Default::default() };
+        let json = serde_json::to_string(&meta).unwrap();
+        assert!(json.contains("\"md5\":\"01010101010101010101010101010101\""));
+        assert!(json.contains("\"sha1\":null"));
+        let decoded: DiscMeta = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.md5, meta.md5);
+    }
+}
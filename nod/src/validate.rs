@@ -0,0 +1,317 @@
+//! File system table validation
+
+use std::fmt;
+
+use encoding_rs::SHIFT_JIS;
+
+use crate::{Fst, NodeKind, PartitionMeta, BI2_SIZE, BOOT_SIZE};
+
+/// A partition system file, stored outside of the file system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemFile {
+    /// Disc and partition header (boot.bin)
+    Boot,
+    /// Debug and region information (bi2.bin)
+    Bi2,
+    /// Apploader (apploader.img)
+    Apploader,
+    /// Main binary (main.dol)
+    Dol,
+    /// File system table (fst.bin)
+    Fst,
+}
+
+impl fmt::Display for SystemFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Boot => write!(f, "boot.bin"),
+            Self::Bi2 => write!(f, "bi2.bin"),
+            Self::Apploader => write!(f, "apploader.img"),
+            Self::Dol => write!(f, "main.dol"),
+            Self::Fst => write!(f, "fst.bin"),
+        }
+    }
+}
+
+/// The location of a partition's system files and the size of its data, used to validate
+/// file offsets in [`Fst::validate`].
+#[derive(Debug, Clone, Default)]
+pub struct PartitionLayout {
+    /// Whether file offsets are stored shifted. (See [`Node::offset`](crate::Node::offset))
+    pub is_wii: bool,
+    /// The size of the partition data, if known.
+    /// (See [`PartitionBase::data_size`](crate::PartitionBase::data_size))
+    pub data_size: Option<u64>,
+    /// The system files, with their offsets and sizes within the partition data.
+    pub system_files: Vec<(SystemFile, u64, u64)>,
+}
+
+impl PartitionMeta {
+    /// Describes the partition layout from its headers, for use with [`Fst::validate`].
+    pub fn layout(&self, data_size: Option<u64>) -> PartitionLayout {
+        let is_wii = self.header().is_wii();
+        let header = self.partition_header();
+        let apploader_offset = (BOOT_SIZE + BI2_SIZE) as u64;
+        PartitionLayout {
+            is_wii,
+            data_size,
+            system_files: vec![
+                (SystemFile::Boot, 0, BOOT_SIZE as u64),
+                (SystemFile::Bi2, BOOT_SIZE as u64, BI2_SIZE as u64),
+                (SystemFile::Apploader, apploader_offset, self.raw_apploader.len() as u64),
+                (SystemFile::Dol, header.dol_offset(is_wii), self.raw_dol.len() as u64),
+                (SystemFile::Fst, header.fst_offset(is_wii), header.fst_size(is_wii)),
+            ],
+        }
+    }
+}
+
+/// A structural problem found by [`Fst::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FstIssue {
+    /// The index of the affected node.
+    pub index: usize,
+    /// The path of the affected node. Invalid names are decoded lossily.
+    pub path: String,
+    /// The problem found.
+    pub kind: FstIssueKind,
+}
+
+impl fmt::Display for FstIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "/{} (node {}): {}", self.path, self.index, self.kind)
+    }
+}
+
+/// The kind of problem found by [`Fst::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FstIssueKind {
+    /// The node kind is neither a file nor a directory.
+    InvalidKind(u8),
+    /// The name offset is outside of the string table.
+    NameOutOfBounds,
+    /// The name is missing its null terminator.
+    NameNotTerminated,
+    /// The name is not valid Shift-JIS.
+    NameEncoding,
+    /// Another node in the same directory has the same name. (ASCII case-insensitive)
+    DuplicateName {
+        /// The index of the first node with the name.
+        other: usize,
+    },
+    /// A directory's end index is not after the directory itself.
+    DirectoryEndBackwards {
+        /// The stored end index.
+        end: usize,
+    },
+    /// A directory's end index is past the end of its parent directory.
+    DirectoryEndOutOfRange {
+        /// The stored end index.
+        end: usize,
+        /// The end index of the parent directory.
+        max: usize,
+    },
+    /// A directory's stored parent index doesn't match its location.
+    WrongParent {
+        /// The stored parent index.
+        parent: usize,
+        /// The index of the directory containing the node.
+        expected: usize,
+    },
+    /// A file's data extends past the end of the partition data.
+    FileOutOfBounds {
+        /// The end offset of the file data.
+        end: u64,
+        /// The size of the partition data.
+        data_size: u64,
+    },
+    /// A file's data overlaps another file.
+    FileOverlap {
+        /// The index of the other file.
+        other: usize,
+    },
+    /// A file's data overlaps a system file.
+    SystemFileOverlap(SystemFile),
+}
+
+impl fmt::Display for FstIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidKind(kind) => write!(f, "invalid node kind {}", kind),
+            Self::NameOutOfBounds => write!(f, "name offset out of bounds"),
+            Self::NameNotTerminated => write!(f, "name not null-terminated"),
+            Self::NameEncoding => write!(f, "name is not valid Shift-JIS"),
+            Self::DuplicateName { other } => write!(f, "duplicate name (node {})", other),
+            Self::DirectoryEndBackwards { end } => {
+                write!(f, "directory end index {} is not after the directory", end)
+            }
+            Self::DirectoryEndOutOfRange { end, max } => {
+                write!(f, "directory end index {} is past the parent end {}", end, max)
+            }
+            Self::WrongParent { parent, expected } => {
+                write!(f, "parent index {} should be {}", parent, expected)
+            }
+            Self::FileOutOfBounds { end, data_size } => {
+                write!(
+                    f,
+                    "file data ends at {:#X}, past the partition data size {:#X}",
+                    end, data_size
+                )
+            }
+            Self::FileOverlap { other } => write!(f, "file data overlaps node {}", other),
+            Self::SystemFileOverlap(file) => write!(f, "file data overlaps {}", file),
+        }
+    }
+}
+
+impl Fst<'_> {
+    /// Checks the FST for structural problems, returning every issue found.
+    ///
+    /// Unlike [`Fst::tree`], this does not stop at the first invalid node. Directory ranges
+    /// are clamped to their parent's range to keep walking malformed tables, the same way
+    /// [`Fst::tree`] and [`Fst::find_all`] do.
+    ///
+    /// Empty files are excluded from overlap checks, as they commonly share offsets.
+    pub fn validate(&self, layout: &PartitionLayout) -> Vec<FstIssue> {
+        let mut issues = Vec::new();
+        let mut paths = vec![String::new(); self.nodes.len()];
+        // Stack of open directories: (index, end, child names)
+        let mut dirs = vec![(0usize, self.nodes.len(), Vec::<(String, usize)>::new())];
+        let mut files = Vec::new();
+        for (idx, node) in self.nodes.iter().enumerate().skip(1) {
+            while dirs.len() > 1 && dirs.last().is_some_and(|(_, end, _)| *end <= idx) {
+                dirs.pop();
+            }
+            let (parent, parent_end, siblings) = dirs.last_mut().unwrap(); // Safe: root is never removed
+            let (parent, parent_end) = (*parent, *parent_end);
+
+            let mut push = |kind| issues.push((idx, kind));
+            let name = match self.get_raw_name(node) {
+                Ok(raw) => {
+                    let (name, _, errors) = SHIFT_JIS.decode(raw);
+                    if errors {
+                        push(FstIssueKind::NameEncoding);
+                    }
+                    Some(name.into_owned())
+                }
+                Err(_) if node.name_offset() as usize >= self.string_table.len() => {
+                    push(FstIssueKind::NameOutOfBounds);
+                    None
+                }
+                Err(_) => {
+                    push(FstIssueKind::NameNotTerminated);
+                    None
+                }
+            };
+            if let Some(name) = &name {
+                if let Some((_, other)) =
+                    siblings.iter().find(|(sibling, _)| sibling.eq_ignore_ascii_case(name))
+                {
+                    push(FstIssueKind::DuplicateName { other: *other });
+                } else {
+                    siblings.push((name.clone(), idx));
+                }
+            }
+            let name = name.unwrap_or_default();
+            paths[idx] = if parent == 0 { name } else { format!("{}/{}", paths[parent], name) };
+
+            match node.kind() {
+                NodeKind::File => {
+                    let start = node.offset(layout.is_wii);
+                    let end = start + node.length();
+                    if let Some(data_size) = layout.data_size {
+                        if end > data_size {
+                            push(FstIssueKind::FileOutOfBounds { end, data_size });
+                        }
+                    }
+                    if start < end {
+                        for &(file, offset, size) in &layout.system_files {
+                            if start < offset + size && offset < end {
+                                push(FstIssueKind::SystemFileOverlap(file));
+                            }
+                        }
+                        files.push((start, end, idx));
+                    }
+                }
+                NodeKind::Directory => {
+                    let stored_parent = node.offset(layout.is_wii) as usize;
+                    if stored_parent != parent {
+                        push(FstIssueKind::WrongParent { parent: stored_parent, expected: parent });
+                    }
+                    let end = node.length() as usize;
+                    if end <= idx {
+                        push(FstIssueKind::DirectoryEndBackwards { end });
+                    } else if end > parent_end {
+                        push(FstIssueKind::DirectoryEndOutOfRange { end, max: parent_end });
+                    }
+                    dirs.push((idx, end.clamp(idx + 1, parent_end), Vec::new()));
+                }
+                NodeKind::Invalid => push(FstIssueKind::InvalidKind(node.raw_kind())),
+            }
+        }
+
+        // Sweep files by start offset, comparing each to the furthest-reaching file before it
+        files.sort_unstable();
+        let mut furthest: Option<(u64, usize)> = None;
+        for &(start, end, idx) in &files {
+            match furthest {
+                Some((furthest_end, other)) if start < furthest_end => {
+                    issues.push((idx, FstIssueKind::FileOverlap { other }));
+                    if end > furthest_end {
+                        furthest = Some((end, idx));
+                    }
+                }
+                _ => furthest = Some((end, idx)),
+            }
+        }
+
+        issues.sort_by_key(|(idx, _)| *idx);
+        issues
+            .into_iter()
+            .map(|(index, kind)| FstIssue { index, path: paths[index].clone(), kind })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zerocopy::AsBytes;
+
+    use super::*;
+    use crate::fst::tests::node;
+
+    #[test]
+    fn test_validate() {
+        let nodes = [
+            node(1, 0, 0, 8),
+            node(0, 0, 0x10000, 0x100),   // a.bin
+            node(0, 6, 0x10080, 0x100),   // A.BIN: duplicate name, overlaps a.bin
+            node(1, 12, 5, 9),            // dir: wrong parent, end past the root
+            node(0, 16, 0x2000, 0x1000),  // dir/b.bin: overlaps the apploader
+            node(0, 99, 0x20000, 0),      // name out of bounds
+            node(0, 22, 0xFF000, 0x2000), // \x82.bin: bad Shift-JIS, past the data end
+            node(2, 28, 0, 0),            // invalid kind
+        ];
+        let mut buf = nodes.as_bytes().to_vec();
+        buf.extend_from_slice(b"a.bin\0A.BIN\0dir\0b.bin\0\x82.bin\0c\0");
+        let fst = Fst::new(&buf).unwrap();
+        let layout = PartitionLayout {
+            is_wii: false,
+            data_size: Some(0x100000),
+            system_files: vec![(SystemFile::Apploader, 0x2440, 0x1000)],
+        };
+        let issues =
+            fst.validate(&layout).into_iter().map(|i| (i.index, i.kind)).collect::<Vec<_>>();
+        assert_eq!(issues, [
+            (2, FstIssueKind::DuplicateName { other: 1 }),
+            (2, FstIssueKind::FileOverlap { other: 1 }),
+            (3, FstIssueKind::WrongParent { parent: 5, expected: 0 }),
+            (3, FstIssueKind::DirectoryEndOutOfRange { end: 9, max: 8 }),
+            (4, FstIssueKind::SystemFileOverlap(SystemFile::Apploader)),
+            (5, FstIssueKind::NameOutOfBounds),
+            (6, FstIssueKind::NameEncoding),
+            (6, FstIssueKind::FileOutOfBounds { end: 0x101000, data_size: 0x100000 }),
+            (7, FstIssueKind::InvalidKind(2)),
+        ]);
+    }
+}
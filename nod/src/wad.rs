@@ -0,0 +1,726 @@
+//! WAD (installable title package) types
+
+use std::{
+    io,
+    io::{Read, SeekFrom, Write},
+    mem::size_of,
+};
+
+use zerocopy::{big_endian::*, AsBytes, FromBytes, FromZeroes};
+
+use crate::{
+    io::{aes_decrypt, aes_encrypt, KeyBytes},
+    static_assert,
+    util::read::{read_box_slice, read_from},
+    CertChain, ContentRecord, Error, FstEntry, KeyStore, PartitionBase, ReadStream, Result,
+    ResultContext, SharedWindowedReadStream, Ticket, Tmd,
+};
+
+/// WAD type of installable titles.
+pub const WAD_TYPE_INSTALLABLE: [u8; 2] = *b"Is";
+/// WAD type of boot2 titles.
+pub const WAD_TYPE_BOOT2: [u8; 2] = *b"ib";
+/// Alignment of each WAD section and content.
+pub const WAD_ALIGNMENT: u64 = 0x40;
+
+/// Title ID of the System Menu.
+pub const SYSTEM_MENU_TITLE_ID: u64 = 0x0000000100000002;
+
+/// WAD header.
+#[derive(Clone, Debug, PartialEq, FromBytes, FromZeroes, AsBytes)]
+#[repr(C, align(4))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WadHeader {
+    /// Size of the header (0x20)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub header_size: U32,
+    /// WAD type ([`WAD_TYPE_INSTALLABLE`] or [`WAD_TYPE_BOOT2`])
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::hex"))]
+    pub wad_type: [u8; 2],
+    /// WAD format version
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub wad_version: U16,
+    /// Size of the certificate chain
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub cert_chain_size: U32,
+    #[cfg_attr(feature = "serde", serde(skip, default = "crate::util::serde::zeroed"))]
+    _reserved: U32,
+    /// Size of the ticket
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub ticket_size: U32,
+    /// Size of the TMD
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub tmd_size: U32,
+    /// Size of the encrypted content data
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub data_size: U32,
+    /// Size of the footer (usually a copy of the boot content's build metadata)
+    #[cfg_attr(feature = "serde", serde(with = "crate::util::serde::be"))]
+    pub footer_size: U32,
+}
+
+static_assert!(size_of::<WadHeader>() == 0x20);
+
+impl WadHeader {
+    /// Offset of the certificate chain.
+    pub fn cert_chain_offset(&self) -> u64 { align(self.header_size.get() as u64) }
+
+    /// Offset of the ticket.
+    pub fn ticket_offset(&self) -> u64 {
+        self.cert_chain_offset() + align(self.cert_chain_size.get() as u64)
+    }
+
+    /// Offset of the TMD.
+    pub fn tmd_offset(&self) -> u64 { self.ticket_offset() + align(self.ticket_size.get() as u64) }
+
+    /// Offset of the encrypted content data.
+    pub fn data_offset(&self) -> u64 { self.tmd_offset() + align(self.tmd_size.get() as u64) }
+
+    /// Offset of the footer.
+    pub fn footer_offset(&self) -> u64 { self.data_offset() + align(self.data_size.get() as u64) }
+}
+
+/// A WAD title package, read from any [`ReadStream`].
+///
+/// The header, certificate chain, ticket, TMD and footer are read into memory. Contents are
+/// read on demand and decrypted with the title key from the ticket.
+///
+/// # Examples
+///
+/// Listing the titles in a disc's update partition:
+///
+/// ```no_run
+/// use nod::{Disc, PartitionKind, Wad};
+///
+/// fn main() -> nod::Result<()> {
+///     let disc = Disc::new("path/to/file.iso")?;
+///     let mut partition = disc.open_partition_kind(PartitionKind::Update)?;
+///     let meta = partition.meta()?;
+///     let fst = meta.fst()?;
+///     for (_, node, name) in fst.iter() {
+///         if !node.is_file() || !name?.ends_with(".wad") {
+///             continue;
+///         }
+///         let mut stream = partition.open_file(node).expect("Failed to open file");
+///         let wad = Wad::new(&mut stream)?;
+///         let tmd = wad.tmd();
+///         println!("{:016X} v{}", tmd.title_id(), tmd.title_version());
+///         let title_key = wad.title_key()?;
+///         for index in 0..tmd.contents.len() {
+///             let valid = wad.verify_content(&mut stream, index, &title_key).expect("Read failed");
+///             println!("  content {}: {}", index, if valid { "OK" } else { "hash mismatch" });
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Wad {
+    /// The WAD header.
+    pub header: WadHeader,
+    /// Certificate chain (cert.bin)
+    pub raw_cert_chain: Box<[u8]>,
+    /// Ticket (ticket.bin)
+    pub raw_ticket: Box<[u8]>,
+    /// TMD (tmd.bin)
+    pub raw_tmd: Box<[u8]>,
+    /// Footer
+    pub raw_footer: Box<[u8]>,
+}
+
+impl Wad {
+    /// Whether the data starts with a WAD header.
+    pub fn is_wad(data: &[u8]) -> bool {
+        data.len() >= 8
+            && data[..4] == [0, 0, 0, 0x20]
+            && matches!(<[u8; 2]>::try_from(&data[4..6]), Ok(WAD_TYPE_INSTALLABLE | WAD_TYPE_BOOT2))
+    }
+
+    /// Reads the header, certificate chain, ticket, TMD and footer from a stream positioned
+    /// anywhere; offsets are relative to the start of the stream.
+    pub fn new<R>(reader: &mut R) -> Result<Self>
+    where R: ReadStream + ?Sized {
+        reader.seek(SeekFrom::Start(0)).context("Seeking to WAD header")?;
+        let header: WadHeader = read_from(reader).context("Reading WAD header")?;
+        if !Self::is_wad(header.as_bytes()) {
+            return Err(Error::DiscFormat(format!(
+                "Invalid WAD header: {:02X?}",
+                &header.as_bytes()[..8]
+            )));
+        }
+        if (header.ticket_size.get() as usize) < size_of::<Ticket>() {
+            return Err(Error::DiscFormat(format!(
+                "WAD: invalid ticket size {:#X}",
+                header.ticket_size.get()
+            )));
+        }
+        let mut read_section = |offset: u64, size: U32, name: &str| -> Result<Box<[u8]>> {
+            reader
+                .seek(SeekFrom::Start(offset))
+                .with_context(|| format!("Seeking to WAD {}", name))?;
+            read_box_slice(reader, size.get() as usize)
+                .with_context(|| format!("Reading WAD {}", name))
+        };
+        let raw_cert_chain =
+            read_section(header.cert_chain_offset(), header.cert_chain_size, "certificate chain")?;
+        let raw_ticket = read_section(header.ticket_offset(), header.ticket_size, "ticket")?;
+        let raw_tmd = read_section(header.tmd_offset(), header.tmd_size, "TMD")?;
+        let raw_footer = read_section(header.footer_offset(), header.footer_size, "footer")?;
+        Tmd::new(&raw_tmd)?;
+        Ok(Self { header, raw_cert_chain, raw_ticket, raw_tmd, raw_footer })
+    }
+
+    /// A view into the ticket.
+    pub fn ticket(&self) -> &Ticket {
+        Ticket::ref_from_prefix(&self.raw_ticket).unwrap() // Safe: size checked in `new`
+    }
+
+    /// A view into the TMD, including the content records.
+    pub fn tmd(&self) -> Tmd<'_> { Tmd::new(&self.raw_tmd).unwrap() }
+
+    /// A view into the certificate chain.
+    pub fn cert_chain(&self) -> Result<CertChain<'_>, String> {
+        CertChain::new(&self.raw_cert_chain)
+    }
+
+    /// Decrypts the title key using the built-in common keys.
+    pub fn title_key(&self) -> Result<KeyBytes> { self.ticket().decrypt_title_key() }
+
+    /// Decrypts the title key using the common keys from `keys`.
+    pub fn title_key_with(&self, keys: &KeyStore) -> Result<KeyBytes> {
+        self.ticket().decrypt_title_key_with(keys)
+    }
+
+    /// The offset of a content's encrypted data, by position in the TMD content records.
+    ///
+    /// Contents are stored in TMD order, each aligned to [`WAD_ALIGNMENT`].
+    pub fn content_offset(&self, index: usize) -> Option<u64> {
+        let tmd = self.tmd();
+        if index >= tmd.contents.len() {
+            return None;
+        }
+        let preceding: u64 = tmd.contents[..index].iter().map(|c| align(c.size())).sum();
+        Some(self.header.data_offset() + preceding)
+    }
+
+    /// Opens a content's encrypted data (padded to the AES block size) as a windowed stream
+    /// over `reader`.
+    pub fn open_content<'a, R>(
+        &self,
+        reader: &'a mut R,
+        index: usize,
+    ) -> io::Result<SharedWindowedReadStream<'a>>
+    where
+        R: ReadStream + ?Sized,
+    {
+        let (offset, content) = self.content(index)?;
+        reader.new_window(offset, encrypted_size(content))
+    }
+
+    /// Reads and decrypts a content with the title key.
+    pub fn read_content<R>(
+        &self,
+        reader: &mut R,
+        index: usize,
+        title_key: &KeyBytes,
+    ) -> io::Result<Vec<u8>>
+    where
+        R: ReadStream + ?Sized,
+    {
+        let (_, content) = self.content(index)?;
+        let mut data = Vec::with_capacity(encrypted_size(content) as usize);
+        self.open_content(reader, index)?.read_to_end(&mut data)?;
+        aes_decrypt(title_key, content_iv(content), &mut data);
+        data.truncate(content.size() as usize);
+        Ok(data)
+    }
+
+    /// Reads and decrypts a content, and checks its SHA-1 hash against the TMD.
+    pub fn verify_content<R>(
+        &self,
+        reader: &mut R,
+        index: usize,
+        title_key: &KeyBytes,
+    ) -> io::Result<bool>
+    where
+        R: ReadStream + ?Sized,
+    {
+        let data = self.read_content(reader, index, title_key)?;
+        Ok(self.tmd().contents[index].verify(&data))
+    }
+
+    /// Creates an installable WAD from a certificate chain, ticket, TMD and footer, to be written
+    /// with [`Wad::write`].
+    ///
+    /// The TMD is trimmed to its content records.
+    pub fn from_parts(
+        raw_cert_chain: Box<[u8]>,
+        raw_ticket: Box<[u8]>,
+        raw_tmd: Box<[u8]>,
+        raw_footer: Box<[u8]>,
+    ) -> Result<Self> {
+        if raw_ticket.len() < size_of::<Ticket>() {
+            return Err(Error::DiscFormat(format!(
+                "WAD: invalid ticket size {:#X}",
+                raw_ticket.len()
+            )));
+        }
+        let tmd = Tmd::new(&raw_tmd)?;
+        let raw_tmd: Box<[u8]> = raw_tmd[..tmd.size()].into();
+        let data_size = tmd.contents.iter().map(|c| align(c.size())).sum::<u64>();
+        let section_size = |size: u64, name: &str| {
+            u32::try_from(size).map(U32::new).map_err(|_| {
+                Error::DiscFormat(format!("WAD: {} too large ({:#X} bytes)", name, size))
+            })
+        };
+        let mut header = WadHeader::new_zeroed();
+        header.header_size = U32::new(size_of::<WadHeader>() as u32);
+        header.wad_type = WAD_TYPE_INSTALLABLE;
+        header.cert_chain_size = section_size(raw_cert_chain.len() as u64, "certificate chain")?;
+        header.ticket_size = section_size(raw_ticket.len() as u64, "ticket")?;
+        header.tmd_size = section_size(raw_tmd.len() as u64, "TMD")?;
+        header.data_size = section_size(data_size, "content data")?;
+        header.footer_size = section_size(raw_footer.len() as u64, "footer")?;
+        Ok(Self { header, raw_cert_chain, raw_ticket, raw_tmd, raw_footer })
+    }
+
+    /// Writes the WAD, encrypting the decrypted contents (in TMD order) with the title key.
+    ///
+    /// Each content must match the size and hash of its TMD content record.
+    pub fn write<W>(&self, out: &mut W, contents: &[&[u8]], title_key: &KeyBytes) -> Result<()>
+    where W: Write + ?Sized {
+        let tmd = self.tmd();
+        if contents.len() != tmd.contents.len() {
+            return Err(Error::Other(format!(
+                "WAD: expected {} contents, got {}",
+                tmd.contents.len(),
+                contents.len()
+            )));
+        }
+        for (record, data) in tmd.contents.iter().zip(contents) {
+            if data.len() as u64 != record.size() || !record.verify(data) {
+                return Err(Error::DiscFormat(format!(
+                    "WAD: content {} doesn't match its TMD hash",
+                    record.index.get()
+                )));
+            }
+        }
+
+        write_aligned(out, self.header.as_bytes()).context("Writing WAD header")?;
+        write_aligned(out, &self.raw_cert_chain).context("Writing WAD certificate chain")?;
+        write_aligned(out, &self.raw_ticket).context("Writing WAD ticket")?;
+        write_aligned(out, &self.raw_tmd).context("Writing WAD TMD")?;
+        for (record, data) in tmd.contents.iter().zip(contents) {
+            let mut encrypted = data.to_vec();
+            encrypted.resize(encrypted_size(record) as usize, 0);
+            aes_encrypt(title_key, content_iv(record), &mut encrypted);
+            write_aligned(out, &encrypted)
+                .with_context(|| format!("Writing WAD content {}", record.index.get()))?;
+        }
+        write_aligned(out, &self.raw_footer).context("Writing WAD footer")?;
+        Ok(())
+    }
+
+    fn content(&self, index: usize) -> io::Result<(u64, &ContentRecord)> {
+        let tmd = self.tmd();
+        match (self.content_offset(index), tmd.contents.get(index)) {
+            (Some(offset), Some(content)) => Ok((offset, content)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Content {} out of range (count {})", index, tmd.contents.len()),
+            )),
+        }
+    }
+}
+
+/// Packages the channel title stored in a Wii partition's file system into an installable WAD,
+/// re-encrypting its contents with the title key.
+///
+/// The ticket and TMD of a channel installer partition belong to the installer itself, whose
+/// only content is the partition's H3 hash table. The channel is stored as a title of its own:
+/// its TMD is found anywhere in the file system as `tmd`, `tmd.bin` or `*.tmd`, with the ticket
+/// (`cetk`, `ticket.bin` or `*.tik`) and contents (`<content ID>.app` or `<content ID>`, e.g.
+/// `00000001.app`) in the same directory. A certificate chain next to the TMD (`cert.sys`,
+/// `cert.bin` or `*.cert`) is used if present, otherwise the partition's own chain, which holds
+/// the same ticket and TMD signers.
+///
+/// Contents may be stored decrypted or encrypted; each must match its TMD hash. The title key
+/// is decrypted with the built-in common keys.
+///
+/// # Examples
+///
+/// ```no_run
+/// use nod::{Disc, PartitionKind};
+///
+/// fn main() -> nod::Result<()> {
+///     let disc = Disc::new("path/to/file.iso")?;
+///     let mut partition = disc.open_partition_kind(PartitionKind::Channel)?;
+///     let mut wad = Vec::new();
+///     nod::write_wad(&mut wad, partition.as_mut())?;
+///     std::fs::write("path/to/channel.wad", wad).expect("Failed to write file");
+///     Ok(())
+/// }
+/// ```
+pub fn write_wad<W>(out: &mut W, partition: &mut dyn PartitionBase) -> Result<()>
+where W: Write + ?Sized {
+    write_wad_with(out, partition, &KeyStore::default())
+}
+
+/// Packages the channel title stored in a Wii partition's file system into an installable WAD,
+/// decrypting the title key with the common keys from `keys`. (See [`write_wad`])
+pub fn write_wad_with<W>(
+    out: &mut W,
+    partition: &mut dyn PartitionBase,
+    keys: &KeyStore,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    let meta = partition.meta()?;
+    let tree = meta.fst()?.tree().map_err(Error::DiscFormat)?;
+    let tmd_entry = match tree
+        .iter()
+        .filter(|e| e.node.is_file() && is_title_file(&e.name, &["tmd", "tmd.bin"], Some("tmd")))
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [entry] => *entry,
+        [] => {
+            return Err(Error::DiscFormat(
+                "No channel TMD found in partition file system".to_string(),
+            ))
+        }
+        entries => {
+            return Err(Error::DiscFormat(format!(
+                "Multiple TMDs found in partition file system: {}",
+                entries.iter().map(|e| e.path.as_str()).collect::<Vec<_>>().join(", ")
+            )))
+        }
+    };
+    let title_dir = tree.parent(tmd_entry).unwrap_or(tree.root());
+    let find = |names: &[&str], ext: Option<&str>| {
+        tree.children(title_dir).find(|e| e.node.is_file() && is_title_file(&e.name, names, ext))
+    };
+
+    let raw_tmd = read_entry(partition, tmd_entry)?;
+    let ticket_entry = find(&["cetk", "ticket.bin"], Some("tik"))
+        .ok_or_else(|| Error::DiscFormat(format!("No ticket found next to {}", tmd_entry.path)))?;
+    let raw_ticket = read_entry(partition, ticket_entry)?;
+    let raw_cert_chain = match find(&["cert.sys", "cert.bin"], Some("cert")) {
+        Some(entry) => read_entry(partition, entry)?.into(),
+        None => meta.raw_cert_chain.clone().ok_or_else(|| {
+            Error::DiscFormat("No certificate chain found for the channel".to_string())
+        })?,
+    };
+    let wad = Wad::from_parts(raw_cert_chain, raw_ticket.into(), raw_tmd.into(), Box::default())?;
+    let tmd = wad.tmd();
+    if wad.ticket().title_id != tmd.header.title_id {
+        return Err(Error::DiscFormat(format!(
+            "Ticket title ID {:016x} doesn't match TMD title ID {:016x}",
+            u64::from_be_bytes(wad.ticket().title_id),
+            tmd.title_id()
+        )));
+    }
+    let title_key = wad.title_key_with(keys)?;
+
+    let mut contents = Vec::with_capacity(tmd.contents.len());
+    for record in tmd.contents {
+        let name = format!("{:08x}", record.content_id.get());
+        let entry = find(&[&format!("{}.app", name), &name], None).ok_or_else(|| {
+            Error::DiscFormat(format!("Content {}.app not found next to {}", name, tmd_entry.path))
+        })?;
+        let mut data = read_entry(partition, entry)?;
+        if !record.verify(&data) && data.len() as u64 == encrypted_size(record) {
+            let mut decrypted = data.clone();
+            aes_decrypt(&title_key, content_iv(record), &mut decrypted);
+            decrypted.truncate(record.size() as usize);
+            if record.verify(&decrypted) {
+                data = decrypted;
+            }
+        }
+        contents.push(data);
+    }
+    let contents: Vec<&[u8]> = contents.iter().map(Vec::as_slice).collect();
+    wad.write(out, &contents, &title_key)
+}
+
+/// Whether a file name is one of `names` or has the extension `ext`, ignoring ASCII case.
+fn is_title_file(name: &str, names: &[&str], ext: Option<&str>) -> bool {
+    names.iter().any(|n| name.eq_ignore_ascii_case(n))
+        || ext.is_some_and(|ext| {
+            name.rsplit_once('.')
+                .is_some_and(|(stem, e)| !stem.is_empty() && e.eq_ignore_ascii_case(ext))
+        })
+}
+
+fn read_entry(partition: &mut dyn PartitionBase, entry: &FstEntry) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(entry.node.length() as usize);
+    partition
+        .open_file(&entry.node)
+        .and_then(|mut file| file.read_to_end(&mut data))
+        .with_context(|| format!("Reading {}", entry.path))?;
+    Ok(data)
+}
+
+/// Known System Menu versions, from the TMD title version.
+const SYSTEM_MENU_VERSIONS: &[(u16, &str)] = &[
+    (33, "1.0"),
+    (97, "2.0U"),
+    (128, "2.0J"),
+    (130, "2.0E"),
+    (162, "2.1E"),
+    (192, "2.2J"),
+    (193, "2.2U"),
+    (194, "2.2E"),
+    (224, "3.0J"),
+    (225, "3.0U"),
+    (226, "3.0E"),
+    (256, "3.1J"),
+    (257, "3.1U"),
+    (258, "3.1E"),
+    (288, "3.2J"),
+    (289, "3.2U"),
+    (290, "3.2E"),
+    (326, "3.3K"),
+    (352, "3.3J"),
+    (353, "3.3U"),
+    (354, "3.3E"),
+    (384, "3.4J"),
+    (385, "3.4U"),
+    (386, "3.4E"),
+    (390, "3.5K"),
+    (416, "4.0J"),
+    (417, "4.0U"),
+    (418, "4.0E"),
+    (448, "4.1J"),
+    (449, "4.1U"),
+    (450, "4.1E"),
+    (454, "4.1K"),
+    (480, "4.2J"),
+    (481, "4.2U"),
+    (482, "4.2E"),
+    (486, "4.2K"),
+    (512, "4.3J"),
+    (513, "4.3U"),
+    (514, "4.3E"),
+    (518, "4.3K"),
+];
+
+/// The release name of a System Menu title version. (e.g. 513 is "4.3U")
+pub fn system_menu_version(version: u16) -> Option<&'static str> {
+    SYSTEM_MENU_VERSIONS.iter().find(|(v, _)| *v == version).map(|(_, name)| *name)
+}
+
+#[inline]
+fn align(size: u64) -> u64 { size.next_multiple_of(WAD_ALIGNMENT) }
+
+#[inline]
+fn encrypted_size(content: &ContentRecord) -> u64 { content.size().next_multiple_of(0x10) }
+
+/// Writes data followed by zero padding to [`WAD_ALIGNMENT`].
+fn write_aligned<W>(out: &mut W, data: &[u8]) -> io::Result<()>
+where W: Write + ?Sized {
+    out.write_all(data)?;
+    let padding = align(data.len() as u64) - data.len() as u64;
+    out.write_all(&[0u8; WAD_ALIGNMENT as usize][..padding as usize])
+}
+
+/// Content IV: the content index followed by zeroes.
+fn content_iv(content: &ContentRecord) -> KeyBytes {
+    let mut iv: KeyBytes = [0; 16];
+    iv[..2].copy_from_slice(content.index.as_bytes());
+    iv
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Seek};
+
+    use sha1::{Digest, Sha1};
+
+    use super::*;
+    use crate::{fst::tests::node, Node, PartitionMeta, TmdHeader, BI2_SIZE, BOOT_SIZE};
+
+    fn content_record(index: usize, content: &[u8]) -> ContentRecord {
+        ContentRecord {
+            content_id: U32::new(index as u32 + 0x10),
+            index: U16::new(index as u16),
+            content_type: U16::new(ContentRecord::CONTENT_TYPE_NORMAL),
+            size: U64::new(content.len() as u64),
+            hash: Sha1::digest(content).into(),
+        }
+    }
+
+    /// Builds a ticket and TMD for the contents.
+    fn title(title_id: u64, title_key: KeyBytes, contents: &[&[u8]]) -> (Ticket, Vec<u8>) {
+        let mut ticket = Ticket::new_zeroed();
+        ticket.sig_issuer[..26].copy_from_slice(b"Root-CA00000001-XS00000003");
+        ticket.title_id = title_id.to_be_bytes();
+        ticket.encrypt_title_key(title_key).unwrap();
+        let mut header = TmdHeader::new_zeroed();
+        header.title_id = title_id.to_be_bytes();
+        header.title_version = U16::new(513);
+        header.num_contents = U16::new(contents.len() as u16);
+        let mut tmd = header.as_bytes().to_vec();
+        for (i, content) in contents.iter().enumerate() {
+            tmd.extend_from_slice(content_record(i, content).as_bytes());
+        }
+        (ticket, tmd)
+    }
+
+    #[test]
+    fn test_wad() {
+        let title_key: KeyBytes = *b"0123456789abcdef";
+        let contents: [&[u8]; 2] = [b"hello", &[0xAA; 0x50]];
+
+        let (ticket, tmd) = title(SYSTEM_MENU_TITLE_ID, title_key, &contents);
+        let mut data = Vec::new();
+        for (i, content) in contents.iter().enumerate() {
+            let record = content_record(i, content);
+            let mut encrypted = content.to_vec();
+            encrypted.resize(encrypted_size(&record) as usize, 0);
+            aes_encrypt(&title_key, content_iv(&record), &mut encrypted);
+            data.extend_from_slice(&encrypted);
+            data.resize(align(data.len() as u64) as usize, 0);
+        }
+
+        let mut wad = WadHeader::new_zeroed();
+        wad.header_size = U32::new(0x20);
+        wad.wad_type = WAD_TYPE_INSTALLABLE;
+        wad.cert_chain_size = U32::new(0x10);
+        wad.ticket_size = U32::new(size_of::<Ticket>() as u32);
+        wad.tmd_size = U32::new(tmd.len() as u32);
+        wad.data_size = U32::new(data.len() as u32);
+        let mut out = wad.as_bytes().to_vec();
+        for section in [&[0u8; 0x10][..], ticket.as_bytes(), &tmd, &data] {
+            out.resize(align(out.len() as u64) as usize, 0);
+            out.extend_from_slice(section);
+        }
+
+        let mut reader = Cursor::new(out);
+        let wad = Wad::new(&mut reader).unwrap();
+        assert_eq!(wad.tmd().title_id(), SYSTEM_MENU_TITLE_ID);
+        assert_eq!(system_menu_version(wad.tmd().title_version()), Some("4.3U"));
+        assert_eq!(wad.title_key().unwrap(), title_key);
+        assert_eq!(wad.content_offset(1), Some(wad.header.data_offset() + 0x40));
+        assert_eq!(wad.read_content(&mut reader, 0, &title_key).unwrap(), b"hello");
+        assert!(wad.verify_content(&mut reader, 1, &title_key).unwrap());
+        assert!(!wad.verify_content(&mut reader, 1, &[0; 16]).unwrap());
+        assert!(wad.read_content(&mut reader, 2, &title_key).is_err());
+        assert!(Wad::new(&mut Cursor::new(vec![0u8; 0x40])).is_err());
+
+        // Rebuilding from the parts re-encrypts the contents identically
+        let rebuilt = Wad::from_parts(
+            wad.raw_cert_chain.clone(),
+            wad.raw_ticket.clone(),
+            wad.raw_tmd.clone(),
+            Box::default(),
+        )
+        .unwrap();
+        assert_eq!(rebuilt.header, wad.header);
+        let mut written = Vec::new();
+        rebuilt.write(&mut written, &contents, &title_key).unwrap();
+        assert_eq!(&written, reader.get_ref());
+        assert!(rebuilt.write(&mut Vec::new(), &[b"hello", b"oops"], &title_key).is_err());
+    }
+
+    #[derive(Clone)]
+    struct MockPartition {
+        meta: PartitionMeta,
+        data: Cursor<Vec<u8>>,
+    }
+
+    impl Read for MockPartition {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> { self.data.read(buf) }
+    }
+
+    impl Seek for MockPartition {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> { self.data.seek(pos) }
+    }
+
+    impl PartitionBase for MockPartition {
+        fn meta(&mut self) -> Result<Box<PartitionMeta>> { Ok(Box::new(self.meta.clone())) }
+
+        fn open_file(&mut self, node: &Node) -> io::Result<SharedWindowedReadStream<'_>> {
+            self.new_window(node.offset(true), node.length())
+        }
+
+        fn ideal_buffer_size(&self) -> usize { 0x7C00 }
+    }
+
+    #[test]
+    fn test_write_wad() {
+        const CHANNEL_TITLE_ID: u64 = 0x0001000148414645;
+        let title_key: KeyBytes = *b"0123456789abcdef";
+        let contents: [&[u8]; 2] = [b"banner", &[0x55; 0x123]];
+        let (ticket, tmd) = title(CHANNEL_TITLE_ID, title_key, &contents);
+
+        // The installer partition's title, whose only content is the H3 table
+        let h3_table = [0x33u8; 0x100];
+        let (partition_ticket, mut partition_tmd) =
+            title(0x0001000152464e45, *b"fedcba9876543210", &[&h3_table]);
+        partition_tmd.truncate(size_of::<TmdHeader>());
+        partition_tmd.extend_from_slice(
+            ContentRecord { content_id: U32::new(0), ..content_record(0, &h3_table) }.as_bytes(),
+        );
+
+        // /install/title.tmd, /install/title.tik, /install/00000010.app (decrypted),
+        // /install/00000011 (encrypted)
+        let nodes = [
+            node(1, 0, 0, 6),
+            node(1, 0, 0, 6),
+            node(0, 8, 0x40, tmd.len() as u32),
+            node(0, 18, 0x100, size_of::<Ticket>() as u32),
+            node(0, 28, 0x200, 6),
+            node(0, 41, 0x280, 0x130),
+        ];
+        let mut raw_fst = nodes.as_bytes().to_vec();
+        raw_fst.extend_from_slice(b"install\0title.tmd\0title.tik\x0000000010.app\x0000000011\0");
+        let mut data = vec![0u8; 0x1000];
+        data[0x100..0x100 + tmd.len()].copy_from_slice(&tmd);
+        data[0x400..0x400 + size_of::<Ticket>()].copy_from_slice(ticket.as_bytes());
+        data[0x800..0x806].copy_from_slice(contents[0]);
+        let mut encrypted = contents[1].to_vec();
+        encrypted.resize(0x130, 0);
+        aes_encrypt(&title_key, content_iv(&content_record(1, contents[1])), &mut encrypted);
+        data[0xA00..0xB30].copy_from_slice(&encrypted);
+        let mut partition = MockPartition {
+            meta: PartitionMeta {
+                raw_boot: Box::new([0; BOOT_SIZE]),
+                raw_bi2: Box::new([0; BI2_SIZE]),
+                raw_apploader: Box::default(),
+                raw_fst: raw_fst.into(),
+                raw_dol: Box::default(),
+                raw_ticket: Some(partition_ticket.as_bytes().into()),
+                raw_tmd: Some(partition_tmd.into()),
+                raw_cert_chain: Some(Box::new([0; 0x10])),
+                raw_h3_table: Some(Box::new(h3_table)),
+            },
+            data: Cursor::new(data),
+        };
+        assert_eq!(partition.meta.verify_tmd_content(), Ok(Some(true)));
+
+        let mut out = Vec::new();
+        write_wad(&mut out, &mut partition).unwrap();
+        let mut reader = Cursor::new(out);
+        let wad = Wad::new(&mut reader).unwrap();
+        assert_eq!(wad.tmd().title_id(), CHANNEL_TITLE_ID);
+        assert_eq!(wad.tmd().contents.len(), 2);
+        assert_eq!(*wad.raw_cert_chain, [0; 0x10]);
+        assert_eq!(wad.read_content(&mut reader, 0, &title_key).unwrap(), contents[0]);
+        assert_eq!(wad.read_content(&mut reader, 1, &title_key).unwrap(), contents[1]);
+
+        // Contents that don't match the TMD, or are missing, are rejected
+        partition.data.get_mut()[0x800] = 0;
+        assert!(write_wad(&mut Vec::new(), &mut partition).is_err());
+        partition.data.get_mut()[0x800] = b'b';
+        let names_offset = nodes.as_bytes().len();
+        partition.meta.raw_fst[names_offset + 48] = b'2';
+        assert!(write_wad(&mut Vec::new(), &mut partition).is_err());
+        partition.meta.raw_fst[names_offset + 48] = b'1';
+        // Without the channel's TMD, the partition's own title isn't packaged
+        partition.meta.raw_fst[names_offset + 14] = b'x';
+        let result = write_wad(&mut Vec::new(), &mut partition);
+        assert!(matches!(result, Err(Error::DiscFormat(e)) if e.contains("No channel TMD")));
+    }
+}
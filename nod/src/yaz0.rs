@@ -0,0 +1,598 @@
+//! Yaz0 and Yay0 compression
+
+use std::{
+    fmt, io,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+};
+
+/// Yaz0 magic.
+pub const YAZ0_MAGIC: [u8; 4] = *b"Yaz0";
+/// Yay0 magic.
+pub const YAY0_MAGIC: [u8; 4] = *b"Yay0";
+
+/// Size of the Yaz0 and Yay0 headers.
+const HEADER_SIZE: usize = 0x10;
+/// Maximum back-reference distance.
+const WINDOW_SIZE: usize = 0x1000;
+/// Minimum back-reference length.
+const MIN_MATCH: usize = 3;
+/// Maximum back-reference length.
+const MAX_MATCH: usize = 0x111;
+/// Compression ratio up to which [`decompress`] preallocates the output.
+const PREALLOC_RATIO: usize = 8;
+
+/// A file compression format, detected by magic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileCompression {
+    /// Yaz0 (`.szs`, `.carc`)
+    Yaz0,
+    /// Yay0 (`.szp`)
+    Yay0,
+}
+
+impl FileCompression {
+    /// Detects the compression format from the start of a file, returning the format and the
+    /// decompressed size from the header.
+    pub fn detect(data: &[u8]) -> Option<(Self, u32)> {
+        let kind = match data.get(..4)? {
+            magic if magic == YAZ0_MAGIC => Self::Yaz0,
+            magic if magic == YAY0_MAGIC => Self::Yay0,
+            _ => return None,
+        };
+        let size = u32::from_be_bytes(data.get(4..8)?.try_into().unwrap());
+        Some((kind, size))
+    }
+
+    /// Compresses data.
+    pub fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Yaz0 => compress_yaz0(data),
+            Self::Yay0 => compress_yay0(data),
+        }
+    }
+}
+
+impl fmt::Display for FileCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Yaz0 => write!(f, "Yaz0"),
+            Self::Yay0 => write!(f, "Yay0"),
+        }
+    }
+}
+
+/// A stream that transparently decompresses Yaz0 and Yay0 data.
+///
+/// Data without a known magic is passed through unchanged.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::io::Read;
+///
+/// use nod::{DecompressedStream, Disc, PartitionKind};
+///
+/// fn main() -> nod::Result<()> {
+///     let disc = Disc::new("path/to/file.iso")?;
+///     let mut partition = disc.open_partition_kind(PartitionKind::Data)?;
+///     let meta = partition.meta()?;
+///     let fst = meta.fst()?;
+///     if let Some((_, node)) = fst.find("/Common.szs") {
+///         let file = partition.open_file(node).expect("Failed to open file stream");
+///         let mut stream = DecompressedStream::new(file).expect("Failed to read header");
+///         let mut data = Vec::new();
+///         stream.read_to_end(&mut data).expect("Failed to decompress file");
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct DecompressedStream<R> {
+    inner: StreamKind<R>,
+}
+
+enum StreamKind<R> {
+    Raw(R),
+    Yaz0(Yaz0Reader<R>),
+    Yay0(Yay0Reader),
+}
+
+impl<R> DecompressedStream<R>
+where R: Read + Seek
+{
+    /// Detects the compression format from the header and creates a decoding stream.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let start = reader.stream_position()?;
+        let mut header = [0u8; HEADER_SIZE];
+        let mut read = 0;
+        while read < HEADER_SIZE {
+            match reader.read(&mut header[read..])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+        reader.seek(SeekFrom::Start(start))?;
+        let inner = match FileCompression::detect(&header[..read]) {
+            Some((FileCompression::Yaz0, _)) => StreamKind::Yaz0(Yaz0Reader::new(reader)?),
+            Some((FileCompression::Yay0, _)) => StreamKind::Yay0(Yay0Reader::new(reader)?),
+            None => StreamKind::Raw(reader),
+        };
+        Ok(Self { inner })
+    }
+
+    /// The detected compression format, or `None` if the data is passed through.
+    pub fn compression(&self) -> Option<FileCompression> {
+        match self.inner {
+            StreamKind::Raw(_) => None,
+            StreamKind::Yaz0(_) => Some(FileCompression::Yaz0),
+            StreamKind::Yay0(_) => Some(FileCompression::Yay0),
+        }
+    }
+
+    /// The decompressed size from the header, or `None` if the data is passed through.
+    pub fn decompressed_size(&self) -> Option<u64> {
+        match &self.inner {
+            StreamKind::Raw(_) => None,
+            StreamKind::Yaz0(r) => Some(r.size as u64),
+            StreamKind::Yay0(r) => Some(r.size as u64),
+        }
+    }
+}
+
+impl<R> Read for DecompressedStream<R>
+where R: Read
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            StreamKind::Raw(r) => r.read(buf),
+            StreamKind::Yaz0(r) => r.read(buf),
+            StreamKind::Yay0(r) => r.read(buf),
+        }
+    }
+}
+
+/// Decompresses Yaz0 or Yay0 data in memory, detected by magic.
+///
+/// Returns `None` if the data isn't compressed.
+pub fn decompress(data: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    let Some((_, size)) = FileCompression::detect(data) else {
+        return Ok(None);
+    };
+    // The header size is untrusted, so preallocate at most a typical ratio of the input
+    let mut out = Vec::with_capacity((size as usize).min(data.len() * PREALLOC_RATIO));
+    DecompressedStream::new(io::Cursor::new(data))?.read_to_end(&mut out)?;
+    Ok(Some(out))
+}
+
+/// A streaming Yaz0 decoder.
+pub struct Yaz0Reader<R> {
+    source: Yaz0Source<R>,
+    size: u32,
+    state: DecodeState,
+}
+
+impl<R> Yaz0Reader<R>
+where R: Read
+{
+    /// Reads the header and creates a decoder.
+    pub fn new(reader: R) -> io::Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let mut header = [0u8; HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        let Some((FileCompression::Yaz0, size)) = FileCompression::detect(&header) else {
+            return Err(invalid_data("Yaz0: invalid magic"));
+        };
+        let source = Yaz0Source { reader, flags: 0, flag_bits: 0 };
+        Ok(Self { source, size, state: DecodeState::new(size) })
+    }
+
+    /// The decompressed size from the header.
+    pub fn decompressed_size(&self) -> u32 { self.size }
+}
+
+impl<R> Read for Yaz0Reader<R>
+where R: Read
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.state.read(&mut self.source, buf)
+    }
+}
+
+struct Yaz0Source<R> {
+    reader: BufReader<R>,
+    flags: u8,
+    flag_bits: u32,
+}
+
+impl<R> Yaz0Source<R>
+where R: Read
+{
+    fn next_byte(&mut self) -> io::Result<u8> {
+        let byte = *self.reader.fill_buf()?.first().ok_or_else(unexpected_eof)?;
+        self.reader.consume(1);
+        Ok(byte)
+    }
+}
+
+impl<R> Source for Yaz0Source<R>
+where R: Read
+{
+    fn next_op(&mut self) -> io::Result<Op> {
+        if self.flag_bits == 0 {
+            self.flags = self.next_byte()?;
+            self.flag_bits = 8;
+        }
+        self.flag_bits -= 1;
+        if self.flags & (1 << self.flag_bits) != 0 {
+            return Ok(Op::Literal(self.next_byte()?));
+        }
+        let (b1, b2) = (self.next_byte()? as usize, self.next_byte()? as usize);
+        let distance = ((b1 & 0xF) << 8 | b2) + 1;
+        let length = match b1 >> 4 {
+            0 => self.next_byte()? as usize + 0x12,
+            n => n + 2,
+        };
+        Ok(Op::Copy { distance, length })
+    }
+}
+
+/// A streaming Yay0 decoder.
+///
+/// Yay0 stores flags, back-references and literals in separate sections, so the compressed
+/// data is read into memory and the output is decoded on demand.
+pub struct Yay0Reader {
+    source: Yay0Source,
+    size: u32,
+    state: DecodeState,
+}
+
+impl Yay0Reader {
+    /// Reads the compressed data and creates a decoder.
+    pub fn new<R>(mut reader: R) -> io::Result<Self>
+    where R: Read {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let Some((FileCompression::Yay0, size)) = FileCompression::detect(&data) else {
+            return Err(invalid_data("Yay0: invalid magic"));
+        };
+        let header = data.get(..HEADER_SIZE).ok_or_else(unexpected_eof)?;
+        let link_pos = u32::from_be_bytes(header[8..12].try_into().unwrap()) as usize;
+        let chunk_pos = u32::from_be_bytes(header[12..16].try_into().unwrap()) as usize;
+        let source = Yay0Source {
+            data: data.into_boxed_slice(),
+            flag_pos: HEADER_SIZE,
+            link_pos,
+            chunk_pos,
+            flags: 0,
+            flag_bits: 0,
+        };
+        Ok(Self { source, size, state: DecodeState::new(size) })
+    }
+
+    /// The decompressed size from the header.
+    pub fn decompressed_size(&self) -> u32 { self.size }
+}
+
+impl Read for Yay0Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.state.read(&mut self.source, buf)
+    }
+}
+
+struct Yay0Source {
+    data: Box<[u8]>,
+    flag_pos: usize,
+    link_pos: usize,
+    chunk_pos: usize,
+    flags: u32,
+    flag_bits: u32,
+}
+
+impl Yay0Source {
+    fn take<const N: usize>(&self, pos: &mut usize) -> io::Result<[u8; N]> {
+        let bytes = self.data.get(*pos..*pos + N).ok_or_else(unexpected_eof)?;
+        *pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+}
+
+impl Source for Yay0Source {
+    fn next_op(&mut self) -> io::Result<Op> {
+        if self.flag_bits == 0 {
+            let mut pos = self.flag_pos;
+            self.flags = u32::from_be_bytes(self.take(&mut pos)?);
+            self.flag_pos = pos;
+            self.flag_bits = 32;
+        }
+        self.flag_bits -= 1;
+        let mut chunk_pos = self.chunk_pos;
+        let op = if self.flags & (1 << self.flag_bits) != 0 {
+            let [byte] = self.take(&mut chunk_pos)?;
+            Op::Literal(byte)
+        } else {
+            let mut link_pos = self.link_pos;
+            let link = u16::from_be_bytes(self.take(&mut link_pos)?) as usize;
+            self.link_pos = link_pos;
+            let distance = (link & 0xFFF) + 1;
+            let length = match link >> 12 {
+                0 => self.take::<1>(&mut chunk_pos)?[0] as usize + 0x12,
+                n => n + 2,
+            };
+            Op::Copy { distance, length }
+        };
+        self.chunk_pos = chunk_pos;
+        Ok(op)
+    }
+}
+
+/// A decoded operation.
+enum Op {
+    Literal(u8),
+    Copy { distance: usize, length: usize },
+}
+
+/// Reads operations from the compressed data.
+trait Source {
+    fn next_op(&mut self) -> io::Result<Op>;
+}
+
+/// Output state shared by the decoders.
+struct DecodeState {
+    window: Box<[u8; WINDOW_SIZE]>,
+    /// Total bytes written to the window.
+    written: usize,
+    /// Bytes left to decode.
+    remaining: usize,
+    /// Pending back-reference.
+    copy_distance: usize,
+    copy_length: usize,
+}
+
+impl DecodeState {
+    fn new(size: u32) -> Self {
+        Self {
+            window: Box::new([0; WINDOW_SIZE]),
+            written: 0,
+            remaining: size as usize,
+            copy_distance: 0,
+            copy_length: 0,
+        }
+    }
+
+    fn read(&mut self, source: &mut impl Source, buf: &mut [u8]) -> io::Result<usize> {
+        let mut n = 0;
+        while n < buf.len() && self.remaining > 0 {
+            let byte = if self.copy_length > 0 {
+                self.copy_length -= 1;
+                self.window[(self.written - self.copy_distance) % WINDOW_SIZE]
+            } else {
+                match source.next_op()? {
+                    Op::Literal(byte) => byte,
+                    Op::Copy { distance, length } => {
+                        if distance > self.written {
+                            return Err(invalid_data("back-reference out of bounds"));
+                        }
+                        self.copy_distance = distance;
+                        self.copy_length = length;
+                        continue;
+                    }
+                }
+            };
+            self.window[self.written % WINDOW_SIZE] = byte;
+            self.written += 1;
+            self.remaining -= 1;
+            buf[n] = byte;
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+/// Compresses data with Yaz0.
+pub fn compress_yaz0(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_SIZE + data.len() + data.len() / 8 + 1);
+    out.extend_from_slice(&YAZ0_MAGIC);
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.resize(HEADER_SIZE, 0);
+    let mut flag_pos = 0;
+    for (idx, op) in Matcher::new(data).enumerate() {
+        if idx % 8 == 0 {
+            flag_pos = out.len();
+            out.push(0);
+        }
+        match op {
+            Op::Literal(byte) => {
+                out[flag_pos] |= 0x80 >> (idx % 8);
+                out.push(byte);
+            }
+            Op::Copy { distance, length } => {
+                let d = distance - 1;
+                if length >= 0x12 {
+                    out.extend_from_slice(&[(d >> 8) as u8, d as u8, (length - 0x12) as u8]);
+                } else {
+                    out.extend_from_slice(&[((length - 2) << 4 | d >> 8) as u8, d as u8]);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Compresses data with Yay0.
+pub fn compress_yay0(data: &[u8]) -> Vec<u8> {
+    let mut flags = Vec::<u32>::new();
+    let mut links = Vec::<u8>::new();
+    let mut chunks = Vec::<u8>::new();
+    for (idx, op) in Matcher::new(data).enumerate() {
+        if idx % 32 == 0 {
+            flags.push(0);
+        }
+        match op {
+            Op::Literal(byte) => {
+                *flags.last_mut().unwrap() |= 0x80000000 >> (idx % 32);
+                chunks.push(byte);
+            }
+            Op::Copy { distance, length } => {
+                let d = distance - 1;
+                if length >= 0x12 {
+                    links.extend_from_slice(&(d as u16).to_be_bytes());
+                    chunks.push((length - 0x12) as u8);
+                } else {
+                    links.extend_from_slice(&(((length - 2) << 12 | d) as u16).to_be_bytes());
+                }
+            }
+        }
+    }
+    let link_offset = HEADER_SIZE + flags.len() * 4;
+    let chunk_offset = link_offset + links.len();
+    let mut out = Vec::with_capacity(chunk_offset + chunks.len());
+    out.extend_from_slice(&YAY0_MAGIC);
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(&(link_offset as u32).to_be_bytes());
+    out.extend_from_slice(&(chunk_offset as u32).to_be_bytes());
+    out.extend(flags.iter().flat_map(|f| f.to_be_bytes()));
+    out.extend_from_slice(&links);
+    out.extend_from_slice(&chunks);
+    out
+}
+
+/// Greedy LZ77 match finder using hash chains over 3-byte prefixes.
+struct Matcher<'a> {
+    data: &'a [u8],
+    pos: usize,
+    head: Box<[usize]>,
+    prev: Box<[usize]>,
+}
+
+const HASH_BITS: u32 = 15;
+/// Maximum number of candidates checked per position.
+const MAX_CHAIN: usize = 256;
+
+impl<'a> Matcher<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            head: vec![usize::MAX; 1 << HASH_BITS].into_boxed_slice(),
+            prev: vec![usize::MAX; WINDOW_SIZE].into_boxed_slice(),
+        }
+    }
+
+    fn hash(&self, pos: usize) -> Option<usize> {
+        let bytes = self.data.get(pos..pos + MIN_MATCH)?;
+        let v = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+        Some((v.wrapping_mul(0x9E3779B1) >> (32 - HASH_BITS)) as usize)
+    }
+
+    fn insert(&mut self, pos: usize) {
+        if let Some(hash) = self.hash(pos) {
+            self.prev[pos % WINDOW_SIZE] = self.head[hash];
+            self.head[hash] = pos;
+        }
+    }
+
+    fn find_match(&self, pos: usize) -> (usize, usize) {
+        let Some(hash) = self.hash(pos) else {
+            return (0, 0);
+        };
+        let max_length = MAX_MATCH.min(self.data.len() - pos);
+        let (mut best_distance, mut best_length) = (0, 0);
+        let mut candidate = self.head[hash];
+        for _ in 0..MAX_CHAIN {
+            if candidate == usize::MAX || pos - candidate > WINDOW_SIZE {
+                break;
+            }
+            let length = self.data[candidate..]
+                .iter()
+                .zip(&self.data[pos..pos + max_length])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best_length {
+                (best_distance, best_length) = (pos - candidate, length);
+                if length == max_length {
+                    break;
+                }
+            }
+            let next = self.prev[candidate % WINDOW_SIZE];
+            if next >= candidate {
+                break;
+            }
+            candidate = next;
+        }
+        (best_distance, best_length)
+    }
+}
+
+impl Iterator for Matcher<'_> {
+    type Item = Op;
+
+    fn next(&mut self) -> Option<Op> {
+        let pos = self.pos;
+        let byte = *self.data.get(pos)?;
+        let (distance, length) = self.find_match(pos);
+        let length = if length >= MIN_MATCH { length } else { 1 };
+        for p in pos..pos + length {
+            self.insert(p);
+        }
+        self.pos += length;
+        Some(if length >= MIN_MATCH { Op::Copy { distance, length } } else { Op::Literal(byte) })
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error { io::Error::new(io::ErrorKind::InvalidData, msg) }
+
+fn unexpected_eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "Unexpected end of compressed data")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut data = b"abcabcabcabc-".repeat(64);
+        data.extend((0..2000u32).map(|i| (i * 7 % 251) as u8));
+        data.extend_from_slice(&[0; 0x300]);
+        for kind in [FileCompression::Yaz0, FileCompression::Yay0] {
+            let compressed = kind.compress(&data);
+            assert!(compressed.len() < data.len());
+            assert_eq!(FileCompression::detect(&compressed), Some((kind, data.len() as u32)));
+            assert_eq!(decompress(&compressed).unwrap().unwrap(), data);
+
+            // Small reads through the stream
+            let mut stream = DecompressedStream::new(Cursor::new(&compressed)).unwrap();
+            assert_eq!(stream.compression(), Some(kind));
+            let mut out = Vec::new();
+            let mut buf = [0u8; 7];
+            loop {
+                match stream.read(&mut buf).unwrap() {
+                    0 => break,
+                    n => out.extend_from_slice(&buf[..n]),
+                }
+            }
+            assert_eq!(out, data);
+
+            assert!(decompress(&compressed[..compressed.len() - 4]).is_err());
+        }
+        assert!(decompress(b"plain data").unwrap().is_none());
+        let mut stream = DecompressedStream::new(Cursor::new(b"plain")).unwrap();
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"plain");
+    }
+
+    #[test]
+    fn test_yaz0_decode() {
+        // "abcabcabcX" with a 6 byte back-reference at distance 3
+        let mut data = YAZ0_MAGIC.to_vec();
+        data.extend_from_slice(&[0, 0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&[0xE8, b'a', b'b', b'c', 0x40, 0x02, b'X']);
+        assert_eq!(decompress(&data).unwrap().unwrap(), b"abcabcabcX");
+
+        // Header size far larger than the data can expand to
+        data[4..8].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(decompress(&data).is_err());
+    }
+}
@@ -3,7 +3,6 @@ mod digest;
 mod redump;
 
 use std::{
-    borrow::Cow,
     cmp::min,
     env,
     error::Error,
@@ -11,8 +10,9 @@ use std::{
     fmt, fs,
     fs::File,
     io,
-    io::{BufWriter, Read, Write},
+    io::{BufWriter, Cursor, Read, Write},
     path::{Path, PathBuf, MAIN_SEPARATOR},
+    ptr,
     str::FromStr,
     sync::{mpsc::sync_channel, Arc},
     thread,
@@ -21,11 +21,14 @@ use std::{
 use argp::{FromArgValue, FromArgs};
 use digest::{digest_thread, DigestResult};
 use enable_ansi_support::enable_ansi_support;
-use indicatif::{ProgressBar, ProgressState, ProgressStyle};
-use itertools::Itertools;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle};
 use nod::{
-    Compression, Disc, DiscHeader, DiscMeta, Fst, Node, OpenOptions, PartitionBase, PartitionKind,
-    PartitionMeta, Result, ResultContext, SECTOR_SIZE,
+    decompress, system_menu_version, AccessRights, Banner, BannerKind, BannerLanguage, Bi2,
+    Compression, Disc, DiscHeader, DiscMeta, Format, Fst, FstEntry, FstMatch, FstTree, Glob,
+    ImetLanguage, KeyStore, KnownApploaders, Node, OpenOptions, PartitionBase, PartitionInfo,
+    PartitionKind, PartitionMeta, PartitionSignatures, PathRegex, Progress, ProgressHandler,
+    ProgressStage, Rarc, ReadStream, RegionInfo, Result, ResultContext, Tmd, TmdRegion, U8Archive,
+    Wad, WiiBanner, BANNER_HEIGHT, BANNER_WIDTH, SECTOR_SIZE, SYSTEM_MENU_TITLE_ID,
 };
 use size::{Base, Size};
 use supports_color::Stream;
@@ -59,8 +62,15 @@ struct TopLevel {
 enum SubCommand {
     Info(InfoArgs),
     Extract(ExtractArgs),
+    Find(FindArgs),
+    Ls(LsArgs),
+    Cat(CatArgs),
     Convert(ConvertArgs),
     Verify(VerifyArgs),
+    Patch(PatchArgs),
+    Banner(BannerArgs),
+    Updates(UpdatesArgs),
+    Wad(WadArgs),
 }
 
 #[derive(FromArgs, Debug)]
@@ -70,6 +80,12 @@ struct InfoArgs {
     #[argp(positional)]
     /// Path to disc image(s)
     file: Vec<PathBuf>,
+    #[argp(option, short = 'k')]
+    /// Keys file (e.g. with root_key to verify certificate chains)
+    keys: Option<PathBuf>,
+    #[argp(option, short = 'a')]
+    /// Known apploaders file, with a `sha1 retail|debug [name]` line per apploader
+    apploaders: Option<PathBuf>,
 }
 
 #[derive(FromArgs, Debug)]
@@ -92,6 +108,88 @@ struct ExtractArgs {
     /// Partition to extract (default: data)
     /// Options: all, data, update, channel, or a partition index
     partition: Option<String>,
+    #[argp(option, short = 'f')]
+    /// Only extract files matching a glob pattern (e.g. "**/*.szs")
+    /// Can be specified multiple times
+    filter: Vec<String>,
+    #[argp(option)]
+    /// Only extract files with paths matching a regular expression
+    regex: Option<String>,
+    #[argp(switch)]
+    /// Also convert the DOL to an ELF executable (sys/main.elf)
+    elf: bool,
+    #[argp(option)]
+    /// Only extract a file or directory, which may be inside U8 or RARC archives
+    /// (e.g. "Common.arc/layout", or "Common.arc/" for the archive's contents)
+    path: Option<String>,
+    #[argp(switch, short = 'd')]
+    /// Decompress Yaz0 and Yay0 files
+    decompress: bool,
+    #[argp(option, short = 'k')]
+    /// Keys file (e.g. with common keys or the NFS key)
+    keys: Option<PathBuf>,
+}
+
+#[derive(FromArgs, Debug)]
+/// Lists files in disc images matching a pattern.
+#[argp(subcommand, name = "find")]
+struct FindArgs {
+    #[argp(positional)]
+    /// Path to disc image(s)
+    file: Vec<PathBuf>,
+    #[argp(option, short = 'f')]
+    /// Glob pattern to match (e.g. "**/*.szs")
+    /// Can be specified multiple times
+    filter: Vec<String>,
+    #[argp(option)]
+    /// Regular expression to match against file paths
+    regex: Option<String>,
+    #[argp(option, short = 'p')]
+    /// Partition to search (default: data)
+    /// Options: all, data, update, channel, or a partition index
+    partition: Option<String>,
+}
+
+#[derive(FromArgs, Debug)]
+/// Lists a directory in a disc image, including the contents of U8 and RARC archives.
+#[argp(subcommand, name = "ls")]
+struct LsArgs {
+    #[argp(positional)]
+    /// Path to disc image
+    file: PathBuf,
+    #[argp(positional)]
+    /// File or directory to list (default: root)
+    /// Archives are browsed with a trailing slash or inner path (e.g. "Common.arc/layout")
+    path: Option<String>,
+    #[argp(option, short = 'p')]
+    /// Partition to list (default: data)
+    /// Options: all, data, update, channel, or a partition index
+    partition: Option<String>,
+    #[argp(switch, short = 'r')]
+    /// List subdirectories recursively
+    recursive: bool,
+}
+
+#[derive(FromArgs, Debug)]
+/// Writes a file from a disc image to standard output.
+#[argp(subcommand, name = "cat")]
+struct CatArgs {
+    #[argp(positional)]
+    /// Path to disc image
+    file: PathBuf,
+    #[argp(positional)]
+    /// File to write, which may be inside U8 or RARC archives (e.g. "Common.arc/layout/a.brlyt")
+    path: String,
+    #[argp(option, short = 'p')]
+    /// Partition to read from (default: data)
+    /// Options: data, update, channel, or a partition index
+    partition: Option<String>,
+    #[argp(switch, short = 'd')]
+    /// Decompress Yaz0 and Yay0 files
+    decompress: bool,
+    #[argp(option, short = 'o')]
+    /// Write to a file instead of standard output
+    output: Option<PathBuf>,
 }
 
 #[derive(FromArgs, Debug)]
@@ -107,6 +205,9 @@ struct ConvertArgs {
     #[argp(switch)]
     /// enable MD5 hashing (slower)
     md5: bool,
+    #[argp(option, short = 'k')]
+    /// keys file (e.g. with common keys or the NFS key)
+    keys: Option<PathBuf>,
 }
 
 #[derive(FromArgs, Debug)]
@@ -119,6 +220,96 @@ struct VerifyArgs {
     #[argp(switch)]
     /// enable MD5 hashing (slower)
     md5: bool,
+    #[argp(option, short = 'k')]
+    /// keys file (e.g. with common keys or the NFS key)
+    keys: Option<PathBuf>,
+}
+
+#[derive(FromArgs, Debug)]
+/// Edits the ticket and TMD of Wii disc images and fakesigns them.
+#[argp(subcommand, name = "patch")]
+struct PatchArgs {
+    #[argp(positional)]
+    /// Path to disc image (ISO only)
+    file: PathBuf,
+    #[argp(option, short = 'p')]
+    /// Partition to patch (default: data)
+    /// Options: all, data, update, channel, or a partition index
+    partition: Option<String>,
+    #[argp(option)]
+    /// Required IOS version (e.g. 36)
+    ios: Option<u32>,
+    #[argp(option)]
+    /// Region (jpn, usa, eur, kor, free)
+    region: Option<String>,
+    #[argp(option, from_str_fn(parse_hex_u32))]
+    /// Access rights flags (e.g. 0x3)
+    access_flags: Option<u32>,
+    #[argp(option, from_str_fn(parse_hex_u64))]
+    /// Title ID of the ticket and TMD (e.g. 0001000052534245)
+    title_id: Option<u64>,
+    #[argp(option)]
+    /// Common key index (0: normal, 1: Korean, 2: vWii)
+    common_key: Option<u8>,
+    #[argp(option, short = 'k')]
+    /// Keys file (e.g. with common keys to re-encrypt the title key)
+    keys: Option<PathBuf>,
+}
+
+#[derive(FromArgs, Debug)]
+/// Exports the banner image of GameCube disc images, or the channel icon of Wii disc
+/// images, as PNG and prints its strings.
+#[argp(subcommand, name = "banner")]
+struct BannerArgs {
+    #[argp(positional)]
+    /// Path to disc image
+    file: PathBuf,
+    #[argp(option, short = 'o')]
+    /// Output PNG path (default: disc image path with .png extension)
+    output: Option<PathBuf>,
+}
+
+#[derive(FromArgs, Debug)]
+/// Lists the system titles (IOS, System Menu, etc.) in the update partition of Wii disc
+/// images.
+#[argp(subcommand, name = "updates")]
+struct UpdatesArgs {
+    #[argp(positional)]
+    /// Path to disc image(s)
+    file: Vec<PathBuf>,
+    #[argp(switch)]
+    /// Decrypt all contents and check their hashes against the TMD
+    verify: bool,
+    #[argp(option, short = 'o')]
+    /// Write decrypted contents to a directory (<title ID>/<content ID>.app)
+    output: Option<PathBuf>,
+}
+
+#[derive(FromArgs, Debug)]
+/// Converts a channel partition of a Wii disc image to an installable WAD.
+#[argp(subcommand, name = "wad")]
+struct WadArgs {
+    #[argp(positional)]
+    /// Path to disc image
+    file: PathBuf,
+    #[argp(option, short = 'p')]
+    /// Partition containing the channel (default: channel)
+    /// Options: data, update, channel, or a partition index
+    partition: Option<String>,
+    #[argp(option, short = 'o')]
+    /// Output WAD path (default: disc image path with .wad extension)
+    output: Option<PathBuf>,
+    #[argp(option, short = 'k')]
+    /// Keys file (e.g. with common keys to decrypt the title key)
+    keys: Option<PathBuf>,
+}
+
+fn parse_hex_u32(s: &str) -> Result<u32, String> {
+    u32::from_str_radix(s.trim_start_matches("0x"), 16).map_err(|e| e.to_string())
+}
+
+fn parse_hex_u64(s: &str) -> Result<u64, String> {
+    u64::from_str_radix(s.trim_start_matches("0x"), 16).map_err(|e| e.to_string())
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -217,7 +408,14 @@ fn main() {
         SubCommand::Info(c_args) => info(c_args),
         SubCommand::Convert(c_args) => convert(c_args),
         SubCommand::Extract(c_args) => extract(c_args),
+        SubCommand::Find(c_args) => find(c_args),
+        SubCommand::Ls(c_args) => ls(c_args),
+        SubCommand::Cat(c_args) => cat(c_args),
         SubCommand::Verify(c_args) => verify(c_args),
+        SubCommand::Patch(c_args) => patch(c_args),
+        SubCommand::Banner(c_args) => banner(c_args),
+        SubCommand::Updates(c_args) => updates(c_args),
+        SubCommand::Wad(c_args) => wad(c_args),
     });
     if let Err(e) = result {
         eprintln!("Failed: {}", e);
@@ -256,18 +454,34 @@ fn print_header(header: &DiscHeader, meta: &DiscMeta) {
     }
 }
 
+fn load_keys(path: Option<&Path>) -> Result<Arc<KeyStore>> {
+    let mut keys = KeyStore::default();
+    if let Some(path) = path {
+        keys.load_file(path)?;
+    }
+    Ok(Arc::new(keys))
+}
+
 fn info(args: InfoArgs) -> Result<()> {
+    let keys = load_keys(args.keys.as_deref())?;
+    let mut apploaders = KnownApploaders::default();
+    if let Some(path) = &args.apploaders {
+        apploaders.load_file(path)?;
+    }
     for file in &args.file {
-        info_file(file)?;
+        info_file(file, &keys, &apploaders)?;
     }
     Ok(())
 }
 
-fn info_file(path: &Path) -> Result<()> {
+fn info_file(path: &Path, keys: &Arc<KeyStore>, apploaders: &KnownApploaders) -> Result<()> {
     log::info!("Loading {}", display(path));
     let disc = Disc::new_with_options(path, &OpenOptions {
         rebuild_encryption: false,
         validate_hashes: false,
+        read_ahead: false,
+        progress: None,
+        keys: Some(keys.clone()),
     })?;
     let header = disc.header();
     let meta = disc.meta();
@@ -306,22 +520,10 @@ fn info_file(path: &Path) -> Result<()> {
 
             let mut partition = disc.open_partition(idx)?;
             let meta = partition.meta()?;
-            let tmd = meta.tmd_header();
-            let title_id_str = if let Some(tmd) = tmd {
-                format!(
-                    "{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
-                    tmd.title_id[0],
-                    tmd.title_id[1],
-                    tmd.title_id[2],
-                    tmd.title_id[3],
-                    tmd.title_id[4],
-                    tmd.title_id[5],
-                    tmd.title_id[6],
-                    tmd.title_id[7]
-                )
-            } else {
-                "N/A".to_string()
-            };
+            let tmd = meta.tmd()?;
+            let title_id_str = tmd
+                .as_ref()
+                .map_or_else(|| "N/A".to_string(), |tmd| format!("{:016x}", tmd.title_id()));
             println!("\tTitle: {}", info.disc_header.game_title_str());
             println!("\tGame ID: {} ({})", info.disc_header.game_id_str(), title_id_str);
             println!(
@@ -329,9 +531,25 @@ fn info_file(path: &Path) -> Result<()> {
                 info.disc_header.disc_num + 1,
                 info.disc_header.disc_version
             );
+            print_region(&meta.region_info()?, "\t");
+            if let Some(tmd) = &tmd {
+                print_tmd(tmd, meta.verify_tmd_content()?);
+            }
+            if let Some(signatures) = meta.verify_signatures(keys)? {
+                print_signatures(&signatures);
+            }
+            print_bi2(meta.bi2(), true, "\t");
+            print_apploader(&meta, apploaders, "\t")?;
+            print_fst_issues(partition.as_ref(), &meta, "\t")?;
         }
     } else if header.is_gamecube() {
-        // TODO
+        let mut partition = disc.open_partition(0)?;
+        let meta = partition.meta()?;
+        println!();
+        print_region(&meta.region_info()?, "");
+        print_bi2(meta.bi2(), false, "");
+        print_apploader(&meta, apploaders, "")?;
+        print_fst_issues(partition.as_ref(), &meta, "")?;
     } else {
         println!(
             "Invalid GC/Wii magic: {:#010X}/{:#010X}",
@@ -343,24 +561,525 @@ fn info_file(path: &Path) -> Result<()> {
     Ok(())
 }
 
+fn print_tmd(tmd: &Tmd, content_valid: Option<bool>) {
+    let ios_id = tmd.ios_title_id();
+    match tmd.ios_version() {
+        Some(ios) => println!("\tIOS: IOS{} ({:016x})", ios, ios_id),
+        None => println!("\tIOS: {:016x}", ios_id),
+    }
+    println!("\tTitle version: {}", tmd.title_version());
+    println!("\tTMD region: {}", tmd.region());
+    let ratings = tmd.ratings().iter().map(|(b, age)| format!("{} {}", b, age)).collect::<Vec<_>>();
+    println!(
+        "\tRatings: {}",
+        if ratings.is_empty() { "None".to_string() } else { ratings.join(", ") }
+    );
+    println!("\tAccess rights: {}", tmd.access_rights());
+    let h3_content = tmd.h3_content();
+    for content in tmd.contents {
+        println!(
+            "\tContent {}: ID {:08x}, type {:#06X}, size {:#X}, SHA-1 {:x}{}",
+            content.index.get(),
+            content.content_id.get(),
+            content.content_type.get(),
+            content.size(),
+            base16ct::HexDisplay(&content.hash),
+            match content_valid {
+                Some(true) if h3_content.is_some_and(|c| ptr::eq(c, content)) => " (OK)",
+                Some(false) if h3_content.is_some_and(|c| ptr::eq(c, content)) => " (INVALID)",
+                _ => "",
+            }
+        );
+    }
+}
+
+fn print_region(info: &RegionInfo, indent: &str) {
+    let region = info.region().map_or("Unknown".to_string(), |r| r.to_string());
+    let conflicts = info.conflicts();
+    if conflicts.is_empty() {
+        println!("{}Region: {}", indent, region);
+    } else {
+        let conflicts =
+            conflicts.iter().map(|(s, r)| format!("{}: {}", s, r)).collect::<Vec<_>>().join(", ");
+        println!("{}Region: {} (conflicts with {})", indent, region, conflicts);
+    }
+}
+
+fn print_signatures(signatures: &PartitionSignatures) {
+    let summary = if signatures.is_valid() {
+        "Valid"
+    } else if signatures.is_fakesigned() {
+        "Fakesigned"
+    } else if signatures.is_unverified() {
+        "Unverified (missing issuer key)"
+    } else {
+        "Invalid"
+    };
+    println!("\tSignatures: {}", summary);
+    println!("\t\tTicket: {}", signatures.ticket);
+    println!("\t\tTMD: {}", signatures.tmd);
+    for (name, status) in &signatures.certs {
+        println!("\t\t{}: {}", name, status);
+    }
+}
+
+fn print_bi2(bi2: &Bi2, is_wii: bool, indent: &str) {
+    println!("{}bi2.bin:", indent);
+    println!("{}\tCountry code: {}", indent, bi2.country_code());
+    println!("{}\tTotal discs: {}", indent, bi2.total_discs());
+    println!("{}\tDebug flag: {}", indent, bi2.debug_flag());
+    println!("{}\tDebug monitor size: {:#X}", indent, bi2.debug_monitor_size());
+    println!("{}\tSimulated memory size: {:#X}", indent, bi2.simulated_memory_size());
+    println!("{}\tArgument offset: {:#X}", indent, bi2.argument_offset());
+    println!(
+        "{}\tTrack location / size: {:#X} / {:#X}",
+        indent,
+        bi2.track_location(),
+        bi2.track_size()
+    );
+    println!("{}\tLong file names: {}", indent, bi2.long_file_names());
+    println!("{}\tPad spec: {}", indent, bi2.pad_spec());
+    println!("{}\tDOL limit: {:#X}", indent, bi2.dol_limit());
+    if is_wii {
+        println!("{}\tDual-layer value: {:#X}", indent, bi2.dual_layer_value());
+    }
+}
+
+fn print_apploader(meta: &PartitionMeta, known: &KnownApploaders, indent: &str) -> Result<()> {
+    let apploader = meta.apploader()?;
+    let date = match (apploader.date(), apploader.date_str()) {
+        (Some(date), _) => date.to_string(),
+        (None, Some(s)) => format!("{:?} (invalid)", s),
+        (None, None) => "N/A".to_string(),
+    };
+    println!("{}Apploader: {}", indent, date);
+    println!(
+        "{}\tEntry point: {:#010X}{}",
+        indent,
+        apploader.entry_point(),
+        if apploader.entry_point_in_code() { "" } else { " (outside code)" }
+    );
+    println!(
+        "{}\tCode / trailer size: {:#X} / {:#X}",
+        indent,
+        apploader.code.len(),
+        apploader.trailer.len()
+    );
+    println!("{}\tSHA-1: {}", indent, base16ct::HexDisplay(&apploader.hash()));
+    match known.identify(&apploader) {
+        Some(known) if known.name.is_empty() => println!("{}\tVariant: {}", indent, known.variant),
+        Some(known) => println!("{}\tVariant: {} ({})", indent, known.variant, known.name),
+        None => println!("{}\tVariant: Unknown", indent),
+    }
+    Ok(())
+}
+
+fn print_fst_issues(
+    partition: &dyn PartitionBase,
+    meta: &PartitionMeta,
+    indent: &str,
+) -> Result<()> {
+    let issues = meta.fst()?.validate(&meta.layout(partition.data_size()));
+    if issues.is_empty() {
+        println!("{}FST: OK", indent);
+    } else {
+        println!("{}FST: {} issue(s)", indent, issues.len());
+        for issue in issues {
+            println!("{}\t{}", indent, issue);
+        }
+    }
+    Ok(())
+}
+
+/// Displays a progress bar while Wii partition hashes are rebuilt.
+struct HashProgress {
+    pb: ProgressBar,
+}
+
+impl HashProgress {
+    fn new() -> Arc<Self> {
+        let pb = ProgressBar::hidden();
+        pb.set_style(
+            ProgressStyle::with_template(
+                "{spinner:.green} [{elapsed_precise}] {msg} [{wide_bar:.cyan/blue}] {pos}/{len} groups",
+            )
+            .unwrap()
+            .progress_chars("#>-"),
+        );
+        pb.set_message("Rebuilding hashes");
+        Arc::new(Self { pb })
+    }
+
+    fn finish(&self) { self.pb.finish_and_clear(); }
+}
+
+impl ProgressHandler for HashProgress {
+    fn on_progress(&self, progress: Progress) {
+        if progress.stage != ProgressStage::RebuildHashes {
+            return;
+        }
+        if self.pb.is_hidden() && !self.pb.is_finished() {
+            self.pb.set_draw_target(ProgressDrawTarget::stderr());
+        }
+        self.pb.set_length(progress.total);
+        self.pb.set_position(progress.processed);
+    }
+}
+
 fn convert(args: ConvertArgs) -> Result<()> {
-    convert_and_verify(&args.file, Some(&args.out), args.md5)
+    let keys = load_keys(args.keys.as_deref())?;
+    convert_and_verify(&args.file, Some(&args.out), args.md5, &keys)
 }
 
 fn verify(args: VerifyArgs) -> Result<()> {
+    let keys = load_keys(args.keys.as_deref())?;
     for file in &args.file {
-        convert_and_verify(file, None, args.md5)?;
+        convert_and_verify(file, None, args.md5, &keys)?;
         println!();
     }
     Ok(())
 }
 
-fn convert_and_verify(in_file: &Path, out_file: Option<&Path>, md5: bool) -> Result<()> {
+fn banner(args: BannerArgs) -> Result<()> {
+    let disc = Disc::new(&args.file)?;
+    if disc.header().is_wii() {
+        return wii_banner(&disc, &args);
+    }
+    let mut partition = disc.open_partition(0)?;
+    let meta = partition.meta()?;
+    let banner = Banner::read(partition.as_mut(), &meta)?.ok_or("opening.bnr not found")?;
+
+    println!("Banner: {}", banner.kind);
+    for (idx, text) in banner.texts.iter().enumerate() {
+        match banner.kind {
+            BannerKind::Bnr1 => println!("Strings:"),
+            BannerKind::Bnr2 => println!("Strings ({}):", BannerLanguage::ALL[idx]),
+        }
+        println!("\tName: {} / {}", text.short_name, text.long_name);
+        println!("\tMaker: {} / {}", text.short_maker, text.long_maker);
+        println!("\tDescription: {}", text.description.replace('\n', " "));
+    }
+
+    let output = args.output.unwrap_or_else(|| args.file.with_extension("png"));
+    write_png(&output, BANNER_WIDTH as u32, BANNER_HEIGHT as u32, &banner.to_rgba8())?;
+    println!("Wrote {}", display(&output));
+    Ok(())
+}
+
+fn wii_banner(disc: &Disc, args: &BannerArgs) -> Result<()> {
+    let mut partition = disc.open_partition_kind(PartitionKind::Data)?;
+    let meta = partition.meta()?;
+    let banner = WiiBanner::read(partition.as_mut(), &meta)?.ok_or("opening.bnr not found")?;
+
+    println!("Names:");
+    for language in ImetLanguage::ALL {
+        let name = banner.name(language);
+        if !name.is_empty() {
+            println!("\t{}: {}", language, name.replace('\n', " / "));
+        }
+    }
+    let textures = banner.icon.textures()?;
+    for (path, tpl) in &textures {
+        for image in &tpl.images {
+            println!("Icon texture: {} ({}x{} {})", path, image.width, image.height, image.format);
+        }
+    }
+    let image =
+        textures.iter().find_map(|(_, tpl)| tpl.images.first()).ok_or("No icon textures found")?;
+
+    let output = args.output.clone().unwrap_or_else(|| args.file.with_extension("png"));
+    write_png(&output, image.width as u32, image.height as u32, &image.to_rgba8())?;
+    println!("Wrote {}", display(&output));
+    Ok(())
+}
+
+fn updates(args: UpdatesArgs) -> Result<()> {
+    for file in &args.file {
+        updates_file(file, &args)?;
+    }
+    Ok(())
+}
+
+fn updates_file(path: &Path, args: &UpdatesArgs) -> Result<()> {
+    let disc = Disc::new(path)?;
+    println!("{} ({}):", display(path), disc.header().game_id_str());
+    if !disc.header().is_wii() {
+        println!("\tNot a Wii disc");
+        return Ok(());
+    }
+    let required_ios = disc
+        .open_partition_kind(PartitionKind::Data)?
+        .meta()?
+        .tmd()?
+        .and_then(|tmd| tmd.ios_version());
+    if !disc.partitions().iter().any(|info| info.kind == PartitionKind::Update) {
+        println!("\tNo update partition");
+        return Ok(());
+    }
+    let mut partition = disc.open_partition_kind(PartitionKind::Update)?;
+    let meta = partition.meta()?;
+    let fst = Fst::new(&meta.raw_fst)?;
+    let mut titles = Vec::new();
+    for (idx, node, _) in fst.iter() {
+        if !node.is_file() {
+            continue;
+        }
+        let file_path = fst.path_of(idx)?;
+        let mut stream =
+            partition.open_file(node).with_context(|| format!("Opening {}", file_path))?;
+        let mut magic = [0u8; 8];
+        if stream.read_exact(&mut magic).is_err() || !Wad::is_wad(&magic) {
+            continue;
+        }
+        let wad = Wad::new(&mut stream)
+            .map_err(|e| nod::Error::Other(format!("{}: {}", file_path, e)))?;
+        let status = if args.verify || args.output.is_some() {
+            Some(check_wad(&wad, &mut stream, args.output.as_deref())?)
+        } else {
+            None
+        };
+        titles.push((wad, status));
+    }
+    titles.sort_by_key(|(wad, _)| wad.tmd().title_id());
+
+    println!("\t{:<16}  {:>7}  {:>8}  Name", "Title ID", "Version", "Contents");
+    for (wad, status) in &titles {
+        let tmd = wad.tmd();
+        println!(
+            "\t{:016x}  {:>7}  {:>8}  {}{}",
+            tmd.title_id(),
+            format!("v{}", tmd.title_version()),
+            tmd.contents.len(),
+            title_name(tmd.title_id(), tmd.title_version()),
+            match status {
+                Some(0) => " (OK)".to_string(),
+                Some(invalid) => format!(" ({} INVALID)", invalid),
+                None => String::new(),
+            }
+        );
+    }
+
+    let find = |title_id: u64| {
+        titles.iter().map(|(wad, _)| wad.tmd()).find(|tmd| tmd.title_id() == title_id)
+    };
+    match find(SYSTEM_MENU_TITLE_ID) {
+        Some(tmd) => println!(
+            "\tSystem Menu: {} (v{})",
+            system_menu_version(tmd.title_version()).unwrap_or("unknown"),
+            tmd.title_version()
+        ),
+        None => println!("\tSystem Menu: not included"),
+    }
+    if let Some(ios) = required_ios {
+        match find(0x0000000100000000 | ios as u64) {
+            Some(tmd) => println!("\tRequired IOS: IOS{} (v{} included)", ios, tmd.title_version()),
+            None => println!("\tRequired IOS: IOS{} (not included)", ios),
+        }
+    }
+    Ok(())
+}
+
+fn wad(args: WadArgs) -> Result<()> {
+    let keys = load_keys(args.keys.as_deref())?;
+    let disc = Disc::new_with_options(&args.file, &OpenOptions {
+        keys: Some(keys.clone()),
+        ..Default::default()
+    })?;
+    if !disc.header().is_wii() {
+        return Err(nod::Error::Other("Not a Wii disc".to_string()));
+    }
+    let mut partitions =
+        open_partitions(&disc, Some(args.partition.as_deref().unwrap_or("channel")))?;
+    if partitions.len() != 1 {
+        return Err(nod::Error::Other("Select a single partition".to_string()));
+    }
+    let (_, mut partition, _) = partitions.remove(0);
+
+    // Build the WAD in memory, so a failed conversion doesn't leave a partial file behind
+    let mut data = Vec::new();
+    nod::write_wad_with(&mut data, partition.as_mut(), &keys)?;
+    let wad = Wad::new(&mut Cursor::new(&data))?;
+    println!("Title ID: {:016x}", wad.tmd().title_id());
+    println!("Title version: {}", wad.tmd().title_version());
+
+    let output = args.output.unwrap_or_else(|| args.file.with_extension("wad"));
+    fs::write(&output, &data).with_context(|| format!("Writing file {}", display(&output)))?;
+    println!("Wrote {}", display(&output));
+    Ok(())
+}
+
+/// Decrypts each content of a WAD, optionally writing it to `out_dir`, and returns the number
+/// of contents that don't match their TMD hash.
+fn check_wad(wad: &Wad, stream: &mut dyn ReadStream, out_dir: Option<&Path>) -> Result<usize> {
+    let tmd = wad.tmd();
+    let title_key = wad.title_key()?;
+    let title_dir = out_dir.map(|dir| dir.join(format!("{:016x}", tmd.title_id())));
+    if let Some(dir) = &title_dir {
+        fs::create_dir_all(dir).with_context(|| format!("Creating directory {}", display(dir)))?;
+    }
+    let mut invalid = 0;
+    for (index, content) in tmd.contents.iter().enumerate() {
+        let data = wad.read_content(stream, index, &title_key).with_context(|| {
+            format!(
+                "Reading title {:016x} content {:08x}",
+                tmd.title_id(),
+                content.content_id.get()
+            )
+        })?;
+        if !content.verify(&data) {
+            invalid += 1;
+        }
+        if let Some(dir) = &title_dir {
+            let out_path = dir.join(format!("{:08x}.app", content.content_id.get()));
+            fs::write(&out_path, &data)
+                .with_context(|| format!("Writing file {}", display(&out_path)))?;
+        }
+    }
+    Ok(invalid)
+}
+
+/// A readable name for a title, e.g. "IOS36" or "System Menu 4.3U".
+fn title_name(title_id: u64, version: u16) -> String {
+    match title_id {
+        0x0000000100000001 => "boot2".to_string(),
+        SYSTEM_MENU_TITLE_ID => match system_menu_version(version) {
+            Some(name) => format!("System Menu {}", name),
+            None => "System Menu".to_string(),
+        },
+        0x0000000100000100 => "BC".to_string(),
+        0x0000000100000101 => "MIOS".to_string(),
+        id if id >> 32 == 1 && (3..256).contains(&(id as u32)) => format!("IOS{}", id as u32),
+        id => {
+            let code = (id as u32).to_be_bytes();
+            if code.iter().all(u8::is_ascii_alphanumeric) {
+                String::from_utf8_lossy(&code).into_owned()
+            } else {
+                String::new()
+            }
+        }
+    }
+}
+
+fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<()> {
+    let file = BufWriter::new(
+        File::create(path).with_context(|| format!("Creating file {}", display(path)))?,
+    );
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(rgba))
+        .map_err(|e| nod::Error::Other(format!("Writing PNG {}: {}", display(path), e)))
+}
+
+fn patch(args: PatchArgs) -> Result<()> {
+    let region = args
+        .region
+        .as_deref()
+        .map(|s| match s.to_ascii_lowercase().as_str() {
+            "jpn" => Ok(TmdRegion::Japan),
+            "usa" => Ok(TmdRegion::Usa),
+            "eur" => Ok(TmdRegion::Europe),
+            "kor" => Ok(TmdRegion::Korea),
+            "free" => Ok(TmdRegion::RegionFree),
+            _ => Err(nod::Error::Other(format!("Invalid region {:?}", s))),
+        })
+        .transpose()?;
+
+    let keys = load_keys(args.keys.as_deref())?;
+    let disc = Disc::new_with_options(&args.file, &OpenOptions {
+        keys: Some(keys.clone()),
+        ..Default::default()
+    })?;
+    if disc.meta().format != Format::Iso {
+        return Err(nod::Error::Other(format!(
+            "Patching {} images is not supported, convert to ISO first",
+            disc.meta().format
+        )));
+    }
+    if !disc.header().is_wii() {
+        return Err(nod::Error::Other("Not a Wii disc".to_string()));
+    }
+    let infos = match args.partition.as_deref() {
+        Some(p) if p.eq_ignore_ascii_case("all") => disc.partitions().iter().collect(),
+        p => {
+            let kind = match p {
+                None => Some(PartitionKind::Data),
+                Some(p) if p.eq_ignore_ascii_case("data") => Some(PartitionKind::Data),
+                Some(p) if p.eq_ignore_ascii_case("update") => Some(PartitionKind::Update),
+                Some(p) if p.eq_ignore_ascii_case("channel") => Some(PartitionKind::Channel),
+                Some(_) => None,
+            };
+            let info = match (kind, p) {
+                (Some(kind), _) => disc.partitions().iter().find(|info| info.kind == kind),
+                (None, Some(p)) => {
+                    let idx = p.parse::<usize>().map_err(|_| "Invalid partition index")?;
+                    disc.partitions().get(idx)
+                }
+                (None, None) => None,
+            };
+            vec![info.ok_or("Partition not found")?]
+        }
+    };
+
+    let mut patched = Vec::with_capacity(infos.len());
+    for info in infos {
+        let mut meta = disc.open_partition(info.index)?.meta()?;
+        if let Some(tmd) = meta.tmd_header_mut() {
+            if let Some(ios) = args.ios {
+                tmd.set_ios_version(ios);
+            }
+            if let Some(region) = region {
+                tmd.set_region(region);
+            }
+            if let Some(access_flags) = args.access_flags {
+                tmd.set_access_rights(AccessRights(access_flags));
+            }
+            if let Some(title_id) = args.title_id {
+                tmd.set_title_id(title_id);
+            }
+        }
+        if let Some(ticket) = meta.ticket_mut() {
+            if let Some(title_id) = args.title_id {
+                ticket.set_title_id_with(title_id, &keys)?;
+            }
+            if let Some(common_key) = args.common_key {
+                ticket.set_common_key_idx_with(common_key, &keys)?;
+            }
+        }
+        meta.fakesign()?;
+        patched.push((info.clone(), meta));
+    }
+    drop(disc);
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(&args.file)
+        .with_context(|| format!("Opening {} for writing", display(&args.file)))?;
+    for (info, meta) in patched {
+        nod::write_partition_header(&mut file, &info, &meta)?;
+        println!("Patched partition {} ({})", info.index, info.kind);
+    }
+    Ok(())
+}
+
+fn convert_and_verify(
+    in_file: &Path,
+    out_file: Option<&Path>,
+    md5: bool,
+    keys: &Arc<KeyStore>,
+) -> Result<()> {
     println!("Loading {}", display(in_file));
+    let hash_progress = HashProgress::new();
     let mut disc = Disc::new_with_options(in_file, &OpenOptions {
         rebuild_encryption: true,
         validate_hashes: false,
+        read_ahead: true,
+        progress: Some(hash_progress.clone()),
+        keys: Some(keys.clone()),
     })?;
+    hash_progress.finish();
     let header = disc.header();
     let meta = disc.meta();
     print_header(header, &meta);
@@ -539,50 +1258,320 @@ fn extract(args: ExtractArgs) -> Result<()> {
     } else {
         output_dir = args.file.with_extension("");
     }
+    let keys = load_keys(args.keys.as_deref())?;
+    let hash_progress = HashProgress::new();
     let disc = Disc::new_with_options(&args.file, &OpenOptions {
         rebuild_encryption: false,
         validate_hashes: args.validate,
+        read_ahead: false,
+        progress: Some(hash_progress.clone()),
+        keys: Some(keys),
     })?;
+    hash_progress.finish();
     let header = disc.header();
     let is_wii = header.is_wii();
-    if let Some(partition) = args.partition {
-        if partition.eq_ignore_ascii_case("all") {
-            for info in disc.partitions() {
-                let mut out_dir = output_dir.clone();
-                out_dir.push(info.kind.dir_name().as_ref());
-                let mut partition = disc.open_partition(info.index)?;
-                extract_partition(header, partition.as_mut(), &out_dir, is_wii, args.quiet)?;
+    let options = ExtractOptions {
+        filter: FileFilter::new(&args.filter, args.regex.as_deref())?,
+        elf: args.elf,
+        quiet: args.quiet,
+        decompress: args.decompress,
+    };
+    for (kind, mut partition, _) in open_partitions(&disc, args.partition.as_deref())? {
+        let mut out_dir = output_dir.clone();
+        if let Some(kind) = kind {
+            out_dir.push(kind.dir_name().as_ref());
+        }
+        if let Some(path) = args.path.as_deref() {
+            let meta = partition.meta()?;
+            let resolved = ResolvedPath::new(partition.as_mut(), &meta, path, is_wii)?;
+            extract_resolved(&resolved, partition.as_mut(), &out_dir, is_wii, &options)?;
+            continue;
+        }
+        extract_partition(header, partition.as_mut(), &out_dir, is_wii, &options)?;
+    }
+    Ok(())
+}
+
+/// Options shared by the extraction functions.
+struct ExtractOptions {
+    filter: FileFilter,
+    elf: bool,
+    quiet: bool,
+    decompress: bool,
+}
+
+/// A partition selected by a `--partition` option. The kind is only set when all partitions
+/// are selected, and the partition info is only available for Wii discs.
+type SelectedPartition<'a> =
+    (Option<PartitionKind>, Box<dyn PartitionBase>, Option<&'a PartitionInfo>);
+
+/// Opens the partitions selected by a `--partition` option.
+fn open_partitions<'a>(
+    disc: &'a Disc,
+    partition: Option<&str>,
+) -> Result<Vec<SelectedPartition<'a>>> {
+    let kind = match partition {
+        None => PartitionKind::Data,
+        Some(p) if p.eq_ignore_ascii_case("all") => {
+            return disc
+                .partitions()
+                .iter()
+                .map(|info| Ok((Some(info.kind), disc.open_partition(info.index)?, Some(info))))
+                .collect();
+        }
+        Some(p) if p.eq_ignore_ascii_case("data") => PartitionKind::Data,
+        Some(p) if p.eq_ignore_ascii_case("update") => PartitionKind::Update,
+        Some(p) if p.eq_ignore_ascii_case("channel") => PartitionKind::Channel,
+        Some(p) => {
+            let idx = p.parse::<usize>().map_err(|_| "Invalid partition index")?;
+            let info = disc.partitions().iter().find(|info| info.index == idx);
+            return Ok(vec![(None, disc.open_partition(idx)?, info)]);
+        }
+    };
+    let info = disc.partitions().iter().find(|info| info.kind == kind);
+    Ok(vec![(None, disc.open_partition_kind(kind)?, info)])
+}
+
+/// Selects files by glob patterns and / or a regular expression.
+struct FileFilter {
+    globs: Vec<Glob>,
+    regex: Option<PathRegex>,
+}
+
+impl FileFilter {
+    fn new(globs: &[String], regex: Option<&str>) -> Result<Self> {
+        let globs = globs.iter().map(|s| Glob::new(s)).collect::<Result<Vec<_>, String>>()?;
+        let regex = regex
+            .map(PathRegex::new)
+            .transpose()
+            .map_err(|e| nod::Error::Other(format!("Invalid regular expression: {}", e)))?;
+        Ok(Self { globs, regex })
+    }
+
+    fn is_empty(&self) -> bool { self.globs.is_empty() && self.regex.is_none() }
+
+    /// Whether a path matches any of the glob patterns.
+    fn is_glob_match(&self, path: &str) -> bool {
+        self.globs.is_empty() || self.globs.iter().any(|glob| glob.is_match(path))
+    }
+
+    /// Whether a path matches any of the glob patterns and the regular expression.
+    fn is_match(&self, path: &str) -> bool {
+        self.is_glob_match(path) && self.regex.as_ref().map_or(true, |regex| regex.is_match(path))
+    }
+}
+
+fn find(args: FindArgs) -> Result<()> {
+    let filter = FileFilter::new(&args.filter, args.regex.as_deref())?;
+    for file in &args.file {
+        let disc = Disc::new(file)?;
+        let is_wii = disc.header().is_wii();
+        if args.file.len() > 1 {
+            println!("{}:", display(file));
+        }
+        for (kind, mut partition, info) in open_partitions(&disc, args.partition.as_deref())? {
+            let meta = partition.meta()?;
+            let tree = Fst::new(&meta.raw_fst)?.tree()?;
+            let prefix = kind.map(|kind| format!("{}/", kind.dir_name())).unwrap_or_default();
+            let (mut count, mut total) = (0usize, 0u64);
+            let matches: Box<dyn Iterator<Item = FstMatch>> = match &filter.regex {
+                Some(regex) => Box::new(tree.regex(is_wii, regex)),
+                None => Box::new(tree.search(is_wii, |_| true)),
+            };
+            for m in matches.filter(|m| m.entry.node.is_file() && filter.is_glob_match(m.path())) {
+                println!("{:#012X} {:>12} {}{}", m.disc_offset(info), m.size, prefix, m.path());
+                count += 1;
+                total += m.size;
             }
-        } else if partition.eq_ignore_ascii_case("data") {
-            let mut partition = disc.open_partition_kind(PartitionKind::Data)?;
-            extract_partition(header, partition.as_mut(), &output_dir, is_wii, args.quiet)?;
-        } else if partition.eq_ignore_ascii_case("update") {
-            let mut partition = disc.open_partition_kind(PartitionKind::Update)?;
-            extract_partition(header, partition.as_mut(), &output_dir, is_wii, args.quiet)?;
-        } else if partition.eq_ignore_ascii_case("channel") {
-            let mut partition = disc.open_partition_kind(PartitionKind::Channel)?;
-            extract_partition(header, partition.as_mut(), &output_dir, is_wii, args.quiet)?;
-        } else {
-            let idx = partition.parse::<usize>().map_err(|_| "Invalid partition index")?;
-            let mut partition = disc.open_partition(idx)?;
-            extract_partition(header, partition.as_mut(), &output_dir, is_wii, args.quiet)?;
+            println!(
+                "{}{} files, {}",
+                prefix,
+                count,
+                Size::from_bytes(total).format().with_base(Base::Base10)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn ls(args: LsArgs) -> Result<()> {
+    let disc = Disc::new(&args.file)?;
+    let is_wii = disc.header().is_wii();
+    let path = args.path.as_deref().unwrap_or_default();
+    for (kind, mut partition, _) in open_partitions(&disc, args.partition.as_deref())? {
+        let meta = partition.meta()?;
+        let resolved = ResolvedPath::new(partition.as_mut(), &meta, path, is_wii)?;
+        if let Some(kind) = kind {
+            println!("{}:", kind.dir_name());
         }
+        for entry in resolved.entries(args.recursive) {
+            let path = resolved.relative_path(entry);
+            if entry.node.is_dir() {
+                println!("{:>12} {}/", "", path);
+            } else {
+                println!("{:>12} {}", entry.node.length(), path);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cat(args: CatArgs) -> Result<()> {
+    let disc = Disc::new(&args.file)?;
+    let is_wii = disc.header().is_wii();
+    let mut partitions = open_partitions(&disc, args.partition.as_deref())?;
+    if partitions.len() != 1 {
+        return Err(nod::Error::Other("Select a single partition".to_string()));
+    }
+    let (_, mut partition, _) = partitions.remove(0);
+    let meta = partition.meta()?;
+    let resolved = ResolvedPath::new(partition.as_mut(), &meta, &args.path, is_wii)?;
+    let node = &resolved.entry().node;
+    if !node.is_file() {
+        return Err(nod::Error::Other(format!("{}: not a file", args.path)));
+    }
+    let mut data = resolved
+        .read_file(partition.as_mut(), node)
+        .with_context(|| format!("Reading {}", args.path))?;
+    if args.decompress {
+        if let Some(decompressed) =
+            decompress(&data).with_context(|| format!("Decompressing {}", args.path))?
+        {
+            data = decompressed;
+        }
+    }
+    if let Some(output) = &args.output {
+        fs::write(output, &data).with_context(|| format!("Writing file {}", display(output)))?;
     } else {
-        let mut partition = disc.open_partition_kind(PartitionKind::Data)?;
-        extract_partition(header, partition.as_mut(), &output_dir, is_wii, args.quiet)?;
+        io::stdout().lock().write_all(&data).context("Writing to standard output")?;
     }
     Ok(())
 }
 
+/// A file or directory resolved from a path, which may lead into nested U8 or RARC archives.
+///
+/// An archive is entered when more path components follow it, or when the path ends with a
+/// slash. For example, `Common.arc/layout/` lists the `layout` directory in `Common.arc`.
+struct ResolvedPath {
+    /// The innermost archive's (decompressed) data, or `None` for files on the disc.
+    archive: Option<Vec<u8>>,
+    /// The tree of the innermost file system.
+    tree: FstTree,
+    /// The resolved entry's index in `tree`.
+    index: usize,
+}
+
+impl ResolvedPath {
+    fn new(
+        partition: &mut dyn PartitionBase,
+        meta: &PartitionMeta,
+        path: &str,
+        is_wii: bool,
+    ) -> Result<Self> {
+        let tree = Fst::new(&meta.raw_fst)?.tree()?;
+        let mut resolved = Self { archive: None, tree, index: 0 };
+        let mut components = path.split('/').filter(|s| !s.is_empty()).peekable();
+        while let Some(name) = components.next() {
+            let Some(child) = resolved
+                .tree
+                .children(resolved.entry())
+                .find(|child| child.name.eq_ignore_ascii_case(name))
+            else {
+                return Err(nod::Error::Other(format!(
+                    "{}: {} not found",
+                    path.trim_end_matches('/'),
+                    name
+                )));
+            };
+            if child.node.is_file() && (components.peek().is_some() || path.ends_with('/')) {
+                let (node, child_path) = (child.node.clone(), child.path.clone());
+                let data = resolved.read_file(partition, &node).with_context(|| {
+                    format!(
+                        "Reading {} (offset {}, size {})",
+                        child_path,
+                        node.offset(is_wii),
+                        node.length()
+                    )
+                })?;
+                let data = decompress(&data)
+                    .with_context(|| format!("Decompressing {}", child_path))?
+                    .unwrap_or(data);
+                resolved.tree = archive_tree(&data)
+                    .map_err(|e| nod::Error::Other(format!("{}: {}", child_path, e)))?;
+                resolved.archive = Some(data);
+                resolved.index = 0;
+            } else {
+                resolved.index = child.index;
+            }
+        }
+        Ok(resolved)
+    }
+
+    fn entry(&self) -> &FstEntry { self.tree.get(self.index).unwrap() }
+
+    /// The resolved entry, or for directories, its contents.
+    fn entries(&self, recursive: bool) -> Vec<&FstEntry> {
+        let entry = self.entry();
+        if entry.node.is_file() {
+            vec![entry]
+        } else if recursive {
+            let end = entry.node.length() as usize;
+            self.tree.iter().filter(|e| e.index > entry.index && e.index < end).collect()
+        } else {
+            self.tree.children(entry).collect()
+        }
+    }
+
+    /// The path of an entry relative to the resolved entry, or its name if it's the resolved
+    /// entry itself.
+    fn relative_path<'a>(&self, entry: &'a FstEntry) -> &'a str {
+        let base = self.entry();
+        if entry.index == base.index {
+            &entry.name
+        } else if base.path.is_empty() {
+            &entry.path
+        } else {
+            &entry.path[base.path.len() + 1..]
+        }
+    }
+
+    /// Reads a file from the innermost file system.
+    fn read_file(&self, partition: &mut dyn PartitionBase, node: &Node) -> io::Result<Vec<u8>> {
+        if let Some(data) = &self.archive {
+            let start = node.offset(false) as usize;
+            return data.get(start..start + node.length() as usize).map(<[u8]>::to_vec).ok_or_else(
+                || io::Error::new(io::ErrorKind::UnexpectedEof, "File data out of bounds"),
+            );
+        }
+        let mut data = Vec::with_capacity(node.length() as usize);
+        partition.open_file(node)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+}
+
+/// Reads the file system of a U8 or RARC archive.
+///
+/// RARC directories are converted to FST nodes, with file offsets relative to the start of the
+/// archive like in U8 archives.
+fn archive_tree(data: &[u8]) -> Result<FstTree> {
+    if U8Archive::is_u8(data) {
+        return Ok(U8Archive::new(&mut Cursor::new(data))?.fst().tree()?);
+    } else if !Rarc::is_rarc(data) {
+        return Err(nod::Error::DiscFormat("not a U8 or RARC archive".to_string()));
+    }
+    Ok(Rarc::new(&mut Cursor::new(data))?.tree()?)
+}
+
 fn extract_partition(
     header: &DiscHeader,
     partition: &mut dyn PartitionBase,
     out_dir: &Path,
     is_wii: bool,
-    quiet: bool,
+    options: &ExtractOptions,
 ) -> Result<()> {
     let meta = partition.meta()?;
-    extract_sys_files(header, meta.as_ref(), out_dir, quiet)?;
+    extract_sys_files(header, meta.as_ref(), out_dir, options.elf, options.quiet)?;
+    let filter = &options.filter;
 
     // Extract FST
     let files_dir = out_dir.join("files");
@@ -590,28 +1579,71 @@ fn extract_partition(
         .with_context(|| format!("Creating directory {}", display(&files_dir)))?;
 
     let fst = Fst::new(&meta.raw_fst)?;
-    let mut path_segments = Vec::<(Cow<str>, usize)>::new();
-    for (idx, node, name) in fst.iter() {
-        // Remove ended path segments
-        let mut new_size = 0;
-        for (_, end) in path_segments.iter() {
-            if *end == idx {
-                break;
+    for issue in fst.validate(&meta.layout(partition.data_size())) {
+        log::warn!("FST: {}", issue);
+    }
+    for entry in fst.tree()?.iter() {
+        if entry.node.is_dir() {
+            if filter.is_empty() {
+                fs::create_dir_all(files_dir.join(&entry.path))
+                    .with_context(|| format!("Creating directory {}", entry.path))?;
+            }
+        } else if filter.is_match(&entry.path) {
+            if !filter.is_empty() {
+                // Only create directories containing matched files
+                if let Some(parent) = files_dir.join(&entry.path).parent() {
+                    fs::create_dir_all(parent)
+                        .with_context(|| format!("Creating directory {}", display(parent)))?;
+                }
             }
-            new_size += 1;
+            extract_node(&entry.node, partition, &files_dir, &entry.path, is_wii, options)?;
         }
-        path_segments.truncate(new_size);
-
-        // Add the new path segment
-        let end = if node.is_dir() { node.length() as usize } else { idx + 1 };
-        path_segments.push((name?, end));
+    }
+    Ok(())
+}
 
-        let path = path_segments.iter().map(|(name, _)| name.as_ref()).join("/");
-        if node.is_dir() {
-            fs::create_dir_all(files_dir.join(&path))
-                .with_context(|| format!("Creating directory {}", path))?;
+/// Extracts a file, or the contents of a directory or archive, resolved from `--path`.
+fn extract_resolved(
+    resolved: &ResolvedPath,
+    partition: &mut dyn PartitionBase,
+    out_dir: &Path,
+    is_wii: bool,
+    options: &ExtractOptions,
+) -> Result<()> {
+    let filter = &options.filter;
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("Creating directory {}", display(out_dir)))?;
+    for entry in resolved.entries(true) {
+        let path = resolved.relative_path(entry);
+        if entry.node.is_dir() {
+            if filter.is_empty() {
+                fs::create_dir_all(out_dir.join(path))
+                    .with_context(|| format!("Creating directory {}", path))?;
+            }
+            continue;
+        } else if !filter.is_match(path) {
+            continue;
+        }
+        if !filter.is_empty() {
+            if let Some(parent) = out_dir.join(path).parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Creating directory {}", display(parent)))?;
+            }
+        }
+        if resolved.archive.is_some() {
+            let mut data = resolved
+                .read_file(partition, &entry.node)
+                .with_context(|| format!("Reading {}", entry.path))?;
+            if options.decompress {
+                if let Some(decompressed) =
+                    decompress(&data).with_context(|| format!("Decompressing {}", entry.path))?
+                {
+                    data = decompressed;
+                }
+            }
+            extract_file(&data, &out_dir.join(path), options.quiet)?;
         } else {
-            extract_node(node, partition, &files_dir, &path, is_wii, quiet)?;
+            extract_node(&entry.node, partition, out_dir, path, is_wii, options)?;
         }
     }
     Ok(())
@@ -621,6 +1653,7 @@ fn extract_sys_files(
     header: &DiscHeader,
     data: &PartitionMeta,
     out_dir: &Path,
+    elf: bool,
     quiet: bool,
 ) -> Result<()> {
     let sys_dir = out_dir.join("sys");
@@ -631,6 +1664,9 @@ fn extract_sys_files(
     extract_file(data.raw_apploader.as_ref(), &sys_dir.join("apploader.img"), quiet)?;
     extract_file(data.raw_fst.as_ref(), &sys_dir.join("fst.bin"), quiet)?;
     extract_file(data.raw_dol.as_ref(), &sys_dir.join("main.dol"), quiet)?;
+    if elf {
+        extract_file(&data.dol()?.to_elf(), &sys_dir.join("main.elf"), quiet)?;
+    }
 
     // Wii files
     if header.is_wii() {
@@ -672,10 +1708,10 @@ fn extract_node(
     base_path: &Path,
     name: &str,
     is_wii: bool,
-    quiet: bool,
+    options: &ExtractOptions,
 ) -> Result<()> {
     let file_path = base_path.join(name);
-    if !quiet {
+    if !options.quiet {
         println!(
             "Extracting {} (size: {})",
             display(&file_path),
@@ -685,20 +1721,27 @@ fn extract_node(
     let file = File::create(&file_path)
         .with_context(|| format!("Creating file {}", display(&file_path)))?;
     let mut w = BufWriter::with_capacity(partition.ideal_buffer_size(), file);
-    let mut r = partition.open_file(node).with_context(|| {
+    let context = || {
         format!(
             "Opening file {} on disc for reading (offset {}, size {})",
             name,
             node.offset(is_wii),
             node.length()
         )
-    })?;
-    io::copy(&mut r, &mut w).with_context(|| format!("Extracting file {}", display(&file_path)))?;
+    };
+    if options.decompress {
+        let mut r = partition.open_file_decompressed(node).with_context(context)?;
+        io::copy(&mut r, &mut w)
+    } else {
+        let mut r = partition.open_file(node).with_context(context)?;
+        io::copy(&mut r, &mut w)
+    }
+    .with_context(|| format!("Extracting file {}", display(&file_path)))?;
     w.flush().with_context(|| format!("Flushing file {}", display(&file_path)))?;
     Ok(())
 }
 
-fn display(path: &Path) -> PathDisplay { PathDisplay { path } }
+fn display(path: &Path) -> PathDisplay<'_> { PathDisplay { path } }
 
 struct PathDisplay<'a> {
     path: &'a Path,